@@ -0,0 +1,115 @@
+//! Measures the memory of each folding step for several `blocks_per_step`, to compare with the
+//! `BYTES_PER_CONSTRAINT` estimate behind `FoldConfig::auto_chunk`.
+//!
+//! The heap is tracked by a counting global allocator installed in this benchmark only: for each
+//! `prove_step`, the peak of the live heap during the step, above the heap live before it. The
+//! peak RSS of the process (`VmHWM`, read from `/proc/self/status`) is reported alongside where
+//! available, on Linux; it never decreases, so it is the high-water mark so far, setup included.
+//!
+//! ```sh
+//! cargo bench --bench memory_per_step
+//! ```
+use folded_sha256::folded_sha256::config::{
+    constraints_per_block, FoldConfig, BYTES_PER_CONSTRAINT,
+};
+use folded_sha256::folded_sha256::main::{preprocess, prove_step_timed, NovaFor, H};
+use folded_sha256::folded_sha256::multi_block::{
+    multi_block_external_inputs, FoldedSha256MultiBlockFCircuit,
+};
+
+use ark_bn254::Fr;
+use ark_std::rand;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::FoldingScheme;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of blocks of the benchmarked input, a multiple of every `blocks_per_step` below.
+const NUM_BLOCKS: usize = 4;
+
+/// `System` allocator keeping track of the live heap and of its peak since the last `reset_peak`.
+struct CountingAllocator;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let live = LIVE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(live, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        LIVE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Resets the peak to the live heap, and returns the latter.
+fn reset_peak() -> usize {
+    let live = LIVE.load(Ordering::Relaxed);
+    PEAK.store(live, Ordering::Relaxed);
+    live
+}
+
+/// Peak resident set size of the process in bytes, `None` if `/proc` is not available.
+fn peak_rss() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kib: usize = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+fn mib(bytes: usize) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1 << 20) as f64)
+}
+
+fn main() {
+    let mut rng = rand::rngs::OsRng;
+    // the padding of 64 * NUM_BLOCKS - 9 bytes fills exactly NUM_BLOCKS blocks
+    let input = vec![0u8; 64 * NUM_BLOCKS - 9];
+    let config = FoldConfig::default();
+
+    println!(
+        "| {:>15} | {:>4} | {:>14} | {:>14} | {:>14} | {:>10} |",
+        "blocks_per_step", "step", "step heap", "estimate", "peak RSS", "time"
+    );
+    println!(
+        "|{:-<17}|{:-<6}|{:-<16}|{:-<16}|{:-<16}|{:-<12}|",
+        "", "", "", "", "", ""
+    );
+    for blocks_per_step in [1, 2, 4] {
+        let circuit = FoldedSha256MultiBlockFCircuit::<Fr>::new(blocks_per_step).unwrap();
+        let nova_params = preprocess(circuit, None, &mut rng).unwrap();
+        let z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        let mut folding_scheme =
+            NovaFor::<FoldedSha256MultiBlockFCircuit<Fr>>::init(&nova_params, circuit, z_0)
+                .unwrap();
+
+        let estimate = blocks_per_step * constraints_per_block() * BYTES_PER_CONSTRAINT;
+        for (i, external_inputs) in
+            multi_block_external_inputs(input.clone(), blocks_per_step).enumerate()
+        {
+            let before = reset_peak();
+            let elapsed =
+                prove_step_timed(&mut folding_scheme, external_inputs, &config, &mut rng).unwrap();
+            let step_heap = PEAK.load(Ordering::Relaxed) - before;
+            println!(
+                "| {:>15} | {:>4} | {:>14} | {:>14} | {:>14} | {:>10} |",
+                blocks_per_step,
+                i,
+                mib(step_heap),
+                mib(estimate),
+                peak_rss().map_or("-".to_string(), mib),
+                format!("{:.2?}", elapsed)
+            );
+        }
+    }
+}
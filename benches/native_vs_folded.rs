@@ -0,0 +1,120 @@
+//! Compares the time of hashing the same input with the crate's native compression chain (on
+//! vectors with `update_state_ref`, and allocation-free with `compress_block`), the `sha2` crate,
+//! and the full folded (proven) path.
+//!
+//! Proving dominates by orders of magnitude, so it only runs when `FOLDED_SHA256_BENCH_PROVING`
+//! is set:
+//!
+//! ```sh
+//! FOLDED_SHA256_BENCH_PROVING=1 cargo bench --bench native_vs_folded
+//! ```
+use folded_sha256::folded_sha256::config::FoldConfig;
+use folded_sha256::folded_sha256::main::{run, H};
+use folded_sha256::folded_sha256::utils::{
+    compress_block, sha256_msg_block_sequence, update_state_ref,
+};
+
+use ark_std::rand;
+use sha2::{Digest, Sha256};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const NATIVE_ITERATIONS: u32 = 100;
+
+fn time_native(input: &[u8]) -> Duration {
+    let start = Instant::now();
+    for _ in 0..NATIVE_ITERATIONS {
+        let mut state = H.to_vec();
+        for block in sha256_msg_block_sequence(black_box(input.to_vec())) {
+            state = update_state_ref(state, block.to_vec()).unwrap();
+        }
+        black_box(state);
+    }
+    start.elapsed() / NATIVE_ITERATIONS
+}
+
+// same chain as `time_native` on fixed-size arrays, without the per-block allocations
+fn time_native_arrays(input: &[u8]) -> Duration {
+    let blocks = sha256_msg_block_sequence(input.to_vec());
+    assert_eq!(
+        chain_arrays(&blocks).to_vec(),
+        blocks.iter().fold(H.to_vec(), |state, block| {
+            update_state_ref(state, block.to_vec()).unwrap()
+        })
+    );
+
+    let start = Instant::now();
+    for _ in 0..NATIVE_ITERATIONS {
+        black_box(chain_arrays(black_box(&blocks)));
+    }
+    start.elapsed() / NATIVE_ITERATIONS
+}
+
+fn chain_arrays(blocks: &[[u8; 64]]) -> [u32; 8] {
+    blocks
+        .iter()
+        .fold(H, |state, block| compress_block(&state, block))
+}
+
+fn time_sha2(input: &[u8]) -> Duration {
+    let start = Instant::now();
+    for _ in 0..NATIVE_ITERATIONS {
+        black_box(Sha256::digest(black_box(input)));
+    }
+    start.elapsed() / NATIVE_ITERATIONS
+}
+
+fn time_folded(input: &[u8]) -> Duration {
+    let start = Instant::now();
+    run(
+        input.to_vec(),
+        &FoldConfig::default(),
+        &mut rand::rngs::OsRng,
+    )
+    .unwrap();
+    start.elapsed()
+}
+
+fn main() {
+    let bench_proving = std::env::var_os("FOLDED_SHA256_BENCH_PROVING").is_some();
+
+    let mut rows = vec![];
+    for log_input_len in [6, 10, 14] {
+        let input = vec![0u8; 1 << log_input_len];
+        let native = time_native(&input);
+        let native_arrays = time_native_arrays(&input);
+        let sha2 = time_sha2(&input);
+        let folded = bench_proving.then(|| time_folded(&input));
+        rows.push((input.len(), native, native_arrays, sha2, folded));
+    }
+
+    println!(
+        "| {:>10} | {:>14} | {:>14} | {:>14} | {:>14} | {:>12} |",
+        "input (B)", "native", "native arrays", "sha2", "folded", "folded/sha2"
+    );
+    println!(
+        "|{:-<12}|{:-<16}|{:-<16}|{:-<16}|{:-<16}|{:-<14}|",
+        "", "", "", "", "", ""
+    );
+    for (len, native, native_arrays, sha2, folded) in rows {
+        let (folded, overhead) = match folded {
+            Some(folded) => (
+                format!("{:?}", folded),
+                format!("{:.0}x", folded.as_secs_f64() / sha2.as_secs_f64()),
+            ),
+            None => ("skipped".to_string(), "-".to_string()),
+        };
+        println!(
+            "| {:>10} | {:>14} | {:>14} | {:>14} | {:>14} | {:>12} |",
+            len,
+            format!("{:?}", native),
+            format!("{:?}", native_arrays),
+            format!("{:?}", sha2),
+            folded,
+            overhead
+        );
+    }
+    if !bench_proving {
+        println!("Set FOLDED_SHA256_BENCH_PROVING=1 to also time the folded proving.");
+    }
+}
@@ -1,26 +1,14 @@
-#![allow(non_snake_case)]
-use ark_groth16::Groth16;
-use folded_sha256::folded_sha256::main::H;
-use folded_sha256::folded_sha256::utils::sha256_msg_block_sequence;
+use folded_sha256::folded_sha256::config::{input_len_from_log, DeciderMode, MAX_INPUT_LEN_LOG};
+use folded_sha256::folded_sha256::digest::{format_digest, DigestFormat};
+use folded_sha256::folded_sha256::hasher::sha256;
+use folded_sha256::folded_sha256::main::prove_and_verify_with;
 
 use clap::{Arg, Command};
-use folded_sha256::folded_sha256::main::FoldedSha256FCircuit;
+use std::process;
 use std::time::Instant;
 
-use ark_bn254::{constraints::GVar, Bn254, Fr, G1Projective as G1};
-use ark_grumpkin::{constraints::GVar as GVar2, Projective as G2};
-
-use ark_std::rand;
-use folding_schemes::{
-    commitment::{kzg::KZG, pedersen::Pedersen},
-    folding::{
-        nova::{decider_eth::Decider as DeciderEth, Nova, PreprocessorParam},
-        traits::CommittedInstanceOps,
-    },
-    frontend::FCircuit,
-    transcript::poseidon::poseidon_canonical_config,
-    Decider, FoldingScheme,
-};
+use ark_std::rand::{self, CryptoRng, RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 
 fn main() {
     let cmd = Command::new("Nova-based SHA256 circuit proof generation and verification")
@@ -30,129 +18,102 @@ fn main() {
             .value_name("Log2 of the test input length")
             .default_value("6")
             .value_parser(clap::value_parser!(usize))
-            .long_help("Base 2 log of the test input length. For example, the value of 8 corresponds to 256 bytes of input. ")   
+            .long_help(format!("Base 2 log of the test input length, at most {}. For example, the value of 8 corresponds to 256 bytes of input. ", MAX_INPUT_LEN_LOG))
+    )
+    .arg(
+        Arg::new("seed")
+            .long("seed")
+            .value_name("Seed of the deterministic RNG")
+            .value_parser(clap::value_parser!(u64))
+            .long_help("Seed a ChaCha20 RNG to make the generated proofs reproducible. Defaults to OsRng when omitted.")
+    )
+    .arg(
+        Arg::new("format")
+            .long("format")
+            .value_name("Output format of the digest")
+            .default_value("hex")
+            .value_parser(["hex", "base64", "bytes"])
+            .long_help("Format used to print the digest recovered from the final folding state: hex, base64 or bytes.")
+    )
+    .arg(
+        Arg::new("decider")
+            .long("decider")
+            .value_name("Final proof")
+            .default_value("eth")
+            .value_parser(["eth", "ivc"])
+            .long_help("eth: Groth16-wrapped DeciderEth proof, verifiable on-chain. ivc: no Decider, the Nova IVC proof checked by the Nova verifier, without the Groth16 setup and proving.")
     )
     .after_help("This command generates a proof that the hash of 2^(input_log_len) zero bytes");
 
-    let initial_state = vec![
-        Fr::from(H[0]),
-        Fr::from(H[1]),
-        Fr::from(H[2]),
-        Fr::from(H[3]),
-        Fr::from(H[4]),
-        Fr::from(H[5]),
-        Fr::from(H[6]),
-        Fr::from(H[7]),
-    ];
-
     let m = cmd.get_matches();
     let log_input_len = *m.get_one::<usize>("input_len_log").unwrap();
-    let input_len = 1 << log_input_len;
+    let format: DigestFormat = m.get_one::<String>("format").unwrap().parse().unwrap();
+    let decider_mode: DeciderMode = m.get_one::<String>("decider").unwrap().parse().unwrap();
+
+    match m.get_one::<u64>("seed") {
+        Some(seed) => run(
+            log_input_len,
+            format,
+            decider_mode,
+            &mut ChaCha20Rng::seed_from_u64(*seed),
+        ),
+        None => run(log_input_len, format, decider_mode, &mut rand::rngs::OsRng),
+    }
+}
+
+fn run<R: RngCore + CryptoRng>(
+    log_input_len: usize,
+    format: DigestFormat,
+    decider_mode: DeciderMode,
+    rng: &mut R,
+) {
+    let input_len = input_len_from_log(log_input_len).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1)
+    });
     println!("Input Length: {:?}", input_len);
 
     println!("Nova-based SHA256 compression function iterations");
     println!("=========================================================");
 
-    let F_circuit = FoldedSha256FCircuit::<Fr>::new(()).unwrap();
-
-    let poseidon_config = poseidon_canonical_config::<Fr>();
-    let mut rng = rand::rngs::OsRng;
-
-    type N = Nova<
-        G1,
-        GVar,
-        G2,
-        GVar2,
-        FoldedSha256FCircuit<Fr>,
-        KZG<'static, Bn254>,
-        Pedersen<G2>,
-        false,
-    >;
-    pub type D = DeciderEth<
-        G1,
-        GVar,
-        G2,
-        GVar2,
-        FoldedSha256FCircuit<Fr>,
-        KZG<'static, Bn254>,
-        Pedersen<G2>,
-        Groth16<Bn254>,
-        N,
-    >;
-
-    let param_gen_timer = Instant::now();
-    println!("Prepare Nova ProverParams & VerifierParams");
-    let nova_preprocess_params = PreprocessorParam::new(poseidon_config, F_circuit);
-    let nova_params = N::preprocess(&mut rng, &nova_preprocess_params).unwrap();
-
-    println!("Initialize FoldingScheme");
-    let mut folding_scheme = N::init(&nova_params, F_circuit, initial_state.clone()).unwrap();
-    let param_gen_time = param_gen_timer.elapsed();
-    println!("PublicParams::setup, took {:?} ", param_gen_time);
-
-    // prepare the Decider prover & verifier params
-    println!("Generating the Decider Public Parameters");
-    let preprocess_timer = Instant::now();
-    let (decider_pp, decider_vp) =
-        D::preprocess(&mut rng, nova_params, folding_scheme.clone()).unwrap();
-    println!(
-        "Nova::Decider preprocess, took: {:?}",
-        preprocess_timer.elapsed()
-    );
-
-    let input: Vec<u8> = vec![0u8; input_len]; // All the input bytes are zero
-    let block_sequence = sha256_msg_block_sequence(input);
-
-    // produce a recursive SNARK
-    println!("Generating a RecursiveSNARK...");
-    let proof_gen_timer = Instant::now();
-    // compute a step of the IVC
-    for (i, external_inputs_at_step) in block_sequence.iter().enumerate() {
-        let step_start = Instant::now();
-        folding_scheme
-            .prove_step(
-                rng,
-                external_inputs_at_step
-                    .clone()
-                    .iter()
-                    .map(|x| Fr::from(x.clone()))
-                    .collect(),
-                None,
-            )
-            .unwrap();
-        println!("Nova::prove_step {}: {:?}", i, step_start.elapsed());
+    let input = vec![0u8; input_len];
+    let start = Instant::now();
+    let report = prove_and_verify_with(&input, decider_mode, rng).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1)
+    });
+    let total = start.elapsed();
+
+    println!("Nova preprocess took {:?}", report.fold.preprocess);
+    for (i, elapsed) in report.fold.steps.iter().enumerate() {
+        println!("Nova::prove_step {}: {:?}", i, elapsed);
+    }
+    if let Some(elapsed) = report.decider_preprocess {
+        println!("Nova::Decider preprocess took {:?}", elapsed);
+    }
+    if let Some(elapsed) = report.decider_prove {
+        println!("Nova::Decider prove took {:?}", elapsed);
+    }
+    if let Some(sizes) = report.sizes {
+        println!(
+            "Sizes: proof {} bytes, verifier key {} bytes, public inputs {} bytes, total {} bytes",
+            sizes.proof,
+            sizes.verifier_key,
+            sizes.public_inputs,
+            sizes.total()
+        );
     }
     println!(
-        "Total time taken by RecursiveSNARK::prove_steps: {:?}",
-        proof_gen_timer.elapsed()
+        "{:?} proof verification: {} (verification took {:?}, {:?} in total)",
+        decider_mode, report.verified, report.verify, total
     );
+    assert!(report.verified);
 
-    // verify the recursive SNARK
-    // println!("Run the Nova's IVC verifier");
-    // let start = Instant::now();
-    // let ivc_proof = folding_scheme.ivc_proof();
-    // N::verify(
-    //     nova_params.1, // Nova's verifier params
-    //     ivc_proof,
-    // )
-    // .unwrap();
-    // println!("RecursiveSNARK::verify took {:?}", start.elapsed());
-
-    println!("Run the Nova's Decider Prover");
-    let start = Instant::now();
-    let proof = D::prove(rng, decider_pp, folding_scheme.clone()).unwrap();
-    println!("generated Decider proof: {:?}", start.elapsed());
-
-    let verified = D::verify(
-        decider_vp.clone(),
-        folding_scheme.i,
-        folding_scheme.z_0.clone(),
-        folding_scheme.z_i.clone(),
-        &folding_scheme.U_i.get_commitments(),
-        &folding_scheme.u_i.get_commitments(),
-        &proof,
-    )
-    .unwrap();
-    assert!(verified);
-    println!("Decider proof verification: {}", verified);
+    // the digest recovered from the proven final state must be the native hash of the input
+    assert_eq!(
+        report.digest,
+        sha256(&input),
+        "the folded digest does not match the native SHA-256 of the input"
+    );
+    println!("Digest: {}", format_digest(&report.digest, format));
 }
@@ -0,0 +1,154 @@
+//! Absorption of field elements into a SHA-256 transcript, e.g. for a Fiat-Shamir transform
+//! whose transcript is SHA-256 based.
+//!
+//! Each element is encoded as the 32-byte big-endian encoding of its canonical representative
+//! (the integer in `[0, p)`, not its Montgomery form), so the field must have at most 256 bits.
+//! The absorbed message is the concatenation of the encodings, i.e.
+//! `SHA256(be(e_0) || be(e_1) || ...)`, padded as usual. Natively the padded blocks can be folded
+//! with `main::prove_from_blocks`; in a circuit, `absorb_field_element_vars` builds the same
+//! blocks from the field variables.
+use crate::folded_sha256::digest::Digest;
+use crate::folded_sha256::hasher::sha256;
+use crate::folded_sha256::utils::{sha256_msg_block_sequence, BLOCK_LENGTH_BYTES};
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{convert::ToBytesGadget, fields::fp::FpVar, uint8::UInt8};
+use ark_relations::r1cs::SynthesisError;
+
+/// Length of the encoding of one field element.
+pub const FIELD_ELEMENT_BYTES: usize = 32;
+
+/// The 32-byte big-endian encoding of the canonical representative of `x`.
+pub fn field_element_to_bytes_be<F: PrimeField>(x: &F) -> [u8; FIELD_ELEMENT_BYTES] {
+    assert!(F::MODULUS_BIT_SIZE as usize <= 8 * FIELD_ELEMENT_BYTES);
+    let bytes = x.into_bigint().to_bytes_be();
+    let mut encoding = [0u8; FIELD_ELEMENT_BYTES];
+    encoding[FIELD_ELEMENT_BYTES - bytes.len()..].copy_from_slice(&bytes);
+    encoding
+}
+
+/// The padded blocks of the message `be(elems[0]) || be(elems[1]) || ...`.
+pub fn absorb_field_elements<F: PrimeField>(elems: &[F]) -> Vec<[u8; BLOCK_LENGTH_BYTES]> {
+    let message = elems.iter().flat_map(field_element_to_bytes_be).collect();
+    sha256_msg_block_sequence(message)
+}
+
+/// Length-bound hash of a vector of field elements:
+/// `SHA256(be32(elems.len()) || be(elems[0]) || be(elems[1]) || ...)`.
+///
+/// The 4-byte big-endian element count makes the encoding prefix-free, so vectors of different
+/// lengths never share a preimage even when one's encoding is a prefix of the other's, and the
+/// digest can serve as a transcript or commitment of the vector.
+pub fn hash_field_vec<F: PrimeField>(elems: &[F]) -> Digest {
+    let count = u32::try_from(elems.len()).expect("more than 2^32 - 1 elements");
+    let mut message = Vec::with_capacity(4 + FIELD_ELEMENT_BYTES * elems.len());
+    message.extend(count.to_be_bytes());
+    message.extend(elems.iter().flat_map(field_element_to_bytes_be));
+    sha256(&message)
+}
+
+/// In-circuit `absorb_field_elements`: the padded blocks, of 64 bytes each, of the encodings of
+/// `elems`, to be compressed with e.g. `circuit::one_compression_round`.
+///
+/// The bytes of each element are those of its canonical bit decomposition, so a prover cannot
+/// absorb `x + p` in place of `x`. The padding only depends on the number of elements, it is made
+/// of constants.
+pub fn absorb_field_element_vars<F: PrimeField>(
+    elems: &[FpVar<F>],
+) -> Result<Vec<Vec<UInt8<F>>>, SynthesisError> {
+    assert!(F::MODULUS_BIT_SIZE as usize <= 8 * FIELD_ELEMENT_BYTES);
+    let mut message = Vec::with_capacity(FIELD_ELEMENT_BYTES * elems.len());
+    for x in elems {
+        let mut bytes = x.to_bytes_le()?;
+        bytes.resize(FIELD_ELEMENT_BYTES, UInt8::constant(0));
+        bytes.reverse();
+        message.extend(bytes);
+    }
+
+    // the padding only depends on the length of the message
+    let mut padded = sha256_msg_block_sequence(vec![0u8; message.len()]).concat();
+    let padding = padded.split_off(message.len());
+    message.extend(UInt8::constant_vec(&padding));
+
+    Ok(message
+        .chunks(BLOCK_LENGTH_BYTES)
+        .map(|block| block.to_vec())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::circuit::{one_compression_round, words_to_bytes_be};
+    use crate::folded_sha256::constants::sha256::H;
+    use crate::folded_sha256::utils::{compress_block, finalize};
+    use ark_bn254::Fr;
+    use ark_r1cs_std::{alloc::AllocVar, uint32::UInt32, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn elems() -> [Fr; 2] {
+        [Fr::from(0x0102030405060708u64), -Fr::from(1u64)]
+    }
+
+    #[test]
+    fn test_absorb_field_elements() {
+        let elems = elems();
+        let mut message = field_element_to_bytes_be(&elems[0]).to_vec();
+        message.extend(field_element_to_bytes_be(&elems[1]));
+        assert_eq!(
+            &message[24..32],
+            &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]
+        );
+        assert_eq!(Fr::from_be_bytes_mod_order(&message[32..]), elems[1]);
+
+        let blocks = absorb_field_elements(&elems);
+        assert_eq!(blocks.len(), 2);
+        let mut state = H;
+        for block in &blocks {
+            state = compress_block(&state, block);
+        }
+        assert_eq!(finalize(state.to_vec()), sha256(&message).0);
+    }
+
+    #[test]
+    fn test_hash_field_vec_binds_the_length() {
+        assert_eq!(hash_field_vec::<Fr>(&[]), sha256(&[0, 0, 0, 0]));
+
+        let elems = elems();
+        let mut message = vec![0, 0, 0, 2];
+        message.extend(field_element_to_bytes_be(&elems[0]));
+        message.extend(field_element_to_bytes_be(&elems[1]));
+        assert_eq!(hash_field_vec(&elems), sha256(&message));
+
+        // the same element bytes, split into vectors of different lengths
+        let zeros = [Fr::from(0u64); 2];
+        assert_ne!(hash_field_vec(&zeros[..1]), hash_field_vec(&zeros));
+        assert_ne!(hash_field_vec(&elems[..1]), hash_field_vec(&elems));
+    }
+
+    #[test]
+    fn test_absorb_field_element_vars_matches_native() {
+        let elems = elems();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let elem_vars = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(elems.to_vec())).unwrap();
+
+        let blocks = absorb_field_element_vars(&elem_vars).unwrap();
+        let native_blocks = absorb_field_elements(&elems);
+        assert_eq!(blocks.len(), native_blocks.len());
+        for (block, native_block) in blocks.iter().zip(&native_blocks) {
+            assert_eq!(block.value().unwrap(), native_block.to_vec());
+        }
+
+        let mut state: Vec<UInt32<Fr>> = H.iter().map(|&x| UInt32::constant(x)).collect();
+        for block in &blocks {
+            state = one_compression_round(&mut state, block).unwrap();
+        }
+        let mut message = field_element_to_bytes_be(&elems[0]).to_vec();
+        message.extend(field_element_to_bytes_be(&elems[1]));
+        assert_eq!(
+            words_to_bytes_be(&state).unwrap().value().unwrap(),
+            sha256(&message).0
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
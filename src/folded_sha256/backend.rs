@@ -0,0 +1,163 @@
+//! Nova instantiations selectable at runtime.
+//!
+//! `main::N` commits to the primary circuit witnesses with KZG, which has short commitments and
+//! is what the Decider needs, but requires a trusted setup. With Pedersen commitments there is no
+//! trusted setup, at the cost of a linear verifier and no Decider. The two instantiations are
+//! different types, so `FoldingBackend` dispatches over them for tools choosing the commitment
+//! scheme from their configuration.
+#![allow(non_snake_case)]
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{preprocess, NovaFor, NovaParamsFor};
+
+use ark_bn254::{constraints::GVar, Fr, G1Projective as Projective};
+use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::commitment::pedersen::Pedersen;
+use folding_schemes::folding::nova::{Nova, PreprocessorParam};
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+use folding_schemes::FoldingScheme;
+
+/// Nova committing to the primary circuit witnesses with Pedersen instead of KZG.
+pub type NovaPedersenFor<FC> = Nova<
+    Projective,
+    GVar,
+    Projective2,
+    GVar2,
+    FC,
+    Pedersen<Projective>,
+    Pedersen<Projective2>,
+    false,
+>;
+
+pub type NovaPedersenParamsFor<FC> = (
+    <NovaPedersenFor<FC> as FoldingScheme<Projective, Projective2, FC>>::ProverParam,
+    <NovaPedersenFor<FC> as FoldingScheme<Projective, Projective2, FC>>::VerifierParam,
+);
+
+/// Commitment scheme of the primary circuit witnesses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitmentKind {
+    Kzg,
+    Pedersen,
+}
+
+/// A folding of `FC` with the commitment scheme chosen at runtime, along with its parameters.
+pub enum FoldingBackend<FC: FCircuit<Fr>> {
+    Kzg {
+        folding_scheme: NovaFor<FC>,
+        params: NovaParamsFor<FC>,
+    },
+    Pedersen {
+        folding_scheme: NovaPedersenFor<FC>,
+        params: NovaPedersenParamsFor<FC>,
+    },
+}
+
+impl<FC: FCircuit<Fr>> FoldingBackend<FC> {
+    /// Runs the Nova setup of `F_circuit` for the `kind` commitment scheme and initializes the
+    /// folding from `z_0`.
+    pub fn init<R: RngCore + CryptoRng>(
+        kind: CommitmentKind,
+        F_circuit: FC,
+        z_0: Vec<Fr>,
+        rng: &mut R,
+    ) -> Result<Self, FoldedSha256Error> {
+        match kind {
+            CommitmentKind::Kzg => {
+                let params = preprocess(F_circuit.clone(), None, rng)?;
+                let folding_scheme = NovaFor::<FC>::init(&params, F_circuit, z_0)?;
+                Ok(FoldingBackend::Kzg {
+                    folding_scheme,
+                    params,
+                })
+            }
+            CommitmentKind::Pedersen => {
+                let preprocess_params =
+                    PreprocessorParam::new(poseidon_canonical_config::<Fr>(), F_circuit.clone());
+                let params = NovaPedersenFor::<FC>::preprocess(&mut *rng, &preprocess_params)
+                    .map_err(FoldedSha256Error::Setup)?;
+                let folding_scheme = NovaPedersenFor::<FC>::init(&params, F_circuit, z_0)?;
+                Ok(FoldingBackend::Pedersen {
+                    folding_scheme,
+                    params,
+                })
+            }
+        }
+    }
+
+    pub fn kind(&self) -> CommitmentKind {
+        match self {
+            FoldingBackend::Kzg { .. } => CommitmentKind::Kzg,
+            FoldingBackend::Pedersen { .. } => CommitmentKind::Pedersen,
+        }
+    }
+
+    /// Folds the next step with `external_inputs`.
+    pub fn prove_step<R: RngCore + CryptoRng>(
+        &mut self,
+        external_inputs: Vec<Fr>,
+        rng: &mut R,
+    ) -> Result<(), FoldedSha256Error> {
+        match self {
+            FoldingBackend::Kzg { folding_scheme, .. } => {
+                folding_scheme.prove_step(rng, external_inputs, None)?
+            }
+            FoldingBackend::Pedersen { folding_scheme, .. } => {
+                folding_scheme.prove_step(rng, external_inputs, None)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Current state `z_i` of the folding.
+    pub fn state(&self) -> Vec<Fr> {
+        match self {
+            FoldingBackend::Kzg { folding_scheme, .. } => folding_scheme.state(),
+            FoldingBackend::Pedersen { folding_scheme, .. } => folding_scheme.state(),
+        }
+    }
+
+    /// Verifies the IVC proof of the steps folded so far.
+    pub fn verify(&self) -> Result<(), FoldedSha256Error> {
+        match self {
+            FoldingBackend::Kzg {
+                folding_scheme,
+                params: (_, vp),
+            } => NovaFor::<FC>::verify(vp.clone(), folding_scheme.ivc_proof())?,
+            FoldingBackend::Pedersen {
+                folding_scheme,
+                params: (_, vp),
+            } => NovaPedersenFor::<FC>::verify(vp.clone(), folding_scheme.ivc_proof())?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::digest::digest_from_state;
+    use crate::folded_sha256::hasher::sha256;
+    use crate::folded_sha256::main::FoldedSha256FCircuit;
+    use crate::folded_sha256::utils::BlockExternalInputs;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_both_backends_prove_abc() {
+        for kind in [CommitmentKind::Kzg, CommitmentKind::Pedersen] {
+            let mut rng = ChaCha20Rng::seed_from_u64(0);
+            let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+            let mut backend =
+                FoldingBackend::init(kind, F_circuit, F_circuit.initial_state(), &mut rng).unwrap();
+            assert_eq!(backend.kind(), kind);
+
+            for external_inputs in BlockExternalInputs::<Fr, _>::new(b"abc".to_vec()) {
+                backend.prove_step(external_inputs, &mut rng).unwrap();
+            }
+            assert_eq!(digest_from_state(&backend.state()), sha256(b"abc"));
+            assert!(backend.verify().is_ok());
+        }
+    }
+}
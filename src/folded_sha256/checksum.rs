@@ -0,0 +1,201 @@
+//! SHA-256 folding with a running XOR checksum of the input blocks in the state.
+//!
+//! The checksum is a cheap integrity tag, not a commitment: it is linear, so anyone can find
+//! other inputs with the same checksum. It is only meaningful alongside the digest, e.g. for a
+//! protocol that wants to compare a tag without recomputing a hash.
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit::compression_round_from_words;
+use crate::folded_sha256::error::violation;
+use crate::folded_sha256::layout::{ByteLayout, ExternalInputsLayout};
+use crate::folded_sha256::main::{bigint_to_u32, FoldedSha256FCircuit, STATE_LEN};
+use crate::folded_sha256::utils::{CompressionOptions, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::Fr;
+use ark_r1cs_std::{fields::fp::FpVar, uint32::UInt32};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::Error;
+
+/// Number of 32-bit words of the checksum.
+pub const CHECKSUM_WORDS: usize = 8;
+
+/// Contribution of a block to the checksum: the XOR of its two 256-bit halves, as 8 big-endian
+/// words.
+fn block_checksum(block: &[u8; BLOCK_LENGTH_BYTES]) -> [u32; CHECKSUM_WORDS] {
+    let word = |j: usize| u32::from_be_bytes(block[4 * j..4 * j + 4].try_into().unwrap());
+    core::array::from_fn(|j| word(j) ^ word(j + CHECKSUM_WORDS))
+}
+
+/// The 256-bit XOR checksum of `blocks`: the XOR of the two halves of every block, i.e. the XOR
+/// of all the 32-byte chunks of the padded message.
+pub fn xor_checksum<'a>(
+    blocks: impl IntoIterator<Item = &'a [u8; BLOCK_LENGTH_BYTES]>,
+) -> [u32; CHECKSUM_WORDS] {
+    let mut checksum = [0u32; CHECKSUM_WORDS];
+    for block in blocks {
+        for (x, w) in checksum.iter_mut().zip(block_checksum(block)) {
+            *x ^= w;
+        }
+    }
+    checksum
+}
+
+/// SHA-256 folding circuit which, alongside the compression, XORs each block into a running
+/// checksum.
+///
+/// The state is `z_i = [H_0, ..., H_7, X_0, ..., X_7]`, the checksum words `X_j` starting at 0,
+/// so that the final state holds both the digest and `xor_checksum` of the padded blocks. The
+/// external inputs are the 64 bytes of the block, as for `FoldedSha256FCircuit`, and are
+/// decoded once for both. The XOR costs a few hundred constraints per step, the compression
+/// tens of thousands.
+#[derive(Clone, Copy, Debug)]
+pub struct FoldedSha256ChecksumFCircuit;
+
+impl FCircuit<Fr> for FoldedSha256ChecksumFCircuit {
+    type Params = ();
+
+    fn new(_params: Self::Params) -> Result<Self, Error> {
+        Ok(Self)
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + CHECKSUM_WORDS
+    }
+    fn external_inputs_len(&self) -> usize {
+        ByteLayout::len()
+    }
+
+    fn step_native(
+        &self,
+        i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let mut z_i1 = FoldedSha256FCircuit::<Fr>::sha256().step_native(
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            external_inputs.clone(),
+        )?;
+
+        let block: [u8; BLOCK_LENGTH_BYTES] = ByteLayout::decode(&external_inputs)
+            .map_err(|e| Error::Other(e.to_string()))?
+            .try_into()
+            .unwrap();
+        for (x, w) in z_i[STATE_LEN..].iter().zip(block_checksum(&block)) {
+            z_i1.push(Fr::from(bigint_to_u32(*x) ^ w));
+        }
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        _cs: ConstraintSystemRef<Fr>,
+        _i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        if z_i.len() != self.state_len() || external_inputs.len() != self.external_inputs_len() {
+            return violation(SynthesisError::Unsatisfiable);
+        }
+        // the state words are outputs of the previous step, hence already 32-bit
+        let words = z_i
+            .iter()
+            .map(|x| Ok(UInt32::from_fp(x)?.0))
+            .collect::<Result<Vec<UInt32<Fr>>, SynthesisError>>()?;
+        let (state, checksum) = words.split_at(STATE_LEN);
+
+        let block_words = ByteLayout::decode_words_in_circuit(&external_inputs)?;
+        let h = compression_round_from_words(state, &block_words, &CompressionOptions::default())?;
+
+        let mut z_i1 = h
+            .iter()
+            .map(|x| x.to_fp())
+            .collect::<Result<Vec<FpVar<Fr>>, SynthesisError>>()?;
+        for (j, x) in checksum.iter().enumerate() {
+            let updated = x ^ &(&block_words[j] ^ &block_words[j + CHECKSUM_WORDS]);
+            z_i1.push(updated.to_fp()?);
+        }
+        Ok(z_i1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::config::FoldConfig;
+    use crate::folded_sha256::digest::digest_from_state;
+    use crate::folded_sha256::hasher::sha256;
+    use crate::folded_sha256::main::{fold, H};
+    use crate::folded_sha256::utils::{sha256_msg_block_sequence, BlockExternalInputs};
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn initial_state() -> Vec<Fr> {
+        let mut z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        z_0.extend([Fr::from(0u32); CHECKSUM_WORDS]);
+        z_0
+    }
+
+    /// Independent checksum: the XOR of all the 32-byte chunks of the padded message.
+    fn chunks_xor(input: &[u8]) -> Vec<Fr> {
+        let mut checksum = [0u8; 32];
+        for block in sha256_msg_block_sequence(input.to_vec()) {
+            for chunk in block.chunks(32) {
+                for (x, b) in checksum.iter_mut().zip(chunk) {
+                    *x ^= b;
+                }
+            }
+        }
+        checksum
+            .chunks(4)
+            .map(|word| Fr::from(u32::from_be_bytes(word.try_into().unwrap())))
+            .collect()
+    }
+
+    #[test]
+    fn test_checksum_f_circuit() {
+        let input: Vec<u8> = (0..150).map(|i| (i * 7) as u8).collect();
+        let circuit = FoldedSha256ChecksumFCircuit;
+
+        let mut z_i = initial_state();
+        for (i, block) in BlockExternalInputs::<Fr, _>::new(input.clone()).enumerate() {
+            let z_i1 = circuit.step_native(i, z_i.clone(), block.clone()).unwrap();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+            assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+            assert!(cs.is_satisfied().unwrap());
+
+            z_i = z_i1;
+        }
+
+        assert_eq!(digest_from_state(&z_i[..STATE_LEN]), sha256(&input));
+        assert_eq!(z_i[STATE_LEN..].to_vec(), chunks_xor(&input));
+        let expected: Vec<Fr> = xor_checksum(&sha256_msg_block_sequence(input))
+            .iter()
+            .map(|&x| Fr::from(x))
+            .collect();
+        assert_eq!(z_i[STATE_LEN..].to_vec(), expected);
+    }
+
+    #[test]
+    fn test_fold_with_checksum() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, _) = fold(
+            FoldedSha256ChecksumFCircuit,
+            initial_state(),
+            BlockExternalInputs::<Fr, _>::new(b"abc".to_vec()),
+            &FoldConfig::default(),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(folding_scheme.z_i[STATE_LEN..].to_vec(), chunks_xor(b"abc"));
+    }
+}
@@ -1,8 +1,79 @@
-use crate::folded_sha256::utils;
+use crate::folded_sha256::profile::{end_compression, Mark, Phase};
+use crate::folded_sha256::schedule::{expand_schedule, small_sigma0, small_sigma1};
+use crate::folded_sha256::utils::{self, CompressionOptions, Endianness};
 use ark_ff::PrimeField;
-use ark_r1cs_std::{uint32::UInt32, uint8::UInt8};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    convert::ToBytesGadget,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    uint32::UInt32,
+    uint8::UInt8,
+    R1CSVar,
+};
 use ark_relations::r1cs::SynthesisError;
 
+/// Converts external inputs to bytes, enforcing that each of them is the canonical encoding of a
+/// byte.
+///
+/// `UInt8::from_fp` decomposes the input into its canonical (< modulus) bits but only keeps the 8
+/// least significant ones, so the remaining ones are enforced to be zero, i.e. the input is < 256.
+pub fn bytes_from_fp<ConstraintF: PrimeField>(
+    vars: &[FpVar<ConstraintF>],
+) -> Result<Vec<UInt8<ConstraintF>>, SynthesisError> {
+    vars.iter()
+        .map(|x| {
+            let (byte, rest) = UInt8::from_fp(x)?;
+            rest.enforce_equal(&FpVar::zero())?;
+            Ok(byte)
+        })
+        .collect()
+}
+
+/// Serializes the words to bytes in big-endian order, the inverse of the message loading in
+/// `one_compression_round`.
+pub fn words_to_bytes_be<ConstraintF: PrimeField>(
+    words: &[UInt32<ConstraintF>],
+) -> Result<Vec<UInt8<ConstraintF>>, SynthesisError> {
+    let mut bytes = Vec::with_capacity(4 * words.len());
+    for word in words {
+        let mut word_bytes = word.to_bytes_le()?;
+        word_bytes.reverse();
+        bytes.extend(word_bytes);
+    }
+    Ok(bytes)
+}
+
+/// Whether the 8 big-endian words `digest` are one of the digests of `set`, which are constants
+/// of the circuit. Costs a comparison of 8 words per element of `set`, and reveals nothing about
+/// which element matched beyond the returned bit.
+pub fn digest_in_set<ConstraintF: PrimeField>(
+    digest: &[UInt32<ConstraintF>],
+    set: &[[u8; 32]],
+) -> Result<Boolean<ConstraintF>, SynthesisError> {
+    assert_eq!(digest.len(), 8);
+    let mut found = Boolean::FALSE;
+    for element in set {
+        let mut equal = Boolean::TRUE;
+        for (x, chunk) in digest.iter().zip(element.chunks(4)) {
+            let word = UInt32::constant(u32::from_be_bytes(chunk.try_into().unwrap()));
+            equal &= x.is_eq(&word)?;
+        }
+        found |= equal;
+    }
+    Ok(found)
+}
+
+/// Enforces, when `condition` is true, that `digest` is one of `set`, see `digest_in_set`.
+pub fn enforce_digest_in_set<ConstraintF: PrimeField>(
+    digest: &[UInt32<ConstraintF>],
+    set: &[[u8; 32]],
+    condition: &Boolean<ConstraintF>,
+) -> Result<(), SynthesisError> {
+    digest_in_set(digest, set)?.conditional_enforce_equal(&Boolean::TRUE, condition)
+}
+
 /// Updates the state of the SHA-256 compression function.
 ///
 /// This function performs one round of the SHA-256 compression algorithm,
@@ -21,44 +92,154 @@ use ark_relations::r1cs::SynthesisError;
 pub fn one_compression_round<ConstraintF: PrimeField>(
     state: &mut Vec<UInt32<ConstraintF>>,
     data: &Vec<UInt8<ConstraintF>>,
+) -> Result<Vec<UInt32<ConstraintF>>, SynthesisError> {
+    compression_round_with_options(state, data, &CompressionOptions::default())
+}
+
+/// Same as `one_compression_round`, with the non-standard `options` applied. See
+/// `utils::CompressionOptions` for the security caveats of each option.
+pub fn compression_round_with_options<ConstraintF: PrimeField>(
+    state: &mut Vec<UInt32<ConstraintF>>,
+    data: &Vec<UInt8<ConstraintF>>,
+    options: &CompressionOptions,
 ) -> Result<Vec<UInt32<ConstraintF>>, SynthesisError> {
     assert_eq!(state.len(), 8);
     assert_eq!(data.len(), 64);
 
-    let mut w = vec![UInt32::constant(0); 64];
-    for (word, chunk) in w.iter_mut().zip(data.chunks(4)) {
-        *word = UInt32::from_bytes_be(chunk)?;
+    let w = load_block_words(data, options.word_endianness)?;
+    compression_round_from_words(state, &w[..16], options)
+}
+
+/// Same as `compression_round_with_options`, with the block given as its 16 big-endian 32-bit
+/// words instead of its 64 bytes, e.g. when the external inputs are packed as words
+/// (`layout::WordLayout`) and can be decoded straight to `UInt32`s.
+pub fn compression_round_from_words<ConstraintF: PrimeField>(
+    state: &[UInt32<ConstraintF>],
+    block_words: &[UInt32<ConstraintF>],
+    options: &CompressionOptions,
+) -> Result<Vec<UInt32<ConstraintF>>, SynthesisError> {
+    assert_eq!(state.len(), 8);
+    assert_eq!(block_words.len(), 16);
+
+    let mut mark = Mark::new(state.cs().or(block_words.cs()));
+    let w = expand_schedule(block_words)?;
+    mark.record(Phase::Schedule);
+
+    compress_with_schedule(state, &w, options)
+}
+
+/// Same as `compression_round_with_options`, but the expanded message words `w[16..64]` are
+/// allocated as witnesses and checked against the schedule recurrence, instead of being computed
+/// symbolically.
+///
+/// The witnessed words are taken from `schedule_hint` (e.g. the schedule computed by the native
+/// step), or computed natively from the values of `data` when `None`. Each of them is checked by
+/// a single linear equation `w[i] + 2^32 * carry = w[i-16] + σ0 + w[i-7] + σ1` with a 2-bit
+/// witnessed `carry`, in place of the bit decomposition of the modular sum. Since the hinted
+/// words still have to be range-checked, the saving is small: `test_schedule_hint_constraints`
+/// reports both constraint counts.
+pub fn compression_round_with_schedule_hint<ConstraintF: PrimeField>(
+    state: &mut Vec<UInt32<ConstraintF>>,
+    data: &Vec<UInt8<ConstraintF>>,
+    schedule_hint: Option<&[u32; 64]>,
+    options: &CompressionOptions,
+) -> Result<Vec<UInt32<ConstraintF>>, SynthesisError> {
+    assert_eq!(state.len(), 8);
+    assert_eq!(data.len(), 64);
+
+    let cs = data.cs();
+    if cs.is_none() {
+        // constant block, there is nothing to witness
+        return compression_round_with_options(state, data, options);
     }
 
+    let hint: Option<[u32; 64]> = match schedule_hint {
+        Some(hint) => Some(*hint),
+        None => data.value().ok().and_then(|bytes| {
+            utils::message_schedule_with_endianness(&bytes, options.word_endianness).ok()
+        }),
+    };
+
+    let mut mark = Mark::new(cs.clone());
+    let mut w = load_block_words(data, options.word_endianness)?;
     for i in 16..64 {
-        let s0 = {
-            let x1 = w[i - 15].rotate_right(7);
-            let x2 = w[i - 15].rotate_right(18);
-            let x3 = &w[i - 15] >> 3u8;
-            x1 ^ &x2 ^ &x3
-        };
-        let s1 = {
-            let x1 = w[i - 2].rotate_right(17);
-            let x2 = w[i - 2].rotate_right(19);
-            let x3 = &w[i - 2] >> 10u8;
-            x1 ^ &x2 ^ &x3
+        let s0 = small_sigma0(&w[i - 15]);
+        let s1 = small_sigma1(&w[i - 2]);
+
+        let word = UInt32::new_witness(cs.clone(), || {
+            hint.map(|hint| hint[i])
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let carry = hint.map(|hint| {
+            let sum = hint[i - 16] as u64
+                + small_sigma0(&hint[i - 15]) as u64
+                + hint[i - 7] as u64
+                + small_sigma1(&hint[i - 2]) as u64;
+            sum >> 32
+        });
+        let carry_bits = (0..2)
+            .map(|k| {
+                Boolean::new_witness(cs.clone(), || {
+                    carry
+                        .map(|carry| (carry >> k) & 1 == 1)
+                        .ok_or(SynthesisError::AssignmentMissing)
+                })
+            })
+            .collect::<Result<Vec<Boolean<ConstraintF>>, SynthesisError>>()?;
+
+        let lhs =
+            word.to_fp()? + Boolean::le_bits_to_fp(&carry_bits)? * ConstraintF::from(1u64 << 32);
+        let rhs = w[i - 16].to_fp()? + s0.to_fp()? + w[i - 7].to_fp()? + s1.to_fp()?;
+        lhs.enforce_equal(&rhs)?;
+
+        w[i] = word;
+    }
+    mark.record(Phase::Schedule);
+
+    compress_with_schedule(state, &w, options)
+}
+
+/// Loads the block as the first 16 words of the message schedule, big-endian unless the
+/// non-standard `word_endianness` says otherwise, the 48 remaining words are left to zero.
+///
+/// This costs no constraint: a `UInt8` is already its 8 range-checked bits, and `from_bytes_be`
+/// only regroups them, so there is nothing to save with a dedicated packing of the bytes.
+fn load_block_words<ConstraintF: PrimeField>(
+    data: &[UInt8<ConstraintF>],
+    word_endianness: Endianness,
+) -> Result<Vec<UInt32<ConstraintF>>, SynthesisError> {
+    let mut w = vec![UInt32::constant(0); 64];
+    for (word, chunk) in w.iter_mut().zip(data.chunks(4)) {
+        *word = match word_endianness {
+            Endianness::Big => UInt32::from_bytes_be(chunk)?,
+            Endianness::Little => UInt32::from_bytes_le(chunk)?,
         };
-        w[i] = UInt32::wrapping_add_many(&[w[i - 16].clone(), s0, w[i - 7].clone(), s1])?;
     }
+    Ok(w)
+}
 
+/// Runs the 64 rounds over the expanded message schedule `w`, then the feed-forward.
+fn compress_with_schedule<ConstraintF: PrimeField>(
+    state: &[UInt32<ConstraintF>],
+    w: &[UInt32<ConstraintF>],
+    options: &CompressionOptions,
+) -> Result<Vec<UInt32<ConstraintF>>, SynthesisError> {
+    let mut mark = Mark::new(state.cs().or(w.cs()));
     let mut h = state.to_vec();
-    for i in 0..64 {
+    for i in 0..options.rounds {
         let ch = {
             let x1 = &h[4] & &h[5];
             let x2 = (!&h[4]) & &h[6];
             x1 ^ &x2
         };
+        mark.record(Phase::Ch);
         let ma = {
             let x1 = &h[0] & &h[1];
             let x2 = &h[0] & &h[2];
             let x3 = &h[1] & &h[2];
             x1 ^ &x2 ^ &x3
         };
+        mark.record(Phase::Maj);
         let s0 = {
             let x1 = h[0].rotate_right(2);
             let x2 = h[0].rotate_right(13);
@@ -71,11 +252,12 @@ pub fn one_compression_round<ConstraintF: PrimeField>(
             let x3 = h[4].rotate_right(25);
             x1 ^ &x2 ^ &x3
         };
+        mark.record(Phase::BigSigma);
         let t0 = UInt32::wrapping_add_many(&[
             h[7].clone(),
             s1,
             ch,
-            UInt32::constant(utils::K[i]),
+            UInt32::constant(options.round_constants[i]),
             w[i].clone(),
         ])?;
         let t1 = s0.wrapping_add(&ma);
@@ -88,22 +270,26 @@ pub fn one_compression_round<ConstraintF: PrimeField>(
         h[2] = h[1].clone();
         h[1] = h[0].clone();
         h[0] = t0.wrapping_add(&t1);
+        mark.record(Phase::RoundAdditions);
     }
 
-    for i in 0..8 {
-        h[i] = h[i].wrapping_add(&state[i].clone());
+    if options.feed_forward {
+        for i in 0..8 {
+            h[i] = h[i].wrapping_add(&state[i]);
+        }
     }
+    mark.record(Phase::FeedForward);
+    end_compression();
 
     Ok(h)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::folded_sha256::main::H;
+    use crate::folded_sha256::constants::sha256::H;
 
     use super::*;
     use ark_bn254::Fr;
-    use ark_r1cs_std::R1CSVar;
     use ark_relations::r1cs::ConstraintSystem;
     use ark_std::iter;
 
@@ -136,11 +322,258 @@ mod tests {
         };
 
         // Compare the results
-        for (result, expected) in result_var.iter().zip(expected_result.iter()) {
-            assert_eq!(result.value().unwrap(), *expected);
-        }
+        utils::assert_state_words_eq(&expected_result, &result_var.value().unwrap());
+
+        // and against the independent reference of `sha2`, which includes the feed-forward, so
+        // that a bug shared by the circuit and the native function cannot go unnoticed
+        let mut reference = H;
+        sha2::compress256(
+            &mut reference,
+            &[sha2::digest::generic_array::GenericArray::clone_from_slice(
+                &[0u8; 64],
+            )],
+        );
+        utils::assert_state_words_eq(&reference, &result_var.value().unwrap());
 
         // Check if the constraint system is satisfied
         assert!(cs.is_satisfied().unwrap());
     }
+
+    #[test]
+    fn test_compression_round_with_little_endian_words() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let block: Vec<u8> = (0..64).map(|i| (i * 5) as u8).collect();
+
+        let state: Vec<UInt32<Fr>> = H.iter().map(|&x| UInt32::constant(x)).collect();
+        let data: Vec<UInt8<Fr>> = block
+            .iter()
+            .map(|&x| UInt8::new_witness(cs.clone(), || Ok(x)).unwrap())
+            .collect();
+        let options = CompressionOptions::default().with_word_endianness(Endianness::Little);
+
+        let result_var =
+            compression_round_with_options(&mut state.clone(), &data, &options).unwrap();
+        let standard_var = one_compression_round(&mut state.clone(), &data).unwrap();
+
+        let expected_result =
+            utils::update_state_with_options(H.to_vec(), block.clone(), &options).unwrap();
+        utils::assert_state_words_eq(&expected_result, &result_var.value().unwrap());
+        utils::assert_state_words_eq(
+            &utils::update_state_ref(H.to_vec(), block).unwrap(),
+            &standard_var.value().unwrap(),
+        );
+        assert_ne!(result_var.value().unwrap(), standard_var.value().unwrap());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_compression_round_without_feed_forward() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+
+        let state: Vec<UInt32<Fr>> = H.iter().map(|&x| UInt32::constant(x)).collect();
+        let data: Vec<UInt8<Fr>> = iter::repeat(0u8).take(64).map(UInt8::constant).collect();
+        let options = CompressionOptions {
+            feed_forward: false,
+            ..Default::default()
+        };
+
+        let result_var =
+            compression_round_with_options(&mut state.clone(), &data, &options).unwrap();
+        let standard_var = one_compression_round(&mut state.clone(), &data).unwrap();
+
+        let expected_result =
+            utils::update_state_with_options(H.to_vec(), vec![0u8; 64], &options).unwrap();
+
+        utils::assert_state_words_eq(&expected_result, &result_var.value().unwrap());
+        assert_ne!(result_var.value().unwrap(), standard_var.value().unwrap());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_compression_round_with_round_constants() {
+        let state: Vec<UInt32<Fr>> = H.iter().map(|&x| UInt32::constant(x)).collect();
+        let data: Vec<UInt8<Fr>> = iter::repeat(0u8).take(64).map(UInt8::constant).collect();
+        let standard_var = one_compression_round(&mut state.clone(), &data).unwrap();
+
+        let default_override = CompressionOptions::default().with_round_constants(utils::K);
+        let result_var =
+            compression_round_with_options(&mut state.clone(), &data, &default_override).unwrap();
+        assert_eq!(result_var.value().unwrap(), standard_var.value().unwrap());
+
+        let mut k = utils::K;
+        k[0] = 0;
+        let faulted = CompressionOptions::default().with_round_constants(k);
+        let faulted_var =
+            compression_round_with_options(&mut state.clone(), &data, &faulted).unwrap();
+        utils::assert_state_words_eq(
+            &utils::update_state_with_options(H.to_vec(), vec![0u8; 64], &faulted).unwrap(),
+            &faulted_var.value().unwrap(),
+        );
+        assert_ne!(faulted_var.value().unwrap(), standard_var.value().unwrap());
+    }
+
+    #[test]
+    fn test_schedule_hint_constraints() {
+        let block: Vec<u8> = (0..64).map(|i| (i * 7) as u8).collect();
+        let expected = utils::update_state_ref(H.to_vec(), block.clone()).unwrap();
+
+        let synthesize = |hinted: bool| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let mut state: Vec<UInt32<Fr>> = H
+                .iter()
+                .map(|&x| UInt32::new_witness(cs.clone(), || Ok(x)).unwrap())
+                .collect();
+            let data: Vec<UInt8<Fr>> = block
+                .iter()
+                .map(|&x| UInt8::new_witness(cs.clone(), || Ok(x)).unwrap())
+                .collect();
+            let before = cs.num_constraints();
+            let options = CompressionOptions::default();
+            let result = if hinted {
+                let hint = utils::message_schedule(&block).unwrap();
+                compression_round_with_schedule_hint(&mut state, &data, Some(&hint), &options)
+            } else {
+                compression_round_with_options(&mut state, &data, &options)
+            }
+            .unwrap();
+            utils::assert_state_words_eq(&expected, &result.value().unwrap());
+            assert!(cs.is_satisfied().unwrap());
+            cs.num_constraints() - before
+        };
+
+        // both circuits are satisfied and compute the same state, the hinted one for fewer
+        // constraints
+        let symbolic = synthesize(false);
+        let hinted = synthesize(true);
+        assert!(
+            hinted < symbolic,
+            "hinted schedule: {} constraints, symbolic schedule: {}",
+            hinted,
+            symbolic
+        );
+    }
+
+    #[cfg(feature = "constraint-profile")]
+    #[test]
+    fn test_constraint_profile_of_one_compression() {
+        use crate::folded_sha256::profile::take_profile;
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut state: Vec<UInt32<Fr>> = H
+            .iter()
+            .map(|&x| UInt32::new_witness(cs.clone(), || Ok(x)).unwrap())
+            .collect();
+        let data: Vec<UInt8<Fr>> = (0..64)
+            .map(|i| UInt8::new_witness(cs.clone(), || Ok(i as u8)).unwrap())
+            .collect();
+        let _ = take_profile();
+
+        let before = cs.num_constraints();
+        one_compression_round(&mut state, &data).unwrap();
+        let profile = take_profile();
+        println!("constraints per phase:\n{}", profile);
+
+        // every constraint of the compression is attributed to a phase
+        assert_eq!(profile.total(), cs.num_constraints() - before);
+        assert!(profile.schedule > 0 && profile.round_additions > 0);
+        assert_eq!(take_profile().total(), 0);
+    }
+
+    #[test]
+    fn test_schedule_hint_is_checked() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let block = vec![0u8; 64];
+        let mut hint = utils::message_schedule(&block).unwrap();
+        hint[20] ^= 1;
+
+        let mut state: Vec<UInt32<Fr>> = H.iter().map(|&x| UInt32::constant(x)).collect();
+        let data: Vec<UInt8<Fr>> = block
+            .iter()
+            .map(|&x| UInt8::new_witness(cs.clone(), || Ok(x)).unwrap())
+            .collect();
+        compression_round_with_schedule_hint(
+            &mut state,
+            &data,
+            Some(&hint),
+            &CompressionOptions::default(),
+        )
+        .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_load_block_words_is_free() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let data: Vec<UInt8<Fr>> = (0..64u8)
+            .map(|x| UInt8::new_witness(cs.clone(), || Ok(x)).unwrap())
+            .collect();
+
+        let before = cs.num_constraints();
+        let w = load_block_words(&data, Endianness::Big).unwrap();
+        assert_eq!(cs.num_constraints() - before, 0);
+        assert_eq!(w[0].value().unwrap(), 0x00010203);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_compression_round_from_words() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let block: Vec<u8> = (0..64).map(|i| (i * 13 + 5) as u8).collect();
+
+        let mut state: Vec<UInt32<Fr>> = H
+            .iter()
+            .map(|&x| UInt32::new_witness(cs.clone(), || Ok(x)).unwrap())
+            .collect();
+        let data: Vec<UInt8<Fr>> = block
+            .iter()
+            .map(|&x| UInt8::new_witness(cs.clone(), || Ok(x)).unwrap())
+            .collect();
+        let words: Vec<UInt32<Fr>> = block
+            .chunks(4)
+            .map(|chunk| {
+                let word = u32::from_be_bytes(chunk.try_into().unwrap());
+                UInt32::new_witness(cs.clone(), || Ok(word)).unwrap()
+            })
+            .collect();
+
+        let from_bytes = one_compression_round(&mut state, &data).unwrap();
+        let from_words =
+            compression_round_from_words(&state, &words, &CompressionOptions::default()).unwrap();
+
+        assert_eq!(from_words.value().unwrap(), from_bytes.value().unwrap());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_enforce_digest_in_set() {
+        let abc = crate::folded_sha256::hasher::sha256(b"abc").0;
+        let empty = crate::folded_sha256::hasher::sha256(b"").0;
+        let digest_words = |digest: [u8; 32]| -> Vec<UInt32<Fr>> {
+            digest
+                .chunks(4)
+                .map(|w| u32::from_be_bytes(w.try_into().unwrap()))
+                .map(UInt32::constant)
+                .collect()
+        };
+
+        for (digest, in_set) in [(abc, true), ([7u8; 32], false)] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let digest_var = digest_words(digest)
+                .iter()
+                .map(|x| UInt32::new_witness(cs.clone(), || x.value()))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            enforce_digest_in_set(&digest_var, &[empty, abc], &Boolean::TRUE).unwrap();
+            assert_eq!(cs.is_satisfied().unwrap(), in_set);
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let digest_var = digest_words(digest)
+                .iter()
+                .map(|x| UInt32::new_witness(cs.clone(), || x.value()))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            enforce_digest_in_set(&digest_var, &[empty, abc], &Boolean::FALSE).unwrap();
+            assert!(cs.is_satisfied().unwrap());
+        }
+    }
 }
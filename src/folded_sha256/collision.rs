@@ -0,0 +1,277 @@
+//! Research tool: folds two inputs side by side and proves whether their digests share a prefix.
+//!
+//! Each step compresses one block of each input and compares the first `prefix_words` words of
+//! the two resulting states in the circuit. With `utils::CompressionOptions::rounds` below 64,
+//! this proves (near-)collisions of reduced-round SHA-256, e.g. to publish a cryptanalysis
+//! result without revealing the colliding inputs. The digests of any non-default
+//! `CompressionOptions` are NOT SHA-256 digests, and a proof about a reduced-round collision says
+//! nothing about the full function.
+//!
+//! The state is `z_i = [A_0, ..., A_7, B_0, ..., B_7, shares_prefix]`: the states of the two
+//! inputs, and whether their first `prefix_words` words are equal, as a 0/1 field element.
+//! `z_0 = prefix_collision_initial_state()` starts both from the initial hash value, hence
+//! sharing any prefix. The external inputs of a step are the block of the first input followed
+//! by the block of the second, one byte per element.
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit::{bytes_from_fp, compression_round_with_options};
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::{violation, FoldedSha256Error};
+use crate::folded_sha256::main::{
+    bigint_to_u32, bytes_from_field, fold, NovaFor, NovaParamsFor, H, STATE_LEN,
+};
+use crate::folded_sha256::utils::{
+    compress_block_with_options, BlockExternalInputs, CompressionOptions, BLOCK_LENGTH_BYTES,
+};
+
+use ark_bn254::Fr;
+use ark_ff::{One, Zero};
+use ark_r1cs_std::{boolean::Boolean, eq::EqGadget, fields::fp::FpVar, uint32::UInt32};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::Error;
+
+/// Index of the `shares_prefix` flag in the state.
+const FLAG_INDEX: usize = 2 * STATE_LEN;
+
+/// Parameters of `FoldedSha256PrefixCollisionFCircuit`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PrefixCollisionParams {
+    /// Number of leading 32-bit words of the digests compared, at most 8.
+    pub prefix_words: usize,
+    /// Compression options applied to both inputs, typically `CompressionOptions::with_rounds`.
+    pub options: CompressionOptions,
+}
+
+/// Folding circuit compressing two inputs in parallel, with the state layout of the module
+/// documentation. Costs two compressions and `prefix_words` word comparisons per step.
+#[derive(Clone, Copy, Debug)]
+pub struct FoldedSha256PrefixCollisionFCircuit {
+    params: PrefixCollisionParams,
+}
+
+impl FCircuit<Fr> for FoldedSha256PrefixCollisionFCircuit {
+    type Params = PrefixCollisionParams;
+
+    fn new(params: Self::Params) -> Result<Self, Error> {
+        if params.prefix_words > STATE_LEN {
+            return Err(Error::Other(format!(
+                "a digest has {} words, cannot compare a prefix of {}",
+                STATE_LEN, params.prefix_words
+            )));
+        }
+        Ok(Self { params })
+    }
+
+    fn state_len(&self) -> usize {
+        FLAG_INDEX + 1
+    }
+    fn external_inputs_len(&self) -> usize {
+        2 * BLOCK_LENGTH_BYTES
+    }
+
+    fn step_native(
+        &self,
+        _i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let bytes = bytes_from_field(&external_inputs)?;
+        let compress = |state: &[Fr], block: &[u8]| {
+            let state: [u32; 8] = core::array::from_fn(|j| bigint_to_u32(state[j]));
+            compress_block_with_options(&state, block.try_into().unwrap(), &self.params.options)
+        };
+        let a = compress(&z_i[..STATE_LEN], &bytes[..BLOCK_LENGTH_BYTES]);
+        let b = compress(&z_i[STATE_LEN..FLAG_INDEX], &bytes[BLOCK_LENGTH_BYTES..]);
+
+        let shares_prefix = a[..self.params.prefix_words] == b[..self.params.prefix_words];
+        let mut z_i1: Vec<Fr> = a.iter().chain(&b).map(|&x| Fr::from(x)).collect();
+        z_i1.push(Fr::from(shares_prefix));
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        _cs: ConstraintSystemRef<Fr>,
+        _i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        if z_i.len() != self.state_len() || external_inputs.len() != self.external_inputs_len() {
+            return violation(SynthesisError::Unsatisfiable);
+        }
+        // the state words are outputs of the previous step, hence already 32-bit
+        let words = z_i[..FLAG_INDEX]
+            .iter()
+            .map(|x| Ok(UInt32::from_fp(x)?.0))
+            .collect::<Result<Vec<UInt32<Fr>>, SynthesisError>>()?;
+        let bytes = bytes_from_fp(&external_inputs)?;
+
+        let mut state_a = words[..STATE_LEN].to_vec();
+        let mut state_b = words[STATE_LEN..].to_vec();
+        let a = compression_round_with_options(
+            &mut state_a,
+            &bytes[..BLOCK_LENGTH_BYTES].to_vec(),
+            &self.params.options,
+        )?;
+        let b = compression_round_with_options(
+            &mut state_b,
+            &bytes[BLOCK_LENGTH_BYTES..].to_vec(),
+            &self.params.options,
+        )?;
+
+        let mut shares_prefix = Boolean::TRUE;
+        for (x, y) in a.iter().zip(&b).take(self.params.prefix_words) {
+            shares_prefix &= x.is_eq(y)?;
+        }
+
+        let mut z_i1 = a
+            .iter()
+            .chain(&b)
+            .map(|x| x.to_fp())
+            .collect::<Result<Vec<FpVar<Fr>>, SynthesisError>>()?;
+        z_i1.push(shares_prefix.into());
+        Ok(z_i1)
+    }
+}
+
+/// The initial state `[H_0, ..., H_7, H_0, ..., H_7, 1]` of a prefix collision folding.
+pub fn prefix_collision_initial_state() -> Vec<Fr> {
+    let mut z_0: Vec<Fr> = H.iter().chain(&H).map(|&x| Fr::from(x)).collect();
+    z_0.push(Fr::one());
+    z_0
+}
+
+/// Reads the `shares_prefix` flag of a state of `FoldedSha256PrefixCollisionFCircuit`.
+pub fn shares_prefix_from_state(z_i: &[Fr]) -> bool {
+    assert_eq!(z_i.len(), FLAG_INDEX + 1);
+    !z_i[FLAG_INDEX].is_zero()
+}
+
+/// Folds `input_a` and `input_b` side by side with `FoldedSha256PrefixCollisionFCircuit`, see the
+/// module documentation. Both are padded as SHA-256 messages.
+///
+/// Returns `FoldedSha256Error::BlockCountMismatch` if their padded messages do not have the same
+/// number of blocks.
+pub fn prove_prefix_collision<R: RngCore + CryptoRng>(
+    input_a: Vec<u8>,
+    input_b: Vec<u8>,
+    params: PrefixCollisionParams,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256PrefixCollisionFCircuit>,
+        NovaParamsFor<FoldedSha256PrefixCollisionFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    let (blocks_a, blocks_b) = (
+        FoldConfig::num_blocks(input_a.len()),
+        FoldConfig::num_blocks(input_b.len()),
+    );
+    if blocks_a != blocks_b {
+        return Err(FoldedSha256Error::BlockCountMismatch { blocks_a, blocks_b });
+    }
+    config.check_input_len(input_a.len())?;
+
+    let external_inputs = BlockExternalInputs::<Fr, _>::new(input_a)
+        .zip(BlockExternalInputs::<Fr, _>::new(input_b))
+        .map(|(a, b)| [a, b].concat());
+    fold(
+        FoldedSha256PrefixCollisionFCircuit::new(params)?,
+        prefix_collision_initial_state(),
+        external_inputs,
+        config,
+        rng,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    /// Two 40-byte inputs sharing their first 16 bytes. SHA-256 reduced to 4 rounds only uses
+    /// the first 4 message words, so they collide.
+    fn inputs() -> (Vec<u8>, Vec<u8>) {
+        let mut a = b"shared 16 bytes!".to_vec();
+        let mut b = a.clone();
+        a.extend([0xaa; 24]);
+        b.extend([0x55; 24]);
+        (a, b)
+    }
+
+    fn params(rounds: usize) -> PrefixCollisionParams {
+        PrefixCollisionParams {
+            prefix_words: 4,
+            options: CompressionOptions::default().with_rounds(rounds),
+        }
+    }
+
+    fn step(circuit: &FoldedSha256PrefixCollisionFCircuit) -> Vec<Fr> {
+        let (a, b) = inputs();
+        let block = BlockExternalInputs::<Fr, _>::new(a)
+            .zip(BlockExternalInputs::<Fr, _>::new(b))
+            .map(|(a, b)| [a, b].concat())
+            .next()
+            .unwrap();
+        let z_0 = prefix_collision_initial_state();
+        let z_1 = circuit.step_native(0, z_0.clone(), block.clone()).unwrap();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_0)).unwrap();
+        let externalInputsVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+        let computed_z_i1Var = circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+            .unwrap();
+        assert_eq!(computed_z_i1Var.value().unwrap(), z_1);
+        assert!(cs.is_satisfied().unwrap());
+        z_1
+    }
+
+    #[test]
+    fn test_prefix_collision_f_circuit() {
+        let reduced = FoldedSha256PrefixCollisionFCircuit::new(params(4)).unwrap();
+        let z_1 = step(&reduced);
+        assert!(shares_prefix_from_state(&z_1));
+        assert_eq!(&z_1[..STATE_LEN], &z_1[STATE_LEN..FLAG_INDEX]);
+
+        let full = FoldedSha256PrefixCollisionFCircuit::new(params(64)).unwrap();
+        assert!(!shares_prefix_from_state(&step(&full)));
+
+        assert!(
+            FoldedSha256PrefixCollisionFCircuit::new(PrefixCollisionParams {
+                prefix_words: 9,
+                ..params(4)
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_prove_prefix_collision() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (a, b) = inputs();
+        let (folding_scheme, _) =
+            prove_prefix_collision(a, b, params(4), &FoldConfig::default(), &mut rng).unwrap();
+        assert!(shares_prefix_from_state(&folding_scheme.z_i));
+
+        assert!(matches!(
+            prove_prefix_collision(
+                vec![0; 10],
+                vec![0; 100],
+                params(4),
+                &FoldConfig::default(),
+                &mut rng
+            ),
+            Err(FoldedSha256Error::BlockCountMismatch {
+                blocks_a: 1,
+                blocks_b: 2
+            })
+        ));
+    }
+}
@@ -0,0 +1,237 @@
+//! Glue to use a SHA-256 digest produced by this crate as the input of another circuit.
+//!
+//! Inside circuits the digest is carried, like the folding state, as 8 field elements holding the
+//! big-endian 32-bit words of the SHA-256 state. When both sides are arithmetic circuits, the
+//! digest can also be packed into two field elements, see `state_to_digest_field`.
+use crate::folded_sha256::circuit::words_to_bytes_be;
+use crate::folded_sha256::digest::digest_from_state;
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    convert::ToBitsGadget,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    uint32::UInt32,
+    uint8::UInt8,
+    R1CSVar,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+
+/// Allocates `digest` as 8 witness field elements, one per big-endian 32-bit word. Each word
+/// is allocated as a `UInt32`, so the field elements are range-checked to be `< 2^32`.
+pub fn digest_to_field_vars<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    digest: &[u8; 32],
+) -> Result<Vec<FpVar<F>>, SynthesisError> {
+    digest
+        .chunks(4)
+        .map(|chunk| {
+            let word = u32::from_be_bytes(chunk.try_into().unwrap());
+            UInt32::new_witness(cs.clone(), || Ok(word))?.to_fp()
+        })
+        .collect()
+}
+
+/// Converts 8 field elements holding the big-endian words of a digest back to its 32 bytes,
+/// enforcing that each element is a 32-bit word.
+pub fn field_vars_to_digest<F: PrimeField>(
+    vars: &[FpVar<F>],
+) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    if vars.len() != 8 {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let words = vars
+        .iter()
+        .map(|x| {
+            // `from_fp` only keeps the 32 least significant bits, the remaining ones must be zero
+            let (word, rest) = UInt32::from_fp(x)?;
+            rest.enforce_equal(&FpVar::zero())?;
+            Ok(word)
+        })
+        .collect::<Result<Vec<UInt32<F>>, SynthesisError>>()?;
+    words_to_bytes_be(&words)
+}
+
+/// Packs the digest held by the folding state `z_i` into two field elements: the first one is the
+/// big-endian integer of the digest bytes `0..16`, the second one that of the bytes `16..32`.
+/// Equivalently, `[w_0 * 2^96 + w_1 * 2^64 + w_2 * 2^32 + w_3, w_4 * 2^96 + ... + w_7]` for the
+/// state words `w_j`. Both are below `2^128`, so the field must have more than 128 bits.
+pub fn state_to_digest_field<F: PrimeField>(z_i: &[F]) -> [F; 2] {
+    assert!(F::MODULUS_BIT_SIZE > 128);
+    let digest = digest_from_state(z_i);
+    [
+        F::from_be_bytes_mod_order(&digest.0[..16]),
+        F::from_be_bytes_mod_order(&digest.0[16..]),
+    ]
+}
+
+/// Inverse of `state_to_digest_field`, returning the 8 state words. Fails if an element does not
+/// fit in 128 bits.
+pub fn digest_field_to_state<F: PrimeField>(digest: &[F; 2]) -> Result<Vec<F>, &'static str> {
+    let mut z_i = Vec::with_capacity(8);
+    for x in digest {
+        let bytes = x.into_bigint().to_bytes_be();
+        let (high, low) = bytes.split_at(bytes.len() - 16);
+        if high.iter().any(|&b| b != 0) {
+            return Err("digest field element does not fit in 128 bits");
+        }
+        z_i.extend(
+            low.chunks(4)
+                .map(|chunk| F::from(u32::from_be_bytes(chunk.try_into().unwrap()))),
+        );
+    }
+    Ok(z_i)
+}
+
+/// In-circuit `state_to_digest_field`. The packing is a linear combination of the words, hence
+/// free, but it assumes they are 32-bit, as are the state words output by the compression. Use
+/// `field_vars_to_digest` to range-check words of another origin.
+pub fn state_vars_to_digest_field<F: PrimeField>(
+    z_i: &[FpVar<F>],
+) -> Result<[FpVar<F>; 2], SynthesisError> {
+    if z_i.len() != 8 {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let pack = |words: &[FpVar<F>]| {
+        words
+            .iter()
+            .fold(FpVar::zero(), |acc, word| acc * F::from(1u64 << 32) + word)
+    };
+    Ok([pack(&z_i[..4]), pack(&z_i[4..])])
+}
+
+/// Encodes the digest held by the 8 state words as its 64 lowercase hexadecimal ASCII
+/// characters, e.g. to compare it with a string identifier.
+///
+/// Each character is witnessed as a byte and enforced to equal `n + '0'` if its nibble `n` is
+/// below 10 and `n - 10 + 'a'` otherwise, i.e. `n + 48 + 39 * [n >= 10]`: a few constraints per
+/// character, instead of the bit decomposition of a field element.
+pub fn digest_to_hex_ascii<F: PrimeField>(
+    state: &[UInt32<F>],
+) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    if state.len() != 8 {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    let bytes = words_to_bytes_be(state)?;
+    let cs = bytes.cs();
+    if cs.is_none() {
+        // all the words are constants
+        let digest = bytes.value()?;
+        return Ok(UInt8::constant_vec(hex::encode(digest).as_bytes()));
+    }
+
+    let mut chars = Vec::with_capacity(64);
+    for byte in &bytes {
+        let bits = byte.to_bits_le()?;
+        // most significant nibble first
+        for nibble in [&bits[4..], &bits[..4]] {
+            let value = nibble
+                .iter()
+                .enumerate()
+                .fold(FpVar::zero(), |acc, (k, bit)| {
+                    acc + FpVar::from(bit.clone()) * F::from(1u64 << k)
+                });
+            // n >= 10 iff b3 and (b2 or b1)
+            let is_letter = &nibble[3] & &(&nibble[2] | &nibble[1]);
+            let expected = value + F::from(48u64) + FpVar::from(is_letter) * F::from(39u64);
+
+            let character = UInt8::new_witness(cs.clone(), || {
+                let mut n = 0u8;
+                for (k, bit) in nibble.iter().enumerate() {
+                    n |= (bit.value()? as u8) << k;
+                }
+                Ok(if n < 10 { b'0' + n } else { b'a' + n - 10 })
+            })?;
+            character.to_fp()?.enforce_equal(&expected)?;
+            chars.push(character);
+        }
+    }
+    Ok(chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn test_digest_field_vars_round_trip() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let digest: [u8; 32] =
+            hex::decode("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+                .unwrap()
+                .try_into()
+                .unwrap();
+
+        let vars = digest_to_field_vars(cs.clone(), &digest).unwrap();
+        assert_eq!(vars[0].value().unwrap(), Fr::from(0xba7816bfu32));
+
+        let bytes = field_vars_to_digest(&vars).unwrap();
+        assert_eq!(bytes.value().unwrap(), digest.to_vec());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_field_vars_to_digest_range_check() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut words = vec![Fr::from(0u32); 8];
+        words[3] = Fr::from(1u64 << 32);
+
+        let vars = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(words)).unwrap();
+        field_vars_to_digest(&vars).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_digest_field_round_trip() {
+        let digest =
+            hex::decode("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+                .unwrap();
+        let z_i: Vec<Fr> = digest
+            .chunks(4)
+            .map(|chunk| Fr::from(u32::from_be_bytes(chunk.try_into().unwrap())))
+            .collect();
+
+        let packed = state_to_digest_field(&z_i);
+        assert_eq!(packed[0], Fr::from_be_bytes_mod_order(&digest[..16]));
+        assert_eq!(packed[1], Fr::from_be_bytes_mod_order(&digest[16..]));
+        assert_eq!(digest_field_to_state(&packed).unwrap(), z_i);
+
+        // the in-circuit packing agrees, without any constraint
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_i_vars = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let packed_vars = state_vars_to_digest_field(&z_i_vars).unwrap();
+        assert_eq!(packed_vars.value().unwrap(), packed);
+        assert_eq!(cs.num_constraints(), 0);
+
+        let too_large = [Fr::from(u128::MAX) + Fr::from(1u64), Fr::from(0u64)];
+        assert!(digest_field_to_state(&too_large).is_err());
+    }
+
+    #[test]
+    fn test_digest_to_hex_ascii() {
+        let hex_digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let digest = hex::decode(hex_digest).unwrap();
+        let words: Vec<u32> = digest
+            .chunks(4)
+            .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()))
+            .collect();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let state = Vec::<UInt32<Fr>>::new_witness(cs.clone(), || Ok(words.clone())).unwrap();
+        let chars = digest_to_hex_ascii(&state).unwrap();
+        assert_eq!(chars.value().unwrap(), hex_digest.as_bytes());
+        assert!(cs.is_satisfied().unwrap());
+
+        let constant_state: Vec<UInt32<Fr>> = words.iter().map(|&x| UInt32::constant(x)).collect();
+        assert_eq!(
+            digest_to_hex_ascii(&constant_state)
+                .unwrap()
+                .value()
+                .unwrap(),
+            hex_digest.as_bytes()
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::folded_sha256::circuit::{bytes_from_fp, one_compression_round};
+use crate::folded_sha256::constants::{sha224, sha256};
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::utils::BLOCK_LENGTH_BYTES;
+
+use ark_bn254::Fr;
+use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, uint32::UInt32};
+use ark_relations::r1cs::ConstraintSystem;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Rough estimate of the prover memory per R1CS constraint of a folding step (sparse matrices,
+/// witness and error vectors, commitment scratch space), used by `FoldConfig::auto_chunk`.
+pub const BYTES_PER_CONSTRAINT: usize = 1024;
+
+/// Upper bound of the `blocks_per_step` picked by `FoldConfig::auto_chunk`.
+pub const MAX_AUTO_BLOCKS_PER_STEP: usize = 16;
+
+/// Upper bound of the base 2 log of the input length accepted by `input_len_from_log`: a 1 GiB
+/// input, already millions of folding steps.
+pub const MAX_INPUT_LEN_LOG: usize = 30;
+
+/// Input length `2^log_input_len` of a test input given by its base 2 log, e.g. on a command line.
+///
+/// Rejects a log above `MAX_INPUT_LEN_LOG` instead of overflowing the shift or allocating an
+/// input that cannot be folded anyway.
+pub fn input_len_from_log(log_input_len: usize) -> Result<usize, String> {
+    if log_input_len > MAX_INPUT_LEN_LOG {
+        return Err(format!(
+            "an input length log of {} is too large: the input would be 2^{} bytes, at most 2^{} \
+             are supported",
+            log_input_len, log_input_len, MAX_INPUT_LEN_LOG
+        ));
+    }
+    Ok(1 << log_input_len)
+}
+
+/// Member of the SHA-2 family folded by a driver. SHA-224 uses the same compression function as
+/// SHA-256, only the initial state (and the truncation of the digest) differ.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Sha256,
+    Sha224,
+}
+
+impl Variant {
+    /// Initial hash value H(0), the state `z_0` of the folding.
+    pub fn initial_state(&self) -> [u32; 8] {
+        match self {
+            Variant::Sha256 => sha256::H,
+            Variant::Sha224 => sha224::H,
+        }
+    }
+}
+
+/// How the final folded instance is proven to a verifier, see `main::prove_and_verify_with`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeciderMode {
+    /// `DeciderEth`: a Groth16 proof wrapping the KZG openings of the final instances, succinct
+    /// and verifiable on-chain, at the cost of the Groth16 setup and proof.
+    #[default]
+    Eth,
+    /// No Decider: the Nova IVC proof itself, checked by the Nova verifier, for verifiers which
+    /// are not on chain. There is no Decider setup or proof, but the proof and its verification
+    /// are linear in the step circuit.
+    ///
+    /// The non-Ethereum Decider of folding-schemes proves the CycleFold instance with a SNARK on
+    /// the second curve, which needs a pairing-friendly cycle: Grumpkin is not pairing-friendly,
+    /// so there is no succinct alternative to `Eth` on BN254/Grumpkin.
+    IvcOnly,
+}
+
+impl FromStr for DeciderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "eth" => Ok(DeciderMode::Eth),
+            "ivc" => Ok(DeciderMode::IvcOnly),
+            _ => Err(format!("unknown decider mode: {}", s)),
+        }
+    }
+}
+
+/// Configuration of the folding driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FoldConfig {
+    /// Maximum number of 512-bit blocks, padding included, folded for a single input, whatever
+    /// the number of blocks per step. `None` means unbounded.
+    ///
+    /// Proving time grows linearly with the number of blocks, so services exposing the prover
+    /// should set it to reject oversized inputs before any work is done.
+    pub max_blocks: Option<usize>,
+    /// Number of times a failing `prove_step` is retried with the same inputs before giving up.
+    pub max_step_retries: usize,
+    /// Number of message blocks compressed by each folding step, see `multi_block`. Larger
+    /// values mean fewer steps but a larger step circuit, hence more memory per step.
+    pub blocks_per_step: usize,
+}
+
+impl Default for FoldConfig {
+    fn default() -> Self {
+        Self {
+            max_blocks: None,
+            max_step_retries: 0,
+            blocks_per_step: 1,
+        }
+    }
+}
+
+impl FoldConfig {
+    /// Picks the largest `blocks_per_step` whose step circuit fits in `memory_budget` bytes,
+    /// estimated from the measured number of constraints of one block (`constraints_per_block`)
+    /// and `BYTES_PER_CONSTRAINT`. Always at least 1, at most `MAX_AUTO_BLOCKS_PER_STEP`.
+    pub fn auto_chunk(memory_budget: usize) -> Self {
+        let bytes_per_block = constraints_per_block() * BYTES_PER_CONSTRAINT;
+        Self {
+            blocks_per_step: (memory_budget / bytes_per_block).clamp(1, MAX_AUTO_BLOCKS_PER_STEP),
+            ..Default::default()
+        }
+    }
+
+    /// Number of 512-bit blocks of an input of `len` bytes, padding included.
+    pub fn num_blocks(len: usize) -> usize {
+        // the padding adds at least the 0x80 byte and the 8-byte length
+        (len + 9).div_ceil(BLOCK_LENGTH_BYTES)
+    }
+
+    /// Number of folding steps needed to hash an input of `len` bytes, padding included.
+    pub fn num_steps(&self, len: usize) -> usize {
+        Self::num_blocks(len).div_ceil(self.blocks_per_step.max(1))
+    }
+
+    /// Returns `FoldedSha256Error::InputTooLarge` if an input of `len` bytes, once padded, takes
+    /// more than `max_blocks` blocks.
+    pub fn check_input_len(&self, len: usize) -> Result<(), FoldedSha256Error> {
+        self.check_num_blocks(Self::num_blocks(len))
+    }
+
+    /// Returns `FoldedSha256Error::InputTooLarge` if `num_blocks` blocks exceed `max_blocks`.
+    pub fn check_num_blocks(&self, num_blocks: usize) -> Result<(), FoldedSha256Error> {
+        match self.max_blocks {
+            Some(max_blocks) if num_blocks > max_blocks => Err(FoldedSha256Error::InputTooLarge {
+                num_blocks,
+                max_blocks,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Number of constraints of the compression of one block, external inputs decoding included,
+/// measured once by synthesizing it.
+pub fn constraints_per_block() -> usize {
+    static CONSTRAINTS_PER_BLOCK: OnceLock<usize> = OnceLock::new();
+    *CONSTRAINTS_PER_BLOCK.get_or_init(|| {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let mut state: Vec<UInt32<Fr>> = (0..8)
+            .map(|_| UInt32::new_witness(cs.clone(), || Ok(0)).unwrap())
+            .collect();
+        let block: Vec<FpVar<Fr>> = (0..BLOCK_LENGTH_BYTES)
+            .map(|_| FpVar::new_witness(cs.clone(), || Ok(Fr::from(0u64))).unwrap())
+            .collect();
+        let data = bytes_from_fp(&block).unwrap();
+        one_compression_round(&mut state, &data).unwrap();
+        cs.num_constraints()
+    })
+}
+
+/// Runs `attempt`, the folding of step `step`, retrying it up to `max_retries` times if it fails.
+///
+/// Failures of `prove_step` are supposed to be transient here (e.g. resource pressure): Nova only
+/// updates its running instance once a step succeeded, so a failed attempt can be retried on the
+/// same folding scheme. The error of the last attempt is returned together with the step index.
+#[cfg(feature = "prover")]
+pub fn retry_step<T>(
+    step: usize,
+    max_retries: usize,
+    mut attempt: impl FnMut() -> Result<T, folding_schemes::Error>,
+) -> Result<T, FoldedSha256Error> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match attempt() {
+            Ok(result) => return Ok(result),
+            Err(e) if attempts > max_retries => {
+                return Err(FoldedSha256Error::StepFailed {
+                    step,
+                    attempts,
+                    source: e,
+                })
+            }
+            Err(_) => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::utils::sha256_msg_block_sequence;
+
+    #[test]
+    fn test_num_steps() {
+        let config = FoldConfig::default();
+        for len in [0, 3, 55, 56, 64, 119, 120, 1000] {
+            assert_eq!(
+                config.num_steps(len),
+                sha256_msg_block_sequence(vec![0u8; len]).len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_num_steps_with_blocks_per_step() {
+        let config = FoldConfig {
+            blocks_per_step: 4,
+            ..Default::default()
+        };
+        // 1000 bytes take 16 blocks, 1100 bytes take 18
+        assert_eq!(config.num_steps(1000), 4);
+        assert_eq!(config.num_steps(1100), 5);
+    }
+
+    #[test]
+    fn test_max_blocks_counts_blocks() {
+        // 1000 bytes take 16 blocks, i.e. 4 steps of 4 blocks: the limit is on the blocks
+        for blocks_per_step in [1, 4] {
+            let config = FoldConfig {
+                max_blocks: Some(16),
+                blocks_per_step,
+                ..Default::default()
+            };
+            assert!(config.check_input_len(1000).is_ok());
+            assert!(matches!(
+                config.check_input_len(1100),
+                Err(FoldedSha256Error::InputTooLarge {
+                    num_blocks: 18,
+                    max_blocks: 16
+                })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_input_len_from_log() {
+        assert_eq!(input_len_from_log(0), Ok(1));
+        assert_eq!(input_len_from_log(6), Ok(64));
+        assert_eq!(
+            input_len_from_log(MAX_INPUT_LEN_LOG),
+            Ok(1 << MAX_INPUT_LEN_LOG)
+        );
+        let error = input_len_from_log(40).unwrap_err();
+        assert!(error.contains("too large"), "{}", error);
+        assert!(input_len_from_log(usize::BITS as usize).is_err());
+    }
+
+    #[test]
+    fn test_auto_chunk() {
+        assert_eq!(FoldConfig::auto_chunk(0).blocks_per_step, 1);
+        assert_eq!(FoldConfig::auto_chunk(1 << 20).blocks_per_step, 1);
+
+        let bytes_per_block = constraints_per_block() * BYTES_PER_CONSTRAINT;
+        assert_eq!(
+            FoldConfig::auto_chunk(3 * bytes_per_block + 1).blocks_per_step,
+            3
+        );
+        assert_eq!(
+            FoldConfig::auto_chunk(usize::MAX).blocks_per_step,
+            MAX_AUTO_BLOCKS_PER_STEP
+        );
+    }
+
+    #[cfg(feature = "prover")]
+    #[test]
+    fn test_retry_step_recovers_from_transient_error() {
+        let mut calls = 0;
+        let result = retry_step(3, 2, || {
+            calls += 1;
+            if calls == 1 {
+                Err(folding_schemes::Error::Other("mock error".to_string()))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[cfg(feature = "prover")]
+    #[test]
+    fn test_retry_step_gives_up() {
+        let mut calls = 0;
+        let result: Result<(), _> = retry_step(3, 2, || {
+            calls += 1;
+            Err(folding_schemes::Error::Other("mock error".to_string()))
+        });
+        assert_eq!(calls, 3);
+        assert!(matches!(
+            result,
+            Err(FoldedSha256Error::StepFailed {
+                step: 3,
+                attempts: 3,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,48 @@
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::N;
+
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef};
+use folding_schemes::folding::nova::decider_eth_circuit::DeciderEthCircuit;
+
+/// Returns the constraint system of the final folded relation, for users plugging their own
+/// SNARK backend instead of the built-in Groth16 `DeciderEth`.
+///
+/// This is the same circuit `DeciderEth` proves: it checks that the running instance `U_i` and
+/// the last incoming instance `u_i` satisfy the augmented SHA-256 step relation, that `u_i` is a
+/// fresh instance of its last step, and the CycleFold instance. Its public inputs follow the
+/// `DeciderEth` layout, i.e. `pp_hash`, `i`, `z_0`, `z_i`, followed by the commitments and the
+/// KZG challenges and evaluations. The witness is fully assigned, so the returned system can be
+/// handed to any R1CS prover.
+pub fn final_constraint_system(
+    folding_scheme: N,
+) -> Result<ConstraintSystemRef<Fr>, FoldedSha256Error> {
+    let circuit = DeciderEthCircuit::<Projective, Projective2, GVar2>::try_from(folding_scheme)?;
+
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    circuit.generate_constraints(cs.clone())?;
+    cs.finalize();
+    Ok(cs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::config::FoldConfig;
+    use crate::folded_sha256::main::run;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    // slow: synthesizes the whole Decider circuit, use `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_final_constraint_system_is_satisfied() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, _) = run(vec![0u8; 100], &FoldConfig::default(), &mut rng).unwrap();
+
+        let cs = final_constraint_system(folding_scheme).unwrap();
+        assert!(cs.num_constraints() > 0);
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
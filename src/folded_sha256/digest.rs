@@ -0,0 +1,219 @@
+use ark_ff::{BigInteger, PrimeField};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::fmt;
+use std::str::FromStr;
+
+/// Recovers the 32-byte digest from the final folding state `z_i`, whose 8 elements are the
+/// big-endian 32-bit words of the SHA-256 state.
+pub fn digest_from_state<F: PrimeField>(z_i: &[F]) -> Digest {
+    assert_eq!(z_i.len(), 8);
+    let mut digest = [0u8; 32];
+    for (chunk, x) in digest.chunks_mut(4).zip(z_i.iter()) {
+        let bytes = x.into_bigint().to_bytes_be();
+        // Take the last 4 bytes to avoid leading zeros
+        chunk.copy_from_slice(&bytes[bytes.len() - 4..]);
+    }
+    Digest(digest)
+}
+
+/// Converts a 32-byte big-endian IV (or midstate) into the 8-element folding state, the inverse
+/// of `digest_from_state`. The result can be passed as `z_0` to `Nova::init` or `main::fold`, e.g.
+/// to fold from a custom IV or to resume from a midstate.
+pub fn initial_state_from_bytes<F: PrimeField>(iv: &[u8; 32]) -> Vec<F> {
+    iv.chunks(4)
+        .map(|word| F::from(u32::from_be_bytes(word.try_into().unwrap())))
+        .collect()
+}
+
+/// A 32-byte digest. `Display` formats it as lowercase hex, like `LowerHex`; with `{:#x}` or
+/// `{:#X}`, the hex is prefixed with `0x`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Digest(pub [u8; 32]);
+
+impl Digest {
+    /// The digest held by the final folding state `z_i`, see `digest_from_state`.
+    pub fn from_state<F: PrimeField>(z_i: &[F]) -> Self {
+        digest_from_state(z_i)
+    }
+
+    /// The digest of a native SHA-256 state, the concatenation of its big-endian words.
+    pub fn from_words(state: [u32; 8]) -> Self {
+        let mut digest = [0u8; 32];
+        for (chunk, word) in digest.chunks_mut(4).zip(state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        Self(digest)
+    }
+}
+
+impl From<[u8; 32]> for Digest {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Digest> for [u8; 32] {
+    fn from(digest: Digest) -> Self {
+        digest.0
+    }
+}
+
+impl PartialEq<[u8; 32]> for Digest {
+    fn eq(&self, other: &[u8; 32]) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<Digest> for [u8; 32] {
+    fn eq(&self, other: &Digest) -> bool {
+        *self == other.0
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl fmt::LowerHex for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        f.write_str(&hex::encode(self.0))
+    }
+}
+
+impl fmt::UpperHex for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            f.write_str("0x")?;
+        }
+        f.write_str(&hex::encode_upper(self.0))
+    }
+}
+
+/// The first 16 bytes of the digest held by `z_i`, i.e. its first 4 words, as used by protocols
+/// committing with a truncated SHA-256.
+///
+/// The truncation halves the collision resistance: a collision is found after about `2^64`
+/// evaluations (birthday bound on 128 bits), instead of `2^128` for the full digest. Preimage
+/// resistance is reduced to 128 bits.
+pub fn digest128<F: PrimeField>(z_i: &[F]) -> [u8; 16] {
+    digest_from_state(z_i).0[..16].try_into().unwrap()
+}
+
+/// Output format of a digest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DigestFormat {
+    /// Lowercase hexadecimal string.
+    #[default]
+    Hex,
+    /// Standard base64 with padding.
+    Base64,
+    /// Decimal byte array, e.g. `[186, 120, ...]`.
+    Bytes,
+}
+
+impl FromStr for DigestFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hex" => Ok(DigestFormat::Hex),
+            "base64" => Ok(DigestFormat::Base64),
+            "bytes" => Ok(DigestFormat::Bytes),
+            _ => Err(format!("unknown digest format: {}", s)),
+        }
+    }
+}
+
+pub fn format_digest(digest: &Digest, format: DigestFormat) -> String {
+    match format {
+        DigestFormat::Hex => digest.to_string(),
+        DigestFormat::Base64 => STANDARD.encode(digest),
+        DigestFormat::Bytes => format!("{:?}", digest.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::constants::sha256::H;
+    use crate::folded_sha256::utils::{sha256_msg_block_sequence, update_state_ref};
+    use ark_bn254::Fr;
+
+    fn abc_digest() -> Digest {
+        let block = sha256_msg_block_sequence(b"abc".to_vec())[0].to_vec();
+        let state = update_state_ref(H.to_vec(), block).unwrap();
+        let z_i: Vec<Fr> = state.iter().map(|&x| Fr::from(x)).collect();
+        digest_from_state(&z_i)
+    }
+
+    #[test]
+    fn test_initial_state_from_bytes() {
+        let iv =
+            hex_literal::hex!("6a09e667bb67ae853c6ef372a54ff53a510e527f9b05688c1f83d9ab5be0cd19");
+        let z_0: Vec<Fr> = initial_state_from_bytes(&iv);
+        assert_eq!(z_0, H.iter().map(|&x| Fr::from(x)).collect::<Vec<Fr>>());
+        assert_eq!(digest_from_state(&z_0), iv);
+        assert_eq!(Digest::from_words(H), iv);
+    }
+
+    #[test]
+    fn test_format_digest() {
+        let digest = abc_digest();
+
+        assert_eq!(
+            format_digest(&digest, DigestFormat::Hex),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            format_digest(&digest, DigestFormat::Base64),
+            "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0="
+        );
+        assert_eq!(
+            format_digest(&digest, DigestFormat::Bytes),
+            "[186, 120, 22, 191, 143, 1, 207, 234, 65, 65, 64, 222, 93, 174, 34, 35, 176, 3, 97, \
+             163, 150, 23, 122, 156, 180, 16, 255, 97, 242, 0, 21, 173]"
+        );
+    }
+
+    #[test]
+    fn test_digest_display() {
+        let digest = abc_digest();
+        let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        assert_eq!(digest.to_string(), abc);
+        assert_eq!(format!("{:x}", digest), abc);
+        assert_eq!(format!("{:#x}", digest), format!("0x{}", abc));
+        assert_eq!(format!("{:X}", digest), abc.to_uppercase());
+        assert_eq!(digest.as_ref(), &hex::decode(abc).unwrap()[..]);
+        assert_eq!(digest, <[u8; 32]>::from(digest));
+        assert_eq!(<[u8; 32]>::from(digest), digest);
+        assert_eq!(Digest::from(digest.0), digest);
+    }
+
+    #[test]
+    fn test_digest128() {
+        let block = sha256_msg_block_sequence(b"abc".to_vec())[0].to_vec();
+        let state = update_state_ref(H.to_vec(), block).unwrap();
+        let z_i: Vec<Fr> = state.iter().map(|&x| Fr::from(x)).collect();
+        assert_eq!(digest128(&z_i), abc_digest().0[..16]);
+    }
+
+    #[test]
+    fn test_digest_format_from_str() {
+        assert_eq!("hex".parse(), Ok(DigestFormat::Hex));
+        assert_eq!("base64".parse(), Ok(DigestFormat::Base64));
+        assert_eq!("bytes".parse(), Ok(DigestFormat::Bytes));
+        assert!("binary".parse::<DigestFormat>().is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::folded_sha256::config::Variant;
+
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::SerializationError;
+use std::fmt;
+
+/// Errors returned by the folded SHA-256 drivers.
+#[derive(Debug)]
+pub enum FoldedSha256Error {
+    /// The input takes more blocks, padding included, than allowed by `FoldConfig::max_blocks`.
+    InputTooLarge {
+        num_blocks: usize,
+        max_blocks: usize,
+    },
+    /// The message is too long for the 64-bit length field of the SHA-256 padding.
+    MessageTooLong,
+    /// A split message prefix of `prefix_len` bytes does not end on a block boundary.
+    UnalignedSplit { prefix_len: usize },
+    /// The number of leaves is not supported by the Merkle tree folding.
+    InvalidMerkleTree { num_leaves: usize },
+    /// Folding step `step` kept failing after `attempts` attempts, `source` is the last error.
+    #[cfg(feature = "prover")]
+    StepFailed {
+        step: usize,
+        attempts: usize,
+        source: folding_schemes::Error,
+    },
+    /// The length carried by the final state is not a whole number of bytes, or its padded
+    /// message does not take `num_steps` blocks.
+    InvalidMessageLength {
+        length_in_bits: u64,
+        num_steps: usize,
+    },
+    /// Blocks of the `found` variant were fed to a folding of the `expected` variant.
+    VariantMismatch { expected: Variant, found: Variant },
+    /// The state after step `step` does not match its native recomputation.
+    SelfCheckFailed { step: usize },
+    /// The IVC proof received as step `step` of an incremental verification does not extend the
+    /// previous ones: it is not the next step, or it starts from another `z_0`.
+    UnexpectedStep { step: usize },
+    /// The IVC proof does not start from the initial state of the statement it claims, e.g. it
+    /// folds a suffix of a message from an intermediate state.
+    InvalidInitialState,
+    /// The public blocks committed to by a proof are not the public prefix of the verifier.
+    PublicPrefixMismatch,
+    /// The digest of the input is not one of the allowed digests of a set membership proof.
+    DigestNotInSet,
+    /// A replayed fold left `unused` bytes of the recorded randomness, i.e. it did not draw the
+    /// same randomness as the recorded fold.
+    ReplayDiverged { unused: usize },
+    /// The output key material is not the output of the HKDF-Expand folding.
+    HkdfOutputMismatch,
+    /// Two inputs folded side by side do not have the same number of padded blocks.
+    BlockCountMismatch { blocks_a: usize, blocks_b: usize },
+    /// The metadata header of a serialized proof does not match the settings of the verifier.
+    #[cfg(feature = "prover")]
+    ProofMetaMismatch {
+        expected: crate::folded_sha256::proof::ProofMeta,
+        found: crate::folded_sha256::proof::ProofMeta,
+    },
+    /// A serialized proof does not start with a well-formed metadata header.
+    #[cfg(feature = "prover")]
+    InvalidProofMeta,
+    /// The folding scheme preprocessing (commitment schemes setup) failed.
+    #[cfg(feature = "prover")]
+    Setup(folding_schemes::Error),
+    /// Error returned by the underlying folding scheme.
+    #[cfg(feature = "prover")]
+    FoldingScheme(folding_schemes::Error),
+    /// Error raised while synthesizing constraints outside of the folding scheme.
+    Synthesis(SynthesisError),
+    /// Error raised while serializing or deserializing a proof.
+    Serialization(SerializationError),
+}
+
+impl fmt::Display for FoldedSha256Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FoldedSha256Error::InputTooLarge {
+                num_blocks,
+                max_blocks,
+            } => write!(
+                f,
+                "input takes {} blocks, but at most {} are allowed",
+                num_blocks, max_blocks
+            ),
+            FoldedSha256Error::MessageTooLong => write!(
+                f,
+                "the message is too long for the 64-bit length field of the padding"
+            ),
+            FoldedSha256Error::UnalignedSplit { prefix_len } => write!(
+                f,
+                "a message can only be split on a block boundary, not after {} bytes",
+                prefix_len
+            ),
+            FoldedSha256Error::InvalidMerkleTree { num_leaves } => write!(
+                f,
+                "cannot fold a Merkle tree of {} leaves, it must be a non-zero power of two",
+                num_leaves
+            ),
+            #[cfg(feature = "prover")]
+            FoldedSha256Error::StepFailed {
+                step,
+                attempts,
+                source,
+            } => write!(
+                f,
+                "folding step {} failed after {} attempts: {}",
+                step, attempts, source
+            ),
+            FoldedSha256Error::InvalidMessageLength {
+                length_in_bits,
+                num_steps,
+            } => write!(
+                f,
+                "a length of {} bits is not consistent with {} folded blocks",
+                length_in_bits, num_steps
+            ),
+            FoldedSha256Error::VariantMismatch { expected, found } => write!(
+                f,
+                "cannot fold {:?} steps into a {:?} folding, the step function must not change",
+                found, expected
+            ),
+            FoldedSha256Error::SelfCheckFailed { step } => write!(
+                f,
+                "state after folding step {} does not match its native recomputation",
+                step
+            ),
+            FoldedSha256Error::UnexpectedStep { step } => write!(
+                f,
+                "IVC proof received as step {} does not extend the previously verified ones",
+                step
+            ),
+            FoldedSha256Error::InvalidInitialState => write!(
+                f,
+                "IVC proof does not start from the initial state of its statement"
+            ),
+            FoldedSha256Error::PublicPrefixMismatch => write!(
+                f,
+                "the public blocks of the proof are not the expected public prefix"
+            ),
+            FoldedSha256Error::DigestNotInSet => {
+                write!(
+                    f,
+                    "digest of the input is not in the set of allowed digests"
+                )
+            }
+            FoldedSha256Error::ReplayDiverged { unused } => write!(
+                f,
+                "the replayed fold diverged, leaving {} recorded bytes of randomness unused",
+                unused
+            ),
+            FoldedSha256Error::HkdfOutputMismatch => write!(
+                f,
+                "the output key material is not the output of the HKDF-Expand proof"
+            ),
+            FoldedSha256Error::BlockCountMismatch { blocks_a, blocks_b } => write!(
+                f,
+                "cannot fold inputs of {} and {} blocks side by side",
+                blocks_a, blocks_b
+            ),
+            #[cfg(feature = "prover")]
+            FoldedSha256Error::ProofMetaMismatch { expected, found } => write!(
+                f,
+                "proof metadata mismatch: the verifier expects {:?}, the proof is for {:?}",
+                expected, found
+            ),
+            #[cfg(feature = "prover")]
+            FoldedSha256Error::InvalidProofMeta => {
+                write!(
+                    f,
+                    "serialized proof does not start with a valid metadata header"
+                )
+            }
+            #[cfg(feature = "prover")]
+            FoldedSha256Error::Setup(e) => write!(f, "folding scheme setup failed: {}", e),
+            #[cfg(feature = "prover")]
+            FoldedSha256Error::FoldingScheme(e) => write!(f, "folding scheme error: {}", e),
+            FoldedSha256Error::Synthesis(e) => write!(f, "synthesis error: {}", e),
+            FoldedSha256Error::Serialization(e) => write!(f, "serialization error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FoldedSha256Error {}
+
+#[cfg(feature = "prover")]
+impl From<folding_schemes::Error> for FoldedSha256Error {
+    fn from(e: folding_schemes::Error) -> Self {
+        FoldedSha256Error::FoldingScheme(e)
+    }
+}
+
+impl From<SynthesisError> for FoldedSha256Error {
+    fn from(e: SynthesisError) -> Self {
+        FoldedSha256Error::Synthesis(e)
+    }
+}
+
+impl From<SerializationError> for FoldedSha256Error {
+    fn from(e: SerializationError) -> Self {
+        FoldedSha256Error::Serialization(e)
+    }
+}
+
+/// Reports a length or range violation of the inputs.
+///
+/// By default it is returned as `Err(error)`, as inputs may be untrusted. With the `strict-panics`
+/// feature it panics with `error` instead: trusted callers, which would unwrap anyway, get the
+/// failure at the faulty call.
+pub(crate) fn violation<T, E: fmt::Display>(error: E) -> Result<T, E> {
+    if cfg!(feature = "strict-panics") {
+        panic!("{}", error);
+    }
+    Err(error)
+}
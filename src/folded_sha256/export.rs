@@ -0,0 +1,283 @@
+//! Export of a folding step circuit to the circom `.r1cs` and `.wtns` binary formats, so that
+//! its constraints can be reused by other proving stacks (e.g. snarkjs).
+//!
+//! The step is synthesized on its own, outside of any folding scheme. The wires are laid out as
+//! circom expects them: the constant 1, the public outputs `z_{i+1}`, the public inputs `z_i`,
+//! the private inputs (the external inputs) and finally the intermediate wires.
+#![allow(non_snake_case)]
+use crate::folded_sha256::error::FoldedSha256Error;
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar};
+use ark_relations::r1cs::{
+    ConstraintMatrices, ConstraintSystem, OptimizationGoal, SynthesisError, SynthesisMode,
+};
+use folding_schemes::frontend::FCircuit;
+use std::io::{self, Write};
+
+/// The R1CS of one folding step, with the witness of one of its executions.
+pub struct StepR1CS<F: PrimeField> {
+    pub matrices: ConstraintMatrices<F>,
+    /// Full assignment, `[1, z_{i+1}, z_i, external_inputs, intermediate wires]`.
+    pub assignment: Vec<F>,
+    pub state_len: usize,
+    pub external_inputs_len: usize,
+}
+
+/// Synthesizes step `i` of `circuit` from the state `z_i` with `external_inputs`.
+pub fn export_step<F: PrimeField, FC: FCircuit<F>>(
+    circuit: &FC,
+    i: usize,
+    z_i: Vec<F>,
+    external_inputs: Vec<F>,
+) -> Result<StepR1CS<F>, FoldedSha256Error> {
+    let state_len = circuit.state_len();
+    let external_inputs_len = circuit.external_inputs_len();
+    let z_i1 = circuit.step_native(i, z_i.clone(), external_inputs.clone())?;
+
+    let cs = ConstraintSystem::<F>::new_ref();
+    // keep every constraint as is, the exported R1CS must match `generate_step_constraints`
+    cs.set_optimization_goal(OptimizationGoal::None);
+    cs.set_mode(SynthesisMode::Prove {
+        construct_matrices: true,
+    });
+
+    // the instance variables are assigned in allocation order, so the outputs go first
+    let z_i1Var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(z_i1))?;
+    let z_iVar = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(z_i))?;
+    let external_inputsVar = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(external_inputs))?;
+
+    let computed_z_i1Var =
+        circuit.generate_step_constraints(cs.clone(), i, z_iVar, external_inputsVar)?;
+    computed_z_i1Var.enforce_equal(&z_i1Var)?;
+
+    cs.finalize();
+    let matrices = cs.to_matrices().ok_or(SynthesisError::AssignmentMissing)?;
+    let cs = cs.borrow().ok_or(SynthesisError::MissingCS)?;
+    let mut assignment = cs.instance_assignment.clone();
+    assignment.extend_from_slice(&cs.witness_assignment);
+
+    Ok(StepR1CS {
+        matrices,
+        assignment,
+        state_len,
+        external_inputs_len,
+    })
+}
+
+impl<F: PrimeField> StepR1CS<F> {
+    /// Whether the assignment satisfies every constraint `<A_j, w> * <B_j, w> = <C_j, w>`.
+    pub fn is_satisfied(&self) -> bool {
+        let eval = |row: &[(F, usize)]| -> F {
+            row.iter()
+                .map(|(coeff, index)| *coeff * self.assignment[*index])
+                .sum()
+        };
+        (0..self.matrices.num_constraints).all(|j| {
+            eval(&self.matrices.a[j]) * eval(&self.matrices.b[j]) == eval(&self.matrices.c[j])
+        })
+    }
+
+    fn num_wires(&self) -> usize {
+        self.matrices.num_instance_variables + self.matrices.num_witness_variables
+    }
+
+    /// Writes the constraints in the `.r1cs` format (version 1): header, constraints and
+    /// wire-to-label map sections, each wire being its own label.
+    pub fn write_r1cs<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let n8 = field_size::<F>();
+
+        let mut header = Vec::new();
+        header.extend((n8 as u32).to_le_bytes());
+        header.extend(field_to_bytes(&F::MODULUS, n8));
+        header.extend((self.num_wires() as u32).to_le_bytes());
+        header.extend((self.state_len as u32).to_le_bytes()); // public outputs
+        header.extend((self.state_len as u32).to_le_bytes()); // public inputs
+        header.extend((self.external_inputs_len as u32).to_le_bytes()); // private inputs
+        header.extend((self.num_wires() as u64).to_le_bytes()); // labels
+        header.extend((self.matrices.num_constraints as u32).to_le_bytes());
+
+        let mut constraints = Vec::new();
+        for j in 0..self.matrices.num_constraints {
+            for row in [
+                &self.matrices.a[j],
+                &self.matrices.b[j],
+                &self.matrices.c[j],
+            ] {
+                constraints.extend((row.len() as u32).to_le_bytes());
+                for (coeff, index) in row {
+                    constraints.extend((*index as u32).to_le_bytes());
+                    constraints.extend(field_to_bytes(&coeff.into_bigint(), n8));
+                }
+            }
+        }
+
+        let labels: Vec<u8> = (0..self.num_wires() as u64)
+            .flat_map(|label| label.to_le_bytes())
+            .collect();
+
+        writer.write_all(b"r1cs")?;
+        writer.write_all(&1u32.to_le_bytes())?;
+        writer.write_all(&3u32.to_le_bytes())?;
+        write_section(writer, 1, &header)?;
+        write_section(writer, 2, &constraints)?;
+        write_section(writer, 3, &labels)
+    }
+
+    /// Writes the assignment in the `.wtns` format (version 2).
+    pub fn write_wtns<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let n8 = field_size::<F>();
+
+        let mut header = Vec::new();
+        header.extend((n8 as u32).to_le_bytes());
+        header.extend(field_to_bytes(&F::MODULUS, n8));
+        header.extend((self.assignment.len() as u32).to_le_bytes());
+
+        let witness: Vec<u8> = self
+            .assignment
+            .iter()
+            .flat_map(|x| field_to_bytes(&x.into_bigint(), n8))
+            .collect();
+
+        writer.write_all(b"wtns")?;
+        writer.write_all(&2u32.to_le_bytes())?;
+        writer.write_all(&2u32.to_le_bytes())?;
+        write_section(writer, 1, &header)?;
+        write_section(writer, 2, &witness)
+    }
+}
+
+/// Number of bytes of a field element, rounded up to whole 64-bit limbs as circom does.
+fn field_size<F: PrimeField>() -> usize {
+    (F::MODULUS_BIT_SIZE as usize).div_ceil(64) * 8
+}
+
+/// Little-endian encoding of `x` on `n8` bytes, in standard (non-Montgomery) form.
+fn field_to_bytes<B: BigInteger>(x: &B, n8: usize) -> Vec<u8> {
+    let mut bytes = x.to_bytes_le();
+    bytes.resize(n8, 0);
+    bytes
+}
+
+fn write_section<W: Write>(writer: &mut W, section_type: u32, content: &[u8]) -> io::Result<()> {
+    writer.write_all(&section_type.to_le_bytes())?;
+    writer.write_all(&(content.len() as u64).to_le_bytes())?;
+    writer.write_all(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::main::{FoldedSha256FCircuit, H, STATE_LEN};
+    use crate::folded_sha256::utils::{BlockExternalInputs, BLOCK_LENGTH_BYTES};
+    use ark_bn254::Fr;
+
+    fn read_u32(bytes: &[u8], offset: &mut usize) -> u32 {
+        let x = u32::from_le_bytes(bytes[*offset..*offset + 4].try_into().unwrap());
+        *offset += 4;
+        x
+    }
+
+    fn read_u64(bytes: &[u8], offset: &mut usize) -> u64 {
+        let x = u64::from_le_bytes(bytes[*offset..*offset + 8].try_into().unwrap());
+        *offset += 8;
+        x
+    }
+
+    fn read_field(bytes: &[u8], offset: &mut usize) -> Fr {
+        let x = Fr::from_le_bytes_mod_order(&bytes[*offset..*offset + 32]);
+        *offset += 32;
+        x
+    }
+
+    fn export() -> StepR1CS<Fr> {
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        let external_inputs = BlockExternalInputs::<Fr, _>::new(b"abc".to_vec())
+            .next()
+            .unwrap();
+        export_step(&circuit, 0, z_i, external_inputs).unwrap()
+    }
+
+    #[test]
+    fn test_export_step() {
+        let step = export();
+
+        // same number of constraints as the step synthesized directly, plus the output equalities
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        cs.set_optimization_goal(OptimizationGoal::None);
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let z_i = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || {
+            Ok(H.iter().map(|&x| Fr::from(x)).collect::<Vec<Fr>>())
+        })
+        .unwrap();
+        let external_inputs =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(vec![Fr::from(0u64); 64])).unwrap();
+        circuit
+            .generate_step_constraints(cs.clone(), 0, z_i, external_inputs)
+            .unwrap();
+        assert_eq!(
+            step.matrices.num_constraints,
+            cs.num_constraints() + STATE_LEN
+        );
+        assert_eq!(step.matrices.num_instance_variables, 1 + 2 * STATE_LEN);
+
+        assert!(step.is_satisfied());
+        let mut tampered = export();
+        tampered.assignment[1] += Fr::from(1u64);
+        assert!(!tampered.is_satisfied());
+    }
+
+    #[test]
+    fn test_exported_witness_satisfies_exported_r1cs() {
+        let step = export();
+        let mut r1cs = Vec::new();
+        step.write_r1cs(&mut r1cs).unwrap();
+        let mut wtns = Vec::new();
+        step.write_wtns(&mut wtns).unwrap();
+
+        // witness
+        assert_eq!(&wtns[..4], b"wtns");
+        let mut offset = 4 + 4 + 4 + 4 + 8 + 4 + 32;
+        let num_witness = read_u32(&wtns, &mut offset) as usize;
+        offset += 4 + 8;
+        let witness: Vec<Fr> = (0..num_witness)
+            .map(|_| read_field(&wtns, &mut offset))
+            .collect();
+        assert_eq!(offset, wtns.len());
+        assert_eq!(witness, step.assignment);
+        assert_eq!(witness[0], Fr::from(1u64));
+
+        // header section
+        assert_eq!(&r1cs[..4], b"r1cs");
+        let mut offset = 4 + 4 + 4 + 4 + 8;
+        assert_eq!(read_u32(&r1cs, &mut offset), 32);
+        offset += 32;
+        assert_eq!(read_u32(&r1cs, &mut offset) as usize, num_witness);
+        assert_eq!(read_u32(&r1cs, &mut offset) as usize, STATE_LEN);
+        assert_eq!(read_u32(&r1cs, &mut offset) as usize, STATE_LEN);
+        assert_eq!(read_u32(&r1cs, &mut offset) as usize, BLOCK_LENGTH_BYTES);
+        assert_eq!(read_u64(&r1cs, &mut offset) as usize, num_witness);
+        let num_constraints = read_u32(&r1cs, &mut offset) as usize;
+        assert_eq!(num_constraints, step.matrices.num_constraints);
+
+        // constraints section, evaluated on the exported witness
+        assert_eq!(read_u32(&r1cs, &mut offset), 2);
+        read_u64(&r1cs, &mut offset);
+        for _ in 0..num_constraints {
+            let mut evals = [Fr::from(0u64); 3];
+            for eval in evals.iter_mut() {
+                for _ in 0..read_u32(&r1cs, &mut offset) {
+                    let wire = read_u32(&r1cs, &mut offset) as usize;
+                    *eval += read_field(&r1cs, &mut offset) * witness[wire];
+                }
+            }
+            assert_eq!(evals[0] * evals[1], evals[2]);
+        }
+
+        // wire to label map section
+        assert_eq!(read_u32(&r1cs, &mut offset), 3);
+        assert_eq!(read_u64(&r1cs, &mut offset) as usize, 8 * num_witness);
+        assert_eq!(offset + 8 * num_witness, r1cs.len());
+    }
+}
@@ -0,0 +1,404 @@
+use crate::folded_sha256::constants::sha256::H;
+use crate::folded_sha256::digest::Digest;
+use crate::folded_sha256::error::violation;
+use crate::folded_sha256::utils::{
+    compress_block, message_length_in_bits, sha256_bit_msg_block_sequence, PaddedBlocks,
+    BLOCK_LENGTH_BYTES, MESSAGE_TOO_LONG,
+};
+
+use ark_serialize::CanonicalSerialize;
+
+/// Native streaming SHA-256 hasher, compressing each 512-bit block as soon as it is complete.
+///
+/// Besides starting from the standard IV, a hasher can be resumed from a midstate with
+/// `from_midstate`, which is how Merkle-Damgård hashing continues after an arbitrary number of
+/// bytes (not necessarily a multiple of 64).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sha256Hasher {
+    state: [u32; 8],
+    // bytes of the current, not yet complete, block
+    buffer: Vec<u8>,
+    // number of bytes absorbed so far, including the buffered ones
+    total_len: u64,
+}
+
+impl Default for Sha256Hasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sha256Hasher {
+    pub fn new() -> Self {
+        Self {
+            state: H,
+            buffer: Vec::with_capacity(BLOCK_LENGTH_BYTES),
+            total_len: 0,
+        }
+    }
+
+    /// Resumes hashing from `midstate`, the state after compressing all the complete blocks of
+    /// the first `total_len_so_far` bytes, with the trailing `buffered_bytes` of the partial block.
+    ///
+    /// The first `update` fills the buffered partial block before continuing.
+    pub fn from_midstate(
+        midstate: [u32; 8],
+        buffered_bytes: &[u8],
+        total_len_so_far: u64,
+    ) -> Result<Self, &'static str> {
+        if buffered_bytes.len() >= BLOCK_LENGTH_BYTES {
+            return violation("Buffered bytes must be shorter than a block");
+        }
+        if total_len_so_far % BLOCK_LENGTH_BYTES as u64 != buffered_bytes.len() as u64 {
+            return violation("Buffered bytes length does not match the total length");
+        }
+        Ok(Self {
+            state: midstate,
+            buffer: buffered_bytes.to_vec(),
+            total_len: total_len_so_far,
+        })
+    }
+
+    /// Returns the `(midstate, buffered_bytes, total_len_so_far)` triple accepted by
+    /// `from_midstate`.
+    pub fn midstate(&self) -> ([u32; 8], Vec<u8>, u64) {
+        (self.state, self.buffer.clone(), self.total_len)
+    }
+
+    /// Absorbs `data`. Fails, leaving the hasher unchanged, if the total length overflows 64 bits.
+    pub fn update(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        self.total_len = self
+            .total_len
+            .checked_add(data.len() as u64)
+            .ok_or(MESSAGE_TOO_LONG)?;
+        for &byte in data {
+            self.buffer.push(byte);
+            if self.buffer.len() == BLOCK_LENGTH_BYTES {
+                self.compress_buffer();
+            }
+        }
+        Ok(())
+    }
+
+    /// Pads the message absorbed so far and returns its digest. Fails if it is longer than
+    /// `MAX_MESSAGE_BYTES`.
+    pub fn finalize(mut self) -> Result<Digest, &'static str> {
+        let length_in_bits = message_length_in_bits(self.total_len)?;
+
+        self.buffer.push(128u8);
+        if self.buffer.len() > BLOCK_LENGTH_BYTES - 8 {
+            self.buffer.resize(BLOCK_LENGTH_BYTES, 0u8);
+            self.compress_buffer();
+        }
+        self.buffer.resize(BLOCK_LENGTH_BYTES - 8, 0u8);
+        self.buffer.extend_from_slice(&length_in_bits.to_be_bytes());
+        self.compress_buffer();
+
+        Ok(Digest::from_words(self.state))
+    }
+
+    fn compress_buffer(&mut self) {
+        // the buffer always holds exactly one block here
+        let block: &[u8; BLOCK_LENGTH_BYTES] = self.buffer.as_slice().try_into().unwrap();
+        self.state = compress_block(&self.state, block);
+        self.buffer.clear();
+    }
+}
+
+/// Native SHA-256 digest of `input`.
+pub fn sha256(input: &[u8]) -> Digest {
+    let mut hasher = Sha256Hasher::new();
+    // a slice held in memory is far shorter than `MAX_MESSAGE_BYTES`
+    hasher.update(input).unwrap();
+    hasher.finalize().unwrap()
+}
+
+/// Native SHA-256 digest of the first `length_in_bits` bits of `input`, see
+/// `utils::sha256_bit_msg_block_sequence` for the bit order.
+pub fn sha256_bits(input: &[u8], length_in_bits: u64) -> Result<Digest, &'static str> {
+    let mut state = H;
+    for block in sha256_bit_msg_block_sequence(input.to_vec(), length_in_bits)? {
+        state = compress_block(&state, &block);
+    }
+    Ok(Digest::from_words(state))
+}
+
+/// Native SHA-256 digest of a sequence of `records`, each of them serialized (compressed) with
+/// `CanonicalSerialize` and prefixed with its serialized length as a 64-bit little-endian
+/// integer, the encoding `ark-serialize` uses for lengths.
+///
+/// The prefix makes the encoding unambiguous, so that e.g. the records `[ab, c]` and `[a, bc]`
+/// do not hash to the same digest.
+pub fn sha256_records<T: CanonicalSerialize>(records: &[T]) -> Digest {
+    let mut hasher = Sha256Hasher::new();
+    let mut bytes = Vec::new();
+    for record in records {
+        bytes.clear();
+        // serializing to a vector cannot fail
+        record.serialize_compressed(&mut bytes).unwrap();
+        // as for `sha256`, records held in memory cannot reach `MAX_MESSAGE_BYTES`
+        hasher.update(&(bytes.len() as u64).to_le_bytes()).unwrap();
+        hasher.update(&bytes).unwrap();
+    }
+    hasher.finalize().unwrap()
+}
+
+/// Native SHA-256 digest of `len` zero bytes, the input hashed by the example and the benchmarks.
+///
+/// # Panics
+///
+/// Panics if `len` is larger than `MAX_MESSAGE_BYTES`.
+pub fn zero_digest(len: usize) -> Digest {
+    let mut hasher = Sha256Hasher::new();
+    let zeros = [0u8; BLOCK_LENGTH_BYTES];
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(BLOCK_LENGTH_BYTES);
+        hasher.update(&zeros[..chunk]).unwrap();
+        remaining -= chunk;
+    }
+    hasher.finalize().unwrap()
+}
+
+/// Lazily compresses the padded blocks of `input`, yielding after each block the state as a
+/// `Digest`, e.g. to show the hashing progress.
+///
+/// Only the last snapshot is the digest of `input`, the other ones are intermediate states (the
+/// states `z_i` of the folding, in bytes).
+pub fn digest_stream<I: IntoIterator<Item = u8>>(input: I) -> impl Iterator<Item = Digest> {
+    let mut state = H;
+    PaddedBlocks::new(input).map(move |block| {
+        state = compress_block(&state, &block);
+        Digest::from_words(state)
+    })
+}
+
+/// Native SHA-256 digest of `input` as a lowercase hex string, exported to JavaScript with the
+/// `wasm` feature. It only relies on the native path, which builds without the `prover` feature
+/// and its heavy proving dependencies.
+#[cfg_attr(feature = "wasm", wasm_bindgen::prelude::wasm_bindgen)]
+pub fn sha256_hex(input: &[u8]) -> String {
+    sha256(input).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::utils::{finalize, sha256_msg_block_sequence, update_state_ref};
+    use ark_serialize::Compress;
+
+    fn reference_digest(input: Vec<u8>) -> Vec<u8> {
+        let mut state = H.to_vec();
+        for block in sha256_msg_block_sequence(input) {
+            state = update_state_ref(state, block.to_vec()).unwrap();
+        }
+        finalize(state)
+    }
+
+    #[test]
+    fn test_resume_from_unaligned_midstate() {
+        let input: Vec<u8> = (0..164).map(|i| i as u8).collect();
+
+        let mut hasher = Sha256Hasher::new();
+        hasher.update(&input[..100]).unwrap();
+        let (midstate, buffered_bytes, total_len) = hasher.midstate();
+        assert_eq!(buffered_bytes.len(), 36);
+        assert_eq!(total_len, 100);
+
+        let mut resumed =
+            Sha256Hasher::from_midstate(midstate, &buffered_bytes, total_len).unwrap();
+        resumed.update(&input[100..]).unwrap();
+
+        assert_eq!(
+            resumed.finalize().unwrap().0.to_vec(),
+            reference_digest(input)
+        );
+    }
+
+    #[test]
+    fn test_length_limits() {
+        use crate::folded_sha256::utils::MAX_MESSAGE_BYTES;
+
+        // the longest message ends 63 bytes into its last block
+        let buffered = [0u8; BLOCK_LENGTH_BYTES - 1];
+        let longest = Sha256Hasher::from_midstate(H, &buffered, MAX_MESSAGE_BYTES).unwrap();
+        assert!(longest.clone().finalize().is_ok());
+
+        let mut too_long = longest;
+        too_long.update(&[0u8]).unwrap();
+        assert!(too_long.finalize().is_err());
+
+        // the total length overflows 64 bits, the hasher is left unchanged
+        let mut hasher = Sha256Hasher::from_midstate(H, &buffered, u64::MAX).unwrap();
+        let before = hasher.clone();
+        assert!(hasher.update(&[0u8]).is_err());
+        assert_eq!(hasher, before);
+        assert!(hasher.update(&[]).is_ok());
+    }
+
+    #[cfg(not(feature = "strict-panics"))]
+    #[test]
+    fn test_from_midstate_rejects_inconsistent_buffer() {
+        assert!(Sha256Hasher::from_midstate(H, &[0u8; 36], 101).is_err());
+        assert!(Sha256Hasher::from_midstate(H, &[0u8; 64], 64).is_err());
+    }
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn test_digest_stream() {
+        let snapshots: Vec<Digest> = digest_stream(b"abc".iter().copied()).collect();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0], sha256(b"abc"));
+
+        // one snapshot per block, the intermediate ones are the midstates
+        let input = vec![5u8; 100];
+        let snapshots: Vec<Digest> = digest_stream(input.clone()).collect();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1], sha256(&input));
+        let first_block = sha256_msg_block_sequence(input)[0];
+        assert_eq!(
+            snapshots[0],
+            Digest::from_words(compress_block(&H, &first_block))
+        );
+    }
+
+    #[test]
+    fn test_sha256_bits_nist_vectors() {
+        // bit-oriented vectors of the NIST SHAVS, a single 0 bit and the 5 bits 01101
+        assert_eq!(
+            sha256_bits(&[0x00], 1).unwrap().to_string(),
+            "bd4f9e98beb68c6ead3243b1b4c7fed75fa4feaab1f84795cbd8a98676a2a375"
+        );
+        assert_eq!(
+            sha256_bits(&[0x68], 5).unwrap().to_string(),
+            "d6d3e02a31a84a8caa9718ed6c2057be09db45e7823eb5079ce7a573a3760f95"
+        );
+        assert_eq!(sha256_bits(b"abc", 24).unwrap(), sha256(b"abc"));
+    }
+
+    #[test]
+    fn test_zero_digest() {
+        assert_eq!(zero_digest(0), sha256(&[]));
+        assert_eq!(
+            zero_digest(64).to_string(),
+            "f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a92759fb4b"
+        );
+        assert_eq!(
+            zero_digest(1000).to_string(),
+            "541b3e9daa09b20bf85fa273e5cbd3e80185aa4ec298e765db87742b70138a53"
+        );
+    }
+
+    // slow: 100k iterated hashes, use `cargo test --release -- --ignored`
+    //
+    // NIST CAVP Monte Carlo test for SHA-256: each checkpoint `MD_j` is the last of 1000 digests
+    // chained as `MD_i = SHA256(MD_{i-3} || MD_{i-2} || MD_{i-1})`, starting from
+    // `MD_0 = MD_1 = MD_2 = Seed`, and is the seed of the next checkpoint. The seed and the
+    // checkpoints are the ones of `SHA256Monte.rsp` (byte-oriented SHAVS vectors).
+    #[test]
+    #[ignore]
+    fn test_nist_monte_carlo() {
+        let checkpoints = [
+            (
+                0,
+                "e93c330ae5447738c8aa85d71a6c80f2a58381d05872d26bdd39f1fcd4f2b788",
+            ),
+            (
+                1,
+                "2e78f8c8772ea7c9331d41ed3f9cdf27d8f514a99342ee766ee3b8b0d0b121c0",
+            ),
+            (
+                2,
+                "d6a23dff1b7f2eddc1a212f8a218397523a799b07386a30692fd6fe9d2bf0944",
+            ),
+            (
+                49,
+                "44b6a895058ed3f31a5549407af8f788631f8a6eb8c0a5f2e15facc9190b5672",
+            ),
+            (
+                98,
+                "3326899b575f93cdaff757f8ab7c3996a2fe930450d5002d4575f4e4cc4b4360",
+            ),
+            (
+                99,
+                "6a912ba4188391a78e6f13d88ed2d14e13afce9db6f7dcbf4a48c24f3db02778",
+            ),
+        ];
+
+        let mut seed: [u8; 32] =
+            hex::decode("6d1e72ad03ddeb5de891e572e2396f8da015d899ef0e79503152d6010a3fe691")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let mut checkpoints = checkpoints.iter().peekable();
+        for j in 0..100 {
+            let mut md = [seed; 3];
+            for _ in 3..1003 {
+                let mut message = Vec::with_capacity(96);
+                for digest in &md {
+                    message.extend_from_slice(digest);
+                }
+                md = [md[1], md[2], sha256(&message).0];
+            }
+            seed = md[2];
+
+            if let Some((_, expected)) = checkpoints.next_if(|(count, _)| *count == j) {
+                assert_eq!(hex::encode(seed), *expected, "checkpoint {}", j);
+            }
+        }
+        assert!(checkpoints.next().is_none());
+    }
+
+    #[derive(CanonicalSerialize)]
+    struct Record {
+        id: u32,
+        name: Vec<u8>,
+    }
+
+    #[test]
+    fn test_sha256_records() {
+        let records = vec![
+            Record {
+                id: 1,
+                name: b"alice".to_vec(),
+            },
+            Record {
+                id: 2,
+                name: b"bob".to_vec(),
+            },
+        ];
+
+        let mut manual = Vec::new();
+        for record in &records {
+            let mut bytes = Vec::new();
+            record.serialize_compressed(&mut bytes).unwrap();
+            assert_eq!(bytes.len(), record.serialized_size(Compress::Yes));
+            manual.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            manual.extend_from_slice(&bytes);
+        }
+        assert_eq!(sha256_records(&records), sha256(&manual));
+
+        // the length prefixes keep the record boundaries
+        let split = |a: &[u8], b: &[u8]| sha256_records(&[a.to_vec(), b.to_vec()]);
+        assert_ne!(split(b"ab", b"c"), split(b"a", b"bc"));
+    }
+
+    // Only built by `cargo test --no-default-features`: the native path, and these tests, must
+    // compile without the proving stack, as for wasm32-unknown-unknown.
+    #[cfg(not(feature = "prover"))]
+    #[test]
+    fn test_native_path_without_prover() {
+        assert_eq!(sha256_hex(b"abc"), sha256(b"abc").to_string());
+    }
+}
@@ -0,0 +1,559 @@
+//! HMAC-SHA256 (RFC 2104) and HKDF-Expand (RFC 5869), natively and as a folding.
+//!
+//! HKDF-Expand iterates HMAC with the pseudorandom key `prk`:
+//! `T(i) = HMAC(prk, T(i-1) || info || i)` for `i = 1, 2, ...`, with `T(0)` empty, and outputs
+//! the first `length` bytes of `T(1) || T(2) || ...`. Each HMAC is two SHA-256 hashes, the inner
+//! one keyed with `prk ^ ipad` and the outer one with `prk ^ opad`. Their first block only
+//! depends on the key, so both resume from the midstates of `hmac_midstates`.
+//!
+//! `FoldedSha256HkdfExpandFCircuit` computes one `T(i)` per folding step, see its documentation.
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit::{bytes_from_fp, one_compression_round, words_to_bytes_be};
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::constants::sha256::H;
+use crate::folded_sha256::digest::digest_from_state;
+use crate::folded_sha256::error::{violation, FoldedSha256Error};
+use crate::folded_sha256::hasher::{sha256, Sha256Hasher};
+use crate::folded_sha256::main::{bigint_to_u32, fold, NovaFor, NovaParamsFor, STATE_LEN};
+use crate::folded_sha256::poseidon_commit::{absorb_block_native, absorb_block_var};
+use crate::folded_sha256::utils::{compress_block, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_crypto_primitives::sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig, PoseidonSponge},
+    CryptographicSponge,
+};
+use ark_ff::{One, Zero};
+use ark_grumpkin::Projective as Projective2;
+use ark_r1cs_std::{
+    alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, uint32::UInt32, uint8::UInt8,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::folding::nova::IVCProof;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+use folding_schemes::{Error, FoldingScheme};
+
+/// Length in bytes of an HMAC-SHA256 output, `HashLen` in RFC 5869.
+pub const HASH_LEN: usize = 32;
+
+/// Largest output of `hkdf_expand`, 255 HMAC outputs.
+pub const MAX_HKDF_EXPAND_LEN: usize = 255 * HASH_LEN;
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+/// Index of the Poseidon hash of the HMAC midstates in the state.
+const PRK_HASH_INDEX: usize = STATE_LEN;
+/// Index of the counter `i` of the next HMAC output in the state.
+const COUNTER_INDEX: usize = STATE_LEN + 1;
+/// Index of the Poseidon accumulator of the outputs in the state.
+const ACC_INDEX: usize = STATE_LEN + 2;
+
+/// The SHA-256 states after compressing `key ^ ipad`, resp. `key ^ opad`, from the IV: the
+/// midstates of the inner and outer hashes of HMAC, which only depend on the key. Keys longer
+/// than a block are hashed first.
+pub fn hmac_midstates(key: &[u8]) -> ([u32; 8], [u32; 8]) {
+    let mut block_key = [0u8; BLOCK_LENGTH_BYTES];
+    if key.len() > BLOCK_LENGTH_BYTES {
+        block_key[..HASH_LEN].copy_from_slice(&sha256(key).0);
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+    let midstate = |pad: u8| compress_block(&H, &block_key.map(|b| b ^ pad));
+    (midstate(IPAD), midstate(OPAD))
+}
+
+/// HMAC-SHA256 of `message` under the key of `midstates`, see `hmac_midstates`.
+fn hmac_from_midstates(midstates: &([u32; 8], [u32; 8]), message: &[u8]) -> [u8; HASH_LEN] {
+    // both hashes resume after their key block, and a message held in memory is far shorter
+    // than `MAX_MESSAGE_BYTES`
+    let resume =
+        |midstate| Sha256Hasher::from_midstate(midstate, &[], BLOCK_LENGTH_BYTES as u64).unwrap();
+    let mut inner = resume(midstates.0);
+    inner.update(message).unwrap();
+    let mut outer = resume(midstates.1);
+    outer.update(&inner.finalize().unwrap().0).unwrap();
+    outer.finalize().unwrap().0
+}
+
+/// Native HMAC-SHA256 of `message` under `key`.
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; HASH_LEN] {
+    hmac_from_midstates(&hmac_midstates(key), message)
+}
+
+/// Message `T(i-1) || info || i` of the HMAC of `T(i)`, with `previous` the output `T(i-1)`,
+/// ignored for `i = 1`.
+fn hkdf_message(previous: &[u8], info: &[u8], i: u8) -> Vec<u8> {
+    let previous = if i == 1 { &[][..] } else { previous };
+    [previous, info, &[i]].concat()
+}
+
+/// HKDF-Expand of RFC 5869 with HMAC-SHA256: derives `length` bytes from the pseudorandom key
+/// `prk` (e.g. the output of HKDF-Extract) and the context `info`, see the module documentation.
+///
+/// Returns an error if `length` exceeds `MAX_HKDF_EXPAND_LEN`.
+pub fn hkdf_expand(prk: &[u8], info: &[u8], length: usize) -> Result<Vec<u8>, &'static str> {
+    if length > MAX_HKDF_EXPAND_LEN {
+        return violation("HKDF-Expand output length must be at most 255 * 32 bytes");
+    }
+
+    let midstates = hmac_midstates(prk);
+    let mut okm = Vec::with_capacity(length);
+    let mut t = [0u8; HASH_LEN];
+    for i in 1..=length.div_ceil(HASH_LEN) as u8 {
+        t = hmac_from_midstates(&midstates, &hkdf_message(&t, info, i));
+        okm.extend_from_slice(&t);
+    }
+    okm.truncate(length);
+    Ok(okm)
+}
+
+/// Number of blocks compressed by each step of the folding of HKDF-Expand with `info`: those of
+/// the longest inner message `T(i-1) || info || i`, and the outer block.
+pub fn hkdf_blocks_per_step(info_len: usize) -> usize {
+    let inner_len = HASH_LEN + info_len + 1;
+    (inner_len + padding_after_key_block(inner_len).len()) / BLOCK_LENGTH_BYTES + 1
+}
+
+/// Padding of a message of `len` bytes hashed after one key block: the `0x80` byte, the zeros,
+/// and the 64-bit length in bits of the key block and the message.
+fn padding_after_key_block(len: usize) -> Vec<u8> {
+    let num_zeros = (BLOCK_LENGTH_BYTES - (len + 9) % BLOCK_LENGTH_BYTES) % BLOCK_LENGTH_BYTES;
+    let mut padding = vec![0x80];
+    padding.resize(1 + num_zeros, 0);
+    padding.extend_from_slice(&(((BLOCK_LENGTH_BYTES + len) * 8) as u64).to_be_bytes());
+    padding
+}
+
+/// In-circuit `message || padding_after_key_block(message.len())`.
+fn pad_after_key_block(mut message: Vec<UInt8<Fr>>) -> Vec<UInt8<Fr>> {
+    let padding = padding_after_key_block(message.len());
+    message.extend(padding.into_iter().map(UInt8::constant));
+    message
+}
+
+/// The 16 words of the HMAC midstates, inner first.
+fn midstate_words(midstates: &([u32; 8], [u32; 8])) -> Vec<Fr> {
+    midstates
+        .0
+        .iter()
+        .chain(&midstates.1)
+        .map(|&x| Fr::from(x))
+        .collect()
+}
+
+/// Poseidon hash of the HMAC midstates of `prk`, the public binding of the key in `z_0`.
+///
+/// As for `prf::key_hash`, it hides a uniformly random key, not a guessable one.
+pub fn prk_hash(poseidon_config: &PoseidonConfig<Fr>, prk: &[u8]) -> Fr {
+    let mut sponge = PoseidonSponge::<Fr>::new(poseidon_config);
+    sponge.absorb(&midstate_words(&hmac_midstates(prk)));
+    sponge.squeeze_field_elements(1)[0]
+}
+
+/// Parameters of `FoldedSha256HkdfExpandFCircuit`.
+#[derive(Clone, Debug)]
+pub struct HkdfExpandParams {
+    /// The secret pseudorandom key. As for `prf::FoldedSha256PrfFCircuit`, the verifier does
+    /// not need it, only the witnesses depend on it.
+    pub prk: Vec<u8>,
+    /// The public context, a constant of the step circuit.
+    pub info: Vec<u8>,
+}
+
+/// Folding circuit of HKDF-Expand, computing the HMAC output `T(i)` at step `i - 1`.
+///
+/// The state is `z_i = [T_0, ..., T_7, prk_hash, i, acc]`: the last output `T(i-1)` as 8
+/// big-endian words (zeros in `z_0`), the Poseidon hash of the HMAC midstates of the key (see
+/// `prk_hash`), the counter of the next output, starting at 1, and a Poseidon accumulator of the
+/// outputs, see `poseidon_commit::absorb_block_native`. There are no external inputs.
+///
+/// Every step allocates the midstates as witnesses and enforces their hash is `prk_hash`, then
+/// compresses the padded inner message `T(i-1) || info || i` (without `T(i-1)` for `i = 1`)
+/// from the inner midstate and the inner digest from the outer midstate. The counter byte is
+/// range-checked, so at most 255 steps can be folded. A step costs the compressions of the
+/// longest inner message and of the outer block, and a Poseidon hash of 16 words.
+///
+/// As for `prf`, the Nova IVC proof carries the witnesses, hence reveals the midstates, from
+/// which anyone can compute the HMAC: only a zero-knowledge Decider proof hides them.
+#[derive(Clone, Debug)]
+pub struct FoldedSha256HkdfExpandFCircuit {
+    midstates: ([u32; 8], [u32; 8]),
+    info: Vec<u8>,
+    poseidon_config: PoseidonConfig<Fr>,
+}
+
+impl FCircuit<Fr> for FoldedSha256HkdfExpandFCircuit {
+    type Params = HkdfExpandParams;
+
+    fn new(params: Self::Params) -> Result<Self, Error> {
+        Ok(Self {
+            midstates: hmac_midstates(&params.prk),
+            info: params.info,
+            poseidon_config: poseidon_canonical_config::<Fr>(),
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        ACC_INDEX + 1
+    }
+    fn external_inputs_len(&self) -> usize {
+        0
+    }
+
+    fn step_native(
+        &self,
+        _i: usize,
+        z_i: Vec<Fr>,
+        _external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let mut sponge = PoseidonSponge::<Fr>::new(&self.poseidon_config);
+        sponge.absorb(&midstate_words(&self.midstates));
+        if sponge.squeeze_field_elements::<Fr>(1)[0] != z_i[PRK_HASH_INDEX] {
+            return Err(Error::Other(
+                "the key does not match the key hash of the state".to_string(),
+            ));
+        }
+        let counter = z_i[COUNTER_INDEX];
+        if counter > Fr::from(255u64) {
+            return Err(Error::Other(
+                "HKDF-Expand outputs at most 255 HMAC blocks".to_string(),
+            ));
+        }
+
+        let previous = digest_from_state(&z_i[..STATE_LEN]);
+        let message = hkdf_message(&previous.0, &self.info, bigint_to_u32(counter) as u8);
+        let t_i = hmac_from_midstates(&self.midstates, &message);
+
+        let mut z_i1: Vec<Fr> = t_i
+            .chunks(4)
+            .map(|word| Fr::from(u32::from_be_bytes(word.try_into().unwrap())))
+            .collect();
+        let acc = absorb_block_native(&self.poseidon_config, z_i[ACC_INDEX], &z_i1);
+        z_i1.extend([z_i[PRK_HASH_INDEX], counter + Fr::one(), acc]);
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        _i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        if z_i.len() != self.state_len() || !external_inputs.is_empty() {
+            return violation(SynthesisError::Unsatisfiable);
+        }
+
+        // the midstate words are range-checked by their allocation as `UInt32`
+        let midstates = self
+            .midstates
+            .0
+            .iter()
+            .chain(&self.midstates.1)
+            .map(|&x| UInt32::new_witness(cs.clone(), || Ok(x)))
+            .collect::<Result<Vec<UInt32<Fr>>, SynthesisError>>()?;
+        let midstate_words = midstates
+            .iter()
+            .map(|x| x.to_fp())
+            .collect::<Result<Vec<FpVar<Fr>>, SynthesisError>>()?;
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &self.poseidon_config);
+        sponge.absorb(&midstate_words)?;
+        sponge.squeeze_field_elements(1)?[0].enforce_equal(&z_i[PRK_HASH_INDEX])?;
+        let (inner_midstate, outer_midstate) = midstates.split_at(STATE_LEN);
+
+        // the previous output words are outputs of the previous step, or zeros in `z_0`
+        let previous = z_i[..STATE_LEN]
+            .iter()
+            .map(|x| Ok(UInt32::from_fp(x)?.0))
+            .collect::<Result<Vec<UInt32<Fr>>, SynthesisError>>()?;
+        let counter = &z_i[COUNTER_INDEX];
+        // enforces the counter is < 256
+        let counter_byte = bytes_from_fp(std::slice::from_ref(counter))?.remove(0);
+        let is_first = counter.is_eq(&FpVar::Constant(Fr::one()))?;
+
+        // both layouts of the inner message, the first one being shorter by `T(0)`
+        let info: Vec<UInt8<Fr>> = self.info.iter().map(|&b| UInt8::constant(b)).collect();
+        let mut first = pad_after_key_block([info.clone(), vec![counter_byte.clone()]].concat());
+        let next =
+            pad_after_key_block([words_to_bytes_be(&previous)?, info, vec![counter_byte]].concat());
+        let num_first_blocks = first.len() / BLOCK_LENGTH_BYTES;
+        first.resize(next.len(), UInt8::constant(0));
+        let message = first
+            .iter()
+            .zip(&next)
+            .map(|(a, b)| is_first.select(a, b))
+            .collect::<Result<Vec<UInt8<Fr>>, SynthesisError>>()?;
+
+        let mut state = inner_midstate.to_vec();
+        for (j, block) in message.chunks(BLOCK_LENGTH_BYTES).enumerate() {
+            let compressed = one_compression_round(&mut state.clone(), &block.to_vec())?;
+            state = if j < num_first_blocks {
+                compressed
+            } else {
+                // past the end of the first message, whose state is left unchanged
+                compressed
+                    .iter()
+                    .zip(&state)
+                    .map(|(c, s)| is_first.select(s, c))
+                    .collect::<Result<Vec<UInt32<Fr>>, SynthesisError>>()?
+            };
+        }
+
+        let outer_block = pad_after_key_block(words_to_bytes_be(&state)?);
+        let t_i = one_compression_round(&mut outer_midstate.to_vec(), &outer_block)?;
+
+        let mut z_i1 = t_i
+            .iter()
+            .map(|x| x.to_fp())
+            .collect::<Result<Vec<FpVar<Fr>>, SynthesisError>>()?;
+        let acc = absorb_block_var(cs, &self.poseidon_config, &z_i[ACC_INDEX], &z_i1)?;
+        z_i1.extend([z_i[PRK_HASH_INDEX].clone(), counter + Fr::one(), acc]);
+        Ok(z_i1)
+    }
+}
+
+/// The initial state `[0, ..., 0, prk_hash, 1, 0]` of an HKDF-Expand folding, see `prk_hash`.
+pub fn hkdf_expand_initial_state(prk_hash: Fr) -> Vec<Fr> {
+    let mut z_0 = vec![Fr::zero(); STATE_LEN];
+    z_0.extend([prk_hash, Fr::one(), Fr::zero()]);
+    z_0
+}
+
+/// Folds HKDF-Expand of `prk` and `info`, one step per HMAC output of `hkdf_expand(prk, info,
+/// length)`.
+///
+/// Returns `FoldedSha256Error::InputTooLarge` if it takes more than 255 steps, or if its steps
+/// compress more than `config.max_blocks` blocks, `hkdf_blocks_per_step` each.
+pub fn prove_hkdf_expand<R: RngCore + CryptoRng>(
+    prk: &[u8],
+    info: &[u8],
+    length: usize,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256HkdfExpandFCircuit>,
+        NovaParamsFor<FoldedSha256HkdfExpandFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    let num_steps = length.div_ceil(HASH_LEN);
+    let blocks_per_step = hkdf_blocks_per_step(info.len());
+    // the counter byte bounds the number of steps
+    let max_blocks = config
+        .max_blocks
+        .map_or(255 * blocks_per_step, |max_blocks| {
+            max_blocks.min(255 * blocks_per_step)
+        });
+    if num_steps * blocks_per_step > max_blocks {
+        return Err(FoldedSha256Error::InputTooLarge {
+            num_blocks: num_steps * blocks_per_step,
+            max_blocks,
+        });
+    }
+
+    fold(
+        FoldedSha256HkdfExpandFCircuit::new(HkdfExpandParams {
+            prk: prk.to_vec(),
+            info: info.to_vec(),
+        })?,
+        hkdf_expand_initial_state(prk_hash(&poseidon_canonical_config::<Fr>(), prk)),
+        std::iter::repeat_n(vec![], num_steps),
+        config,
+        rng,
+    )
+}
+
+/// Verifies the IVC proof produced by `prove_hkdf_expand`, for the key committed to by
+/// `prk_hash` and the `info` of the circuit of `vp`, and checks `okm` is its output.
+///
+/// Returns `FoldedSha256Error::InvalidInitialState` if the proof does not start from
+/// `hkdf_expand_initial_state(prk_hash)`, and `FoldedSha256Error::HkdfOutputMismatch` if `okm`
+/// is not the output of the folded steps.
+pub fn verify_hkdf_expand(
+    vp: <NovaFor<FoldedSha256HkdfExpandFCircuit> as FoldingScheme<
+        Projective,
+        Projective2,
+        FoldedSha256HkdfExpandFCircuit,
+    >>::VerifierParam,
+    ivc_proof: IVCProof<Projective, Projective2>,
+    prk_hash: Fr,
+    okm: &[u8],
+) -> Result<(), FoldedSha256Error> {
+    if ivc_proof.z_0 != hkdf_expand_initial_state(prk_hash) {
+        return Err(FoldedSha256Error::InvalidInitialState);
+    }
+    let num_steps = bigint_to_u32(ivc_proof.i) as usize;
+    let z_n = ivc_proof.z_i.clone();
+    NovaFor::<FoldedSha256HkdfExpandFCircuit>::verify(vp, ivc_proof)?;
+
+    if num_steps != okm.len().div_ceil(HASH_LEN) {
+        return Err(FoldedSha256Error::HkdfOutputMismatch);
+    }
+    if num_steps == 0 {
+        return Ok(());
+    }
+    // the last output is in the final state, possibly truncated in `okm`
+    let last = digest_from_state(&z_n[..STATE_LEN]);
+    let (full, tail) = okm.split_at((num_steps - 1) * HASH_LEN);
+    if tail != &last.0[..tail.len()] {
+        return Err(FoldedSha256Error::HkdfOutputMismatch);
+    }
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    let acc = full
+        .chunks(HASH_LEN)
+        .chain([&last.0[..]])
+        .fold(Fr::zero(), |acc, t| {
+            let words: Vec<Fr> = t
+                .chunks(4)
+                .map(|word| Fr::from(u32::from_be_bytes(word.try_into().unwrap())))
+                .collect();
+            absorb_block_native(&poseidon_config, acc, &words)
+        });
+    if acc != z_n[ACC_INDEX] {
+        return Err(FoldedSha256Error::HkdfOutputMismatch);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use hex_literal::hex;
+    use rand_chacha::ChaCha20Rng;
+
+    // RFC 5869, test case 1: 42 bytes, i.e. two HMAC outputs, the second one truncated
+    const PRK: [u8; 32] = hex!("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5");
+    const INFO: [u8; 10] = hex!("f0f1f2f3f4f5f6f7f8f9");
+    const OKM: [u8; 42] = hex!(
+        "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+    );
+
+    #[test]
+    fn test_hmac_sha256_rfc4231() {
+        // test case 2
+        assert_eq!(
+            hmac_sha256(b"Jefe", b"what do ya want for nothing?"),
+            hex!("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        // test case 6, a key longer than a block
+        assert_eq!(
+            hmac_sha256(
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            ),
+            hex!("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54")
+        );
+    }
+
+    #[test]
+    fn test_hkdf_expand_rfc5869() {
+        assert_eq!(hkdf_expand(&PRK, &INFO, 42).unwrap(), OKM);
+
+        // test case 3: no info
+        let prk = hex!("19ef24a32c717b167f33a91d6f648bdf96596776afdb6377ac434c1c293ccb04");
+        assert_eq!(
+            hkdf_expand(&prk, &[], 42).unwrap(),
+            hex!(
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8"
+            )
+        );
+
+        assert!(hkdf_expand(&prk, &INFO, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(not(feature = "strict-panics"))]
+    fn test_hkdf_expand_rejects_long_output() {
+        let prk = [0u8; HASH_LEN];
+        assert_eq!(
+            hkdf_expand(&prk, &[], MAX_HKDF_EXPAND_LEN).unwrap().len(),
+            MAX_HKDF_EXPAND_LEN
+        );
+        assert!(hkdf_expand(&prk, &[], MAX_HKDF_EXPAND_LEN + 1).is_err());
+    }
+
+    #[test]
+    fn test_prove_hkdf_expand_counts_blocks() {
+        // the inner message takes 1 block with 10 bytes of info, 2 with 30 bytes
+        assert_eq!(hkdf_blocks_per_step(INFO.len()), 2);
+        assert_eq!(hkdf_blocks_per_step(30), 3);
+
+        let config = FoldConfig {
+            max_blocks: Some(3),
+            ..Default::default()
+        };
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        assert!(matches!(
+            prove_hkdf_expand(&PRK, &INFO, OKM.len(), &config, &mut rng),
+            Err(FoldedSha256Error::InputTooLarge {
+                num_blocks: 4,
+                max_blocks: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_hkdf_expand_f_circuit() {
+        // with 30 bytes of info, the inner message of T(1) takes one block and the next ones two
+        for info in [INFO.to_vec(), vec![0x17; 30]] {
+            let circuit = FoldedSha256HkdfExpandFCircuit::new(HkdfExpandParams {
+                prk: PRK.to_vec(),
+                info: info.clone(),
+            })
+            .unwrap();
+
+            let mut z_i = hkdf_expand_initial_state(prk_hash(&poseidon_canonical_config(), &PRK));
+            let mut okm = vec![];
+            for i in 0..3 {
+                let z_i1 = circuit.step_native(i, z_i.clone(), vec![]).unwrap();
+
+                let cs = ConstraintSystem::<Fr>::new_ref();
+                let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+                let computed_z_i1Var = circuit
+                    .generate_step_constraints(cs.clone(), i, z_iVar, vec![])
+                    .unwrap();
+                assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+                assert!(cs.is_satisfied().unwrap());
+
+                okm.extend(digest_from_state(&z_i1[..STATE_LEN]).0);
+                z_i = z_i1;
+            }
+            assert_eq!(okm, hkdf_expand(&PRK, &info, 3 * HASH_LEN).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_prove_hkdf_expand_rfc5869() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, nova_vp)) =
+            prove_hkdf_expand(&PRK, &INFO, OKM.len(), &FoldConfig::default(), &mut rng).unwrap();
+        assert_eq!(folding_scheme.i, Fr::from(2u64));
+
+        let prk_hash = prk_hash(&poseidon_canonical_config(), &PRK);
+        let ivc_proof = folding_scheme.ivc_proof();
+        verify_hkdf_expand(nova_vp.clone(), ivc_proof.clone(), prk_hash, &OKM).unwrap();
+
+        let mut tampered = OKM;
+        tampered[0] ^= 1;
+        assert!(matches!(
+            verify_hkdf_expand(nova_vp.clone(), ivc_proof.clone(), prk_hash, &tampered),
+            Err(FoldedSha256Error::HkdfOutputMismatch)
+        ));
+        assert!(matches!(
+            verify_hkdf_expand(nova_vp.clone(), ivc_proof.clone(), prk_hash, &OKM[..32]),
+            Err(FoldedSha256Error::HkdfOutputMismatch)
+        ));
+        assert!(matches!(
+            verify_hkdf_expand(nova_vp, ivc_proof, prk_hash + Fr::one(), &OKM),
+            Err(FoldedSha256Error::InvalidInitialState)
+        ));
+    }
+}
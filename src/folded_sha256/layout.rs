@@ -0,0 +1,184 @@
+//! Packings of a 512-bit message block into the external inputs of a folding step.
+//!
+//! The step circuits only see the block as 64 `UInt8`s, the layout decides how many field
+//! elements carry them and how they are decoded (and range-checked) in-circuit.
+use crate::folded_sha256::circuit::{bytes_from_fp, words_to_bytes_be};
+use crate::folded_sha256::error::violation;
+use crate::folded_sha256::utils::BLOCK_LENGTH_BYTES;
+
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    uint32::UInt32,
+    uint8::UInt8,
+};
+use ark_relations::r1cs::SynthesisError;
+use ark_std::fmt::Debug;
+
+/// Encoding of a message block as external inputs.
+///
+/// `decode` and `decode_in_circuit` must accept exactly the same elements, the canonical
+/// encodings produced by `encode`, so that the native step and the step circuit agree.
+pub trait ExternalInputsLayout: Clone + Copy + Debug + Default {
+    /// Number of external inputs carrying one block.
+    fn len() -> usize;
+
+    fn encode<F: PrimeField>(block: &[u8; BLOCK_LENGTH_BYTES]) -> Vec<F>;
+
+    /// Natively decodes the external inputs to the block bytes, rejecting non-canonical inputs.
+    fn decode<F: PrimeField>(xs: &[F]) -> Result<Vec<u8>, &'static str>;
+
+    /// Decodes the external inputs to the block bytes, enforcing that they are canonical.
+    fn decode_in_circuit<F: PrimeField>(vars: &[FpVar<F>])
+        -> Result<Vec<UInt8<F>>, SynthesisError>;
+
+    /// Decodes the external inputs to the 16 big-endian words of the block, enforcing that they
+    /// are canonical. By default the words are rebuilt from `decode_in_circuit`.
+    fn decode_words_in_circuit<F: PrimeField>(
+        vars: &[FpVar<F>],
+    ) -> Result<Vec<UInt32<F>>, SynthesisError> {
+        Self::decode_in_circuit(vars)?
+            .chunks(4)
+            .map(UInt32::from_bytes_be)
+            .collect()
+    }
+}
+
+/// One byte per external input, the default layout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ByteLayout;
+
+impl ExternalInputsLayout for ByteLayout {
+    fn len() -> usize {
+        BLOCK_LENGTH_BYTES
+    }
+
+    fn encode<F: PrimeField>(block: &[u8; BLOCK_LENGTH_BYTES]) -> Vec<F> {
+        block.iter().map(|&x| F::from(x)).collect()
+    }
+
+    fn decode<F: PrimeField>(xs: &[F]) -> Result<Vec<u8>, &'static str> {
+        xs.iter()
+            .map(|x| {
+                if *x >= F::from(256u64) {
+                    return violation("external input is not the canonical encoding of a byte");
+                }
+                // we only need to take the least significant byte for each input
+                Ok(x.into_bigint().to_bytes_le()[0])
+            })
+            .collect()
+    }
+
+    fn decode_in_circuit<F: PrimeField>(
+        vars: &[FpVar<F>],
+    ) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        bytes_from_fp(vars)
+    }
+}
+
+/// One big-endian 32-bit word per external input, i.e. 16 inputs per block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WordLayout;
+
+impl ExternalInputsLayout for WordLayout {
+    fn len() -> usize {
+        BLOCK_LENGTH_BYTES / 4
+    }
+
+    fn encode<F: PrimeField>(block: &[u8; BLOCK_LENGTH_BYTES]) -> Vec<F> {
+        block
+            .chunks(4)
+            .map(|chunk| F::from(u32::from_be_bytes(chunk.try_into().unwrap())))
+            .collect()
+    }
+
+    fn decode<F: PrimeField>(xs: &[F]) -> Result<Vec<u8>, &'static str> {
+        let mut bytes = Vec::with_capacity(4 * xs.len());
+        for x in xs {
+            if *x >= F::from(1u64 << 32) {
+                return violation("external input is not the canonical encoding of a 32-bit word");
+            }
+            let le_bytes = x.into_bigint().to_bytes_le();
+            bytes.extend(le_bytes[..4].iter().rev());
+        }
+        Ok(bytes)
+    }
+
+    fn decode_in_circuit<F: PrimeField>(
+        vars: &[FpVar<F>],
+    ) -> Result<Vec<UInt8<F>>, SynthesisError> {
+        words_to_bytes_be(&Self::decode_words_in_circuit(vars)?)
+    }
+
+    fn decode_words_in_circuit<F: PrimeField>(
+        vars: &[FpVar<F>],
+    ) -> Result<Vec<UInt32<F>>, SynthesisError> {
+        vars.iter()
+            .map(|x| {
+                // `from_fp` only keeps the 32 least significant bits, the remaining ones must be zero
+                let (word, rest) = UInt32::from_fp(x)?;
+                rest.enforce_equal(&FpVar::zero())?;
+                Ok(word)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn check_layout<L: ExternalInputsLayout>() {
+        let block: [u8; BLOCK_LENGTH_BYTES] = core::array::from_fn(|i| (i * 37 + 11) as u8);
+
+        let encoded: Vec<Fr> = L::encode(&block);
+        assert_eq!(encoded.len(), L::len());
+        assert_eq!(L::decode(&encoded).unwrap(), block.to_vec());
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let vars: Vec<FpVar<Fr>> = encoded
+            .iter()
+            .map(|x| FpVar::new_witness(cs.clone(), || Ok(*x)).unwrap())
+            .collect();
+        let decoded = L::decode_in_circuit(&vars).unwrap();
+        assert_eq!(decoded.value().unwrap(), block.to_vec());
+        let words = L::decode_words_in_circuit(&vars).unwrap();
+        let expected_words: Vec<u32> = block
+            .chunks(4)
+            .map(|chunk| u32::from_be_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(words.value().unwrap(), expected_words);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    fn check_non_canonical_input_is_rejected<L: ExternalInputsLayout>(x: Fr) {
+        let mut encoded: Vec<Fr> = L::encode(&[0u8; BLOCK_LENGTH_BYTES]);
+        encoded[3] = x;
+        #[cfg(not(feature = "strict-panics"))]
+        assert!(L::decode(&encoded).is_err());
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let vars: Vec<FpVar<Fr>> = encoded
+            .iter()
+            .map(|x| FpVar::new_witness(cs.clone(), || Ok(*x)).unwrap())
+            .collect();
+        L::decode_in_circuit(&vars).unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_byte_layout() {
+        check_layout::<ByteLayout>();
+        check_non_canonical_input_is_rejected::<ByteLayout>(Fr::from(256u64 + 7));
+    }
+
+    #[test]
+    fn test_word_layout() {
+        check_layout::<WordLayout>();
+        check_non_canonical_input_is_rejected::<WordLayout>(Fr::from((1u64 << 32) + 7));
+    }
+}
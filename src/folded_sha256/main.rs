@@ -3,26 +3,40 @@
 #![allow(non_camel_case_types)]
 #![allow(clippy::upper_case_acronyms)]
 use crate::folded_sha256::circuit;
-use crate::folded_sha256::utils::{sha256_msg_block_sequence, update_state_ref};
+use crate::folded_sha256::config::{retry_step, DeciderMode, FoldConfig, Variant};
+use crate::folded_sha256::digest::{digest_from_state, Digest};
+use crate::folded_sha256::error::{violation, FoldedSha256Error};
+use crate::folded_sha256::layout::{ByteLayout, ExternalInputsLayout, WordLayout};
+use crate::folded_sha256::output::{OutputMode, RawDigest};
+use crate::folded_sha256::proof::{ProofSizes, Sha256Proof};
+use crate::folded_sha256::srs::required_srs_degree;
+use crate::folded_sha256::utils::{
+    update_state_ref, BlockExternalInputs, CompressionOptions, BLOCK_LENGTH_BYTES,
+};
 
-use std::time::Instant;
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
 
 use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::alloc::AllocVar;
 use ark_r1cs_std::fields::fp::FpVar;
 use ark_r1cs_std::uint32::UInt32;
-use ark_r1cs_std::uint8::UInt8;
-use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_relations::r1cs::{ConstraintSystem, ConstraintSystemRef, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 use ark_std::marker::PhantomData;
 
 use ark_bn254::{constraints::GVar, Bn254, Fr, G1Projective as Projective};
 use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
 
-use ark_std::rand;
-use folding_schemes::commitment::{kzg::KZG, pedersen::Pedersen};
-use folding_schemes::folding::nova::{Nova, PreprocessorParam};
+use ark_groth16::Groth16;
+use ark_std::rand::{self, CryptoRng, RngCore};
+use folding_schemes::commitment::{kzg::KZG, pedersen::Pedersen, CommitmentScheme};
+use folding_schemes::folding::nova::{
+    decider_eth::Decider as DeciderEth, CommittedInstance, IVCProof, Nova, PreprocessorParam,
+};
 use folding_schemes::frontend::FCircuit;
 use folding_schemes::transcript::poseidon::poseidon_canonical_config;
-use folding_schemes::{Error, FoldingScheme};
+use folding_schemes::{Decider, Error, FoldingScheme};
 
 /// This is the circuit that we want to fold, it implements the FCircuit trait from the Sonobe library.
 /// The parameter z_i denotes the current state which contains 8 elements (the H state of the SHA-256 algorithm),
@@ -57,16 +71,23 @@ use folding_schemes::{Error, FoldingScheme};
 /// where each w_i value is the next block to be hashed.
 ///
 /// The last state z_i will be the final H state, that then can be concatenated to get the final hash.
+///
+/// Each external input must be the canonical encoding of a byte, i.e. a field element `< 256`:
+/// the circuit enforces it, so a prover cannot feed `b + 256 * k` in place of the byte `b` (which
+/// `UInt8::from_fp` alone would silently truncate to `b`), and `step_native` rejects it.
+///
+/// This is the default `ByteLayout`, the circuit can use any other `ExternalInputsLayout` (e.g.
+/// `WordLayout`, packing a 32-bit word per external input) as long as the external inputs are
+/// encoded with the same layout.
+///
+/// The output mode `M` (see `output`) only decides what is exposed from the final state, through
+/// `public_outputs` and `generate_output_constraints`: the step circuit is the same for all modes.
 
 pub const STATE_LEN: usize = 8;
 
-type State = [u32; STATE_LEN];
-
-pub const H: State = [
-    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
-];
+pub use crate::folded_sha256::constants::sha256::H;
 
-fn bigint_to_u32<F: PrimeField>(x: F) -> u32 {
+pub(crate) fn bigint_to_u32<F: PrimeField>(x: F) -> u32 {
     let bigint = x.into_bigint();
     let bytes = bigint.to_bytes_le();
     let mut array = [0u8; 4];
@@ -75,22 +96,160 @@ fn bigint_to_u32<F: PrimeField>(x: F) -> u32 {
     u32::from_le_bytes(array)
 }
 
+pub type NovaFor<FC> = Nova<
+    Projective,
+    GVar,
+    Projective2,
+    GVar2,
+    FC,
+    KZG<'static, Bn254>,
+    Pedersen<Projective2>,
+    false,
+>;
+
+pub type NovaParamsFor<FC> = (
+    <NovaFor<FC> as FoldingScheme<Projective, Projective2, FC>>::ProverParam,
+    <NovaFor<FC> as FoldingScheme<Projective, Projective2, FC>>::VerifierParam,
+);
+
+pub type N = NovaFor<FoldedSha256FCircuit<Fr>>;
+
+pub type NovaProverParam =
+    <N as FoldingScheme<Projective, Projective2, FoldedSha256FCircuit<Fr>>>::ProverParam;
+pub type NovaVerifierParam =
+    <N as FoldingScheme<Projective, Projective2, FoldedSha256FCircuit<Fr>>>::VerifierParam;
+
+pub type KZGProverParam = <KZG<'static, Bn254> as CommitmentScheme<Projective>>::ProverParams;
+pub type KZGVerifierParam = <KZG<'static, Bn254> as CommitmentScheme<Projective>>::VerifierParams;
+
+pub type D = DeciderEth<
+    Projective,
+    GVar,
+    Projective2,
+    GVar2,
+    FoldedSha256FCircuit<Fr>,
+    KZG<'static, Bn254>,
+    Pedersen<Projective2>,
+    Groth16<Bn254>,
+    N,
+>;
+
+pub type DeciderProof = <D as Decider<Projective, Projective2, FoldedSha256FCircuit<Fr>, N>>::Proof;
+pub type DeciderVerifierParam =
+    <D as Decider<Projective, Projective2, FoldedSha256FCircuit<Fr>, N>>::VerifierParam;
+
+/// Converts natively external inputs to bytes, rejecting the elements which are not the canonical
+/// encoding of a byte, as `circuit::bytes_from_fp` does in-circuit.
+pub(crate) fn bytes_from_field<F: PrimeField>(xs: &[F]) -> Result<Vec<u8>, Error> {
+    ByteLayout::decode(xs).map_err(|e| Error::Other(e.to_string()))
+}
+
 #[derive(Clone, Copy, Debug)]
-pub struct FoldedSha256FCircuit<F: PrimeField> {
+pub struct FoldedSha256FCircuit<
+    F: PrimeField,
+    L: ExternalInputsLayout = ByteLayout,
+    M: OutputMode = RawDigest,
+> {
+    variant: Variant,
+    output_mode: M,
     _f: PhantomData<F>,
+    _layout: PhantomData<L>,
 }
-impl<F: PrimeField> FCircuit<F> for FoldedSha256FCircuit<F> {
-    type Params = ();
 
-    fn new(_params: Self::Params) -> Result<Self, Error> {
-        Ok(Self { _f: PhantomData })
+impl<F: PrimeField, L: ExternalInputsLayout, M: OutputMode> FoldedSha256FCircuit<F, L, M> {
+    /// Circuit folding SHA-256, with the default output mode.
+    ///
+    /// ```
+    /// use ark_bn254::Fr;
+    /// use folded_sha256::folded_sha256::main::FoldedSha256FCircuit;
+    /// use folding_schemes::frontend::FCircuit;
+    ///
+    /// let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+    /// assert_eq!(circuit.state_len(), 8);
+    /// assert_eq!(circuit.initial_state()[0], Fr::from(0x6a09e667u32));
+    /// ```
+    pub fn sha256() -> Self {
+        Self::with_variant(Variant::Sha256)
+    }
+
+    /// Circuit folding SHA-224, with the default output mode. The step function is the same as
+    /// SHA-256's, only the initial state differs; the digest is the first 28 bytes of the final
+    /// state, e.g. `with_output_mode(Truncated(28))`.
+    pub fn sha224() -> Self {
+        Self::with_variant(Variant::Sha224)
+    }
+
+    fn with_variant(variant: Variant) -> Self {
+        Self {
+            variant,
+            output_mode: M::default(),
+            _f: PhantomData,
+            _layout: PhantomData,
+        }
+    }
+
+    /// Same circuit, exposing the final state through `output_mode` instead.
+    pub fn with_output_mode<M2: OutputMode>(
+        self,
+        output_mode: M2,
+    ) -> FoldedSha256FCircuit<F, L, M2> {
+        FoldedSha256FCircuit {
+            variant: self.variant,
+            output_mode,
+            _f: PhantomData,
+            _layout: PhantomData,
+        }
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Initial state `z_0` of the folding, the initial hash value of the variant.
+    pub fn initial_state(&self) -> Vec<F> {
+        self.variant
+            .initial_state()
+            .iter()
+            .map(|&x| F::from(x))
+            .collect()
+    }
+
+    pub fn output_mode(&self) -> &M {
+        &self.output_mode
+    }
+
+    /// Public outputs of the final state `z_n` of the folding, e.g. `folding_scheme.z_i`.
+    pub fn public_outputs(&self, z_n: &[F]) -> Result<Vec<F>, Error> {
+        self.output_mode
+            .outputs(z_n)
+            .map_err(|e| Error::Other(e.to_string()))
+    }
+
+    /// In-circuit counterpart of `public_outputs`, e.g. for a circuit checking the final state.
+    pub fn generate_output_constraints(
+        &self,
+        z_n: &[FpVar<F>],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        self.output_mode.generate_output_constraints(z_n)
+    }
+}
+
+impl<F: PrimeField, L: ExternalInputsLayout, M: OutputMode> FCircuit<F>
+    for FoldedSha256FCircuit<F, L, M>
+{
+    /// The SHA-2 variant, see `sha256` and `sha224`.
+    type Params = Variant;
+
+    /// Uses the default output mode, see `with_output_mode` to pick another one.
+    fn new(variant: Self::Params) -> Result<Self, Error> {
+        Ok(Self::with_variant(variant))
     }
 
     fn state_len(&self) -> usize {
         8
     }
     fn external_inputs_len(&self) -> usize {
-        64
+        L::len()
     }
 
     fn step_native(
@@ -101,16 +260,20 @@ impl<F: PrimeField> FCircuit<F> for FoldedSha256FCircuit<F> {
     ) -> Result<Vec<F>, Error> {
         // z_i is the state of our sha2 algo
         // external_inputs is the message block to be compressed
+        if _external_inputs.len() != self.external_inputs_len() {
+            return violation(Error::Other(format!(
+                "expected {} external inputs, got {}",
+                self.external_inputs_len(),
+                _external_inputs.len()
+            )));
+        }
 
         // Convert z_i to Vec<u32>
         let z_to_u32: Vec<u32> = z_i.iter().map(|&x| bigint_to_u32(x)).collect::<Vec<u32>>();
 
         // Convert external_inputs to Vec<u8>
-        let _external_inputs_to_u8: Vec<u8> = _external_inputs
-            .iter()
-            // we only need to take the most significant byte for each input
-            .map(|x| x.into_bigint().to_bytes_le()[0])
-            .collect();
+        let _external_inputs_to_u8: Vec<u8> =
+            L::decode(&_external_inputs).map_err(|e| Error::Other(e.to_string()))?;
 
         let updated_state = update_state_ref(z_to_u32, _external_inputs_to_u8).unwrap();
 
@@ -128,7 +291,10 @@ impl<F: PrimeField> FCircuit<F> for FoldedSha256FCircuit<F> {
     ) -> Result<Vec<FpVar<F>>, SynthesisError> {
         // z_i is the state of our sha2 algo
         // external_inputs is the message block to be compressed
-        let mut state: Vec<UInt32<F>> = vec![
+        if _external_inputs.len() != self.external_inputs_len() {
+            return violation(SynthesisError::Unsatisfiable);
+        }
+        let state: Vec<UInt32<F>> = vec![
             UInt32::from_fp(&z_i[0].clone()).unwrap().0,
             UInt32::from_fp(&z_i[1].clone()).unwrap().0,
             UInt32::from_fp(&z_i[2].clone()).unwrap().0,
@@ -139,12 +305,15 @@ impl<F: PrimeField> FCircuit<F> for FoldedSha256FCircuit<F> {
             UInt32::from_fp(&z_i[7].clone()).unwrap().0,
         ];
 
-        let data: Vec<UInt8<F>> = _external_inputs
-            .iter()
-            .map(|x| UInt8::from_fp(&x.clone()).unwrap().0)
-            .collect();
+        // the block goes straight to its words, e.g. without any byte decomposition with
+        // `WordLayout`
+        let block_words: Vec<UInt32<F>> = L::decode_words_in_circuit(&_external_inputs)?;
 
-        let h = circuit::one_compression_round(&mut state, &data).unwrap();
+        let h = circuit::compression_round_from_words(
+            &state,
+            &block_words,
+            &CompressionOptions::default(),
+        )?;
 
         let h_to_fp_var: Vec<FpVar<F>> = h.iter().map(|x| x.to_fp().unwrap()).collect();
 
@@ -155,15 +324,19 @@ impl<F: PrimeField> FCircuit<F> for FoldedSha256FCircuit<F> {
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::folded_sha256::utils::{assert_state_words_eq, sha256_msg_block_sequence};
     use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
     use ark_relations::r1cs::ConstraintSystem;
+    use ark_serialize::CanonicalSerialize;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
 
     // test to check that the MultiInputsFCircuit computes the same values inside and outside the circuit
     #[test]
     fn test_f_circuit() {
         let cs = ConstraintSystem::<Fr>::new_ref();
 
-        let circuit = FoldedSha256FCircuit::<Fr>::new(()).unwrap();
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
         let z_i = vec![
             Fr::from(H[0]),
             Fr::from(H[1]),
@@ -191,12 +364,16 @@ pub mod tests {
             .generate_step_constraints(cs.clone(), 0, z_iVar.clone(), externalInputsVar)
             .unwrap();
 
-        assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+        let to_words = |state: Vec<Fr>| state.into_iter().map(bigint_to_u32).collect::<Vec<u32>>();
+        assert_state_words_eq(
+            &to_words(z_i1),
+            &to_words(computed_z_i1Var.value().unwrap()),
+        );
     }
 
     #[test]
     fn test_sha256_correctness() {
-        let circuit = FoldedSha256FCircuit::<Fr>::new(()).unwrap();
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
         let z_i: Vec<ark_ff::Fp<ark_ff::MontBackend<ark_bn254::FrConfig, 4>, 4>> = vec![
             Fr::from(H[0]),
             Fr::from(H[1]),
@@ -221,33 +398,779 @@ pub mod tests {
             .step_native(0, z_i.clone(), external_inputs.clone())
             .unwrap();
 
-        // Convert the final state to a hexadecimal string
-        let final_hash = z_i1
+        assert_eq!(
+            Digest::from_state(&z_i1).to_string(),
+            // Corresponding sha256 hash of "abc"
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_step_native_chain_matches_sha2() {
+        use sha2::{Digest as _, Sha256};
+
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let three_blocks: Vec<u8> = (0..150).map(|i| (i * 11 + 1) as u8).collect();
+        for input in [b"".to_vec(), b"abc".to_vec(), three_blocks] {
+            let mut z_i = circuit.initial_state();
+            let mut num_steps = 0;
+            for (i, block) in BlockExternalInputs::<Fr, _>::new(input.clone()).enumerate() {
+                z_i = circuit.step_native(i, z_i, block).unwrap();
+                num_steps += 1;
+            }
+            assert_eq!(num_steps, FoldConfig::num_blocks(input.len()));
+            assert_eq!(
+                digest_from_state(&z_i).0[..],
+                Sha256::digest(&input)[..],
+                "input of {} bytes",
+                input.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_non_canonical_external_input_is_rejected() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+
+        let mut external_inputs: Vec<Fr> = sha256_msg_block_sequence(b"abc".to_vec())[0]
             .iter()
-            .flat_map(|x| {
-                let bytes = x.into_bigint().to_bytes_be();
-                // Take the last 4 bytes to avoid leading zeros
-                bytes[bytes.len() - 4..].to_vec()
-            })
-            .collect::<Vec<u8>>();
+            .map(|&x| Fr::from(x))
+            .collect();
+        // same least significant byte as 'a', but not a byte
+        external_inputs[0] += Fr::from(256u64);
+
+        #[cfg(not(feature = "strict-panics"))]
+        assert!(circuit
+            .step_native(0, z_i.clone(), external_inputs.clone())
+            .is_err());
+
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let externalInputsVar =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[cfg(not(feature = "strict-panics"))]
+    #[test]
+    fn test_wrong_external_inputs_len_is_rejected() {
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        let mut external_inputs = BlockExternalInputs::<Fr, _>::new(b"abc".to_vec())
+            .next()
+            .unwrap();
+        external_inputs.pop();
+        assert_eq!(external_inputs.len(), 63);
+
+        assert!(circuit
+            .step_native(0, z_i.clone(), external_inputs.clone())
+            .is_err());
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let externalInputsVar =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        assert!(matches!(
+            circuit.generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar),
+            Err(SynthesisError::Unsatisfiable)
+        ));
+    }
+
+    #[cfg(feature = "strict-panics")]
+    #[test]
+    #[should_panic(expected = "expected 64 external inputs, got 63")]
+    fn test_wrong_external_inputs_len_panics() {
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        let _ = circuit.step_native(0, z_i, vec![Fr::from(0u64); 63]);
+    }
+
+    #[test]
+    fn test_variant_constructors() {
+        use crate::folded_sha256::constants::sha224;
+
+        let sha256 = FoldedSha256FCircuit::<Fr>::sha256();
+        let sha224 = FoldedSha256FCircuit::<Fr>::sha224();
+        assert_eq!(sha256.variant(), Variant::Sha256);
+        assert_eq!(
+            FoldedSha256FCircuit::<Fr>::new(Variant::Sha224)
+                .unwrap()
+                .variant(),
+            Variant::Sha224
+        );
+        assert_eq!(
+            sha224.initial_state(),
+            sha224::H.iter().map(|&x| Fr::from(x)).collect::<Vec<Fr>>()
+        );
+
+        // both variants share the step function
+        let block = BlockExternalInputs::<Fr, _>::new(b"abc".to_vec())
+            .next()
+            .unwrap();
+        let z_0 = sha224.initial_state();
+        assert_eq!(
+            sha224.step_native(0, z_0.clone(), block.clone()).unwrap(),
+            sha256.step_native(0, z_0, block).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_output_modes_public_inputs() {
+        use crate::folded_sha256::output::{EqualsPublic, Truncated};
+
+        let z_n: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        assert_eq!(circuit.public_outputs(&z_n).unwrap(), z_n);
+
+        let truncated = circuit.with_output_mode(Truncated(16));
+        let equals_public = circuit.with_output_mode(EqualsPublic([0u8; 32]));
+        for (circuit_outputs, num_outputs) in [
+            (truncated.public_outputs(&z_n).unwrap(), 16),
+            (equals_public.public_outputs(&z_n).unwrap(), 1),
+        ] {
+            assert_eq!(circuit_outputs.len(), num_outputs);
+        }
+        // the step function does not depend on the output mode
+        assert_eq!(truncated.state_len(), circuit.state_len());
+        assert_eq!(
+            truncated.external_inputs_len(),
+            circuit.external_inputs_len()
+        );
 
-        let hex_string = final_hash
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_nVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_n.clone())).unwrap();
+        let outputs = truncated.generate_output_constraints(&z_nVar).unwrap();
+        assert_eq!(
+            outputs.value().unwrap(),
+            truncated.public_outputs(&z_n).unwrap()
+        );
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_f_circuit_word_layout() {
+        use crate::folded_sha256::layout::WordLayout;
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = FoldedSha256FCircuit::<Fr, WordLayout>::sha256();
+        assert_eq!(circuit.external_inputs_len(), 16);
+        let z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+
+        let input: Vec<u8> = (0..100).map(|i| i as u8).collect();
+        let blocks: Vec<Vec<Fr>> =
+            BlockExternalInputs::<Fr, _, WordLayout>::new(input.clone()).collect();
+        let byte_blocks = sha256_msg_block_sequence(input);
+        assert_eq!(blocks.len(), byte_blocks.len());
+
+        let z_i1 = circuit
+            .step_native(0, z_i.clone(), blocks[0].clone())
+            .unwrap();
+        let expected = update_state_ref(H.to_vec(), byte_blocks[0].to_vec()).unwrap();
+        assert_eq!(
+            z_i1,
+            expected.iter().map(|&x| Fr::from(x)).collect::<Vec<Fr>>()
+        );
+
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let externalInputsVar =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(blocks[0].clone())).unwrap();
+        let computed_z_i1Var = circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+            .unwrap();
+        assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_report_constraints() {
+        let report = report_constraints().unwrap();
+        assert_eq!(report.len(), 4);
+        let count = |variant: Variant, layout: &str| {
+            report
+                .iter()
+                .find(|c| c.variant == variant && c.layout == layout)
+                .unwrap()
+                .num_constraints
+        };
+
+        for variant in [Variant::Sha256, Variant::Sha224] {
+            // one word per external input needs fewer range checks than one byte per input
+            assert!(count(variant, "words") < count(variant, "bytes"));
+        }
+        // the variants only differ by their initial state
+        assert_eq!(
+            count(Variant::Sha256, "bytes"),
+            count(Variant::Sha224, "bytes")
+        );
+    }
+
+    #[test]
+    fn test_verify_step_native_detects_corrupted_state() {
+        let circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        let external_inputs: Vec<Fr> = sha256_msg_block_sequence(b"abc".to_vec())[0]
             .iter()
-            .map(|byte| format!("{:02x}", byte))
-            .collect::<String>();
+            .map(|&x| Fr::from(x))
+            .collect();
+
+        let mut z_1 = circuit
+            .step_native(0, z_0.clone(), external_inputs.clone())
+            .unwrap();
+        assert!(
+            verify_step_native(&circuit, 0, z_0.clone(), external_inputs.clone(), &z_1).is_ok()
+        );
+
+        z_1[5] += Fr::from(1u64);
+        assert!(matches!(
+            verify_step_native(&circuit, 0, z_0, external_inputs, &z_1),
+            Err(FoldedSha256Error::SelfCheckFailed { step: 0 })
+        ));
+    }
+
+    #[test]
+    fn test_seeded_run_is_reproducible() {
+        let prove_with_seed = |seed: u64| {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            let (folding_scheme, _) =
+                run(b"abc".to_vec(), &FoldConfig::default(), &mut rng).unwrap();
+            let mut bytes = Vec::new();
+            folding_scheme
+                .ivc_proof()
+                .serialize_compressed(&mut bytes)
+                .unwrap();
+            bytes
+        };
+
+        assert_eq!(prove_with_seed(42), prove_with_seed(42));
+    }
+
+    #[test]
+    fn test_prove_from_blocks() {
+        use crate::folded_sha256::digest::digest_from_state;
+        use crate::folded_sha256::hasher::sha256;
+
+        // "abc", the '1' bit and the 24-bit length, padded by hand
+        let mut block = [0u8; BLOCK_LENGTH_BYTES];
+        block[..3].copy_from_slice(b"abc");
+        block[3] = 0x80;
+        block[BLOCK_LENGTH_BYTES - 1] = 24;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, nova_vp)) =
+            prove_from_blocks([block], &FoldConfig::default(), &mut rng).unwrap();
+        assert_eq!(folding_scheme.i, Fr::from(1u64));
+        assert_eq!(digest_from_state(&folding_scheme.z_i), sha256(b"abc"));
+        assert!(N::verify(nova_vp, folding_scheme.ivc_proof()).is_ok());
+    }
+
+    #[test]
+    fn test_verify_ivc_multi_block() {
+        let input: Vec<u8> = (0..150).map(|i| i as u8).collect();
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, nova_vp)) = run(input, &FoldConfig::default(), &mut rng).unwrap();
+        assert_eq!(folding_scheme.i, Fr::from(3u64));
+
+        let ivc_proof = folding_scheme.ivc_proof();
+        assert!(verify_ivc(nova_vp.clone(), ivc_proof.clone()).is_ok());
 
+        let mut forged_proof = ivc_proof;
+        forged_proof.z_i[0] += Fr::from(1u64);
+        assert!(matches!(
+            verify_ivc(nova_vp, forged_proof),
+            Err(FoldedSha256Error::FoldingScheme(_))
+        ));
+    }
+
+    #[test]
+    fn test_block_aligned_input_folds_a_padding_step() {
+        use crate::folded_sha256::digest::digest_from_state;
+        use sha2::{Digest, Sha256};
+
+        for len in [64, 128] {
+            let input = vec![b'x'; len];
+            let mut rng = ChaCha20Rng::seed_from_u64(0);
+            let (folding_scheme, _) = run(input.clone(), &FoldConfig::default(), &mut rng).unwrap();
+            assert_eq!(
+                folding_scheme.i,
+                Fr::from((len / BLOCK_LENGTH_BYTES + 1) as u64)
+            );
+            assert_eq!(
+                digest_from_state(&folding_scheme.z_i).0.to_vec(),
+                Sha256::digest(&input).to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_prove_from_bit_oriented_blocks() {
+        use crate::folded_sha256::digest::digest_from_state;
+        use crate::folded_sha256::hasher::sha256_bits;
+        use crate::folded_sha256::utils::sha256_bit_msg_block_sequence;
+
+        let input: Vec<u8> = (0..71).map(|i| (i * 13) as u8).collect();
+        let blocks = sha256_bit_msg_block_sequence(input.clone(), 563).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, _) =
+            prove_from_blocks(blocks, &FoldConfig::default(), &mut rng).unwrap();
         assert_eq!(
-            hex_string,
-            // Corresponding sha256 hash of "abc"
-            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+            digest_from_state(&folding_scheme.z_i),
+            sha256_bits(&input, 563).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_prove_step_timed() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let nova_params = preprocess(F_circuit, None, &mut rng).unwrap();
+        let mut folding_scheme =
+            N::init(&nova_params, F_circuit, F_circuit.initial_state()).unwrap();
+
+        let external_inputs = BlockExternalInputs::<Fr, _>::new(b"abc".to_vec())
+            .next()
+            .unwrap();
+        let elapsed = prove_step_timed(
+            &mut folding_scheme,
+            external_inputs,
+            &FoldConfig::default(),
+            &mut rng,
+        )
+        .unwrap();
+        assert!(elapsed > Duration::ZERO);
+        assert_eq!(folding_scheme.i, Fr::from(1u64));
+    }
+
+    #[test]
+    fn test_incremental_verification() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let (nova_pp, nova_vp) = preprocess(F_circuit, None, &mut rng).unwrap();
+        let z_0 = F_circuit.initial_state();
+        let mut folding_scheme =
+            N::init(&(nova_pp, nova_vp.clone()), F_circuit, z_0.clone()).unwrap();
+        let mut verifier = IncrementalVerifier::new(nova_vp.clone(), z_0.clone());
+
+        let mut proofs = Vec::new();
+        for external_inputs in BlockExternalInputs::<Fr, _>::new(vec![7u8; 150]) {
+            folding_scheme
+                .prove_step(&mut rng, external_inputs, None)
+                .unwrap();
+            let ivc_proof = folding_scheme.ivc_proof();
+            verifier.verify_step(ivc_proof.clone()).unwrap();
+            proofs.push(ivc_proof);
+        }
+        assert_eq!(verifier.num_steps(), 3);
+
+        // replaying a proof, skipping one or starting from another z_0 breaks the chain
+        assert!(matches!(
+            verifier.verify_step(proofs[2].clone()),
+            Err(FoldedSha256Error::UnexpectedStep { step: 4 })
+        ));
+        let mut verifier = IncrementalVerifier::new(nova_vp.clone(), z_0.clone());
+        assert!(verifier.verify_step(proofs[1].clone()).is_err());
+        let mut other_z_0 = z_0;
+        other_z_0[0] += Fr::from(1u64);
+        let mut verifier = IncrementalVerifier::new(nova_vp, other_z_0);
+        assert!(verifier.verify_step(proofs[0].clone()).is_err());
+    }
+
+    #[test]
+    fn test_prove_batch() {
+        use crate::folded_sha256::digest::digest_from_state;
+        use crate::folded_sha256::hasher::sha256;
+
+        let messages = vec![b"abc".to_vec(), vec![], vec![1u8; 100]];
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_schemes, (_, nova_vp)) =
+            prove_batch(&messages, &FoldConfig::default(), &mut rng).unwrap();
+
+        assert_eq!(folding_schemes.len(), messages.len());
+        for (folding_scheme, message) in folding_schemes.iter().zip(&messages) {
+            assert_eq!(digest_from_state(&folding_scheme.z_i), sha256(message));
+            assert!(N::verify(nova_vp.clone(), folding_scheme.ivc_proof()).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_fold_until_stops_when_the_predicate_holds() {
+        use crate::folded_sha256::utils::compress_block;
+
+        let input = vec![7u8; 200];
+        let blocks = sha256_msg_block_sequence(input.clone());
+        assert_eq!(blocks.len(), 4);
+        let mut state = H;
+        for block in &blocks[..2] {
+            state = compress_block(&state, block);
+        }
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, nova_vp), stopped_at) = fold_until(
+            input.clone(),
+            |s| *s == state,
+            &FoldConfig::default(),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(stopped_at, Some(1));
+        assert_eq!(folding_scheme.i, Fr::from(2u64));
+        assert_eq!(
+            folding_scheme.z_i,
+            state.iter().map(|&x| Fr::from(x)).collect::<Vec<Fr>>()
+        );
+        assert!(N::verify(nova_vp, folding_scheme.ivc_proof()).is_ok());
+
+        // a predicate which never holds folds the whole message
+        let (folding_scheme, _, stopped_at) =
+            fold_until(input, |_| false, &FoldConfig::default(), &mut rng).unwrap();
+        assert_eq!(stopped_at, None);
+        assert_eq!(folding_scheme.i, Fr::from(4u64));
+    }
+
+    #[test]
+    fn test_snapshot_after_the_last_step_is_what_the_decider_verifies() {
+        use crate::folded_sha256::proof::PublicInputsBundle;
+        use folding_schemes::folding::traits::CommittedInstanceOps;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+        let nova_params = preprocess(F_circuit, None, &mut rng).unwrap();
+        let mut folding_scheme =
+            N::init(&nova_params, F_circuit, F_circuit.initial_state()).unwrap();
+
+        let mut snapshots = vec![];
+        for external_inputs in BlockExternalInputs::<Fr, _>::new(vec![1u8; 100]) {
+            snapshots.push(
+                prove_step_snapshot(
+                    &mut folding_scheme,
+                    external_inputs,
+                    &FoldConfig::default(),
+                    &mut rng,
+                )
+                .unwrap(),
+            );
+        }
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].i, Fr::from(1u64));
+        assert_ne!(snapshots[0].U_i, snapshots[1].U_i);
+
+        let last = snapshots.pop().unwrap();
+        let public_inputs = PublicInputsBundle::new(&folding_scheme);
+        assert_eq!(last.i, public_inputs.i);
+        assert_eq!(
+            last.U_i.get_commitments(),
+            public_inputs.running_commitments
+        );
+        assert_eq!(
+            last.u_i.get_commitments(),
+            public_inputs.incoming_commitments
+        );
+
+        let mut bytes = Vec::new();
+        last.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(
+            InstanceSnapshot::deserialize_compressed(&bytes[..]).unwrap(),
+            last
         );
     }
+
+    #[test]
+    fn test_prove_and_verify_ivc_only() {
+        use crate::folded_sha256::hasher::sha256;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let report = prove_and_verify_with(b"abc", DeciderMode::IvcOnly, &mut rng).unwrap();
+        assert!(report.verified);
+        assert_eq!(report.digest, sha256(b"abc"));
+        assert_eq!(report.fold.steps.len(), 1);
+        assert!(report.decider_prove.is_none() && report.sizes.is_none());
+    }
+
+    #[test]
+    fn test_fold_from_channel() {
+        use crate::folded_sha256::digest::digest_from_state;
+        use crate::folded_sha256::hasher::sha256;
+        use std::sync::mpsc;
+        use std::thread;
+
+        let blocks: Vec<[u8; BLOCK_LENGTH_BYTES]> = (0..3u8)
+            .map(|i| core::array::from_fn(|j| i * 64 + j as u8))
+            .collect();
+        let (sender, receiver) = mpsc::channel();
+        let producer = {
+            let blocks = blocks.clone();
+            thread::spawn(move || {
+                for block in blocks {
+                    sender.send(block).unwrap();
+                }
+                // the sender is dropped here, closing the channel
+            })
+        };
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, nova_vp)) =
+            fold_from_channel(receiver, &FoldConfig::default(), &mut rng).unwrap();
+        producer.join().unwrap();
+
+        // the 3 blocks and the padding block
+        assert_eq!(folding_scheme.i, Fr::from(4u64));
+        assert_eq!(
+            digest_from_state(&folding_scheme.z_i),
+            sha256(&blocks.concat())
+        );
+        assert!(N::verify(nova_vp, folding_scheme.ivc_proof()).is_ok());
+    }
+
+    #[test]
+    fn test_fold_from_channel_limit_matches_run() {
+        use std::sync::mpsc;
+
+        let send_blocks = |num_blocks: usize| {
+            let (sender, receiver) = mpsc::channel();
+            for _ in 0..num_blocks {
+                sender.send([1u8; BLOCK_LENGTH_BYTES]).unwrap();
+            }
+            receiver
+        };
+        let config = FoldConfig {
+            max_blocks: Some(2),
+            ..Default::default()
+        };
+        // 1 message block and the padding block
+        assert!(config.check_input_len(BLOCK_LENGTH_BYTES).is_ok());
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, _) = fold_from_channel(send_blocks(1), &config, &mut rng).unwrap();
+        assert_eq!(folding_scheme.i, Fr::from(2u64));
+
+        // the padding of 2 message blocks would be the third block
+        assert!(config.check_input_len(2 * BLOCK_LENGTH_BYTES).is_err());
+        assert!(matches!(
+            fold_from_channel(send_blocks(2), &config, &mut rng),
+            Err(FoldedSha256Error::InputTooLarge {
+                num_blocks: 3,
+                max_blocks: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn test_mixing_variants_is_rejected() {
+        use crate::folded_sha256::digest::digest_from_state;
+        use crate::folded_sha256::hasher::sha256;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let mut session = FoldingSession::new(
+            FoldedSha256FCircuit::<Fr>::sha256(),
+            Variant::Sha256,
+            &mut rng,
+        )
+        .unwrap();
+        session
+            .prove_steps(
+                Variant::Sha256,
+                BlockExternalInputs::<Fr, _>::new(b"abc".to_vec()),
+                &FoldConfig::default(),
+                &mut rng,
+            )
+            .unwrap();
+
+        let result = session.prove_steps(
+            Variant::Sha224,
+            BlockExternalInputs::<Fr, _>::new(b"abc".to_vec()),
+            &FoldConfig::default(),
+            &mut rng,
+        );
+        assert!(matches!(
+            result,
+            Err(FoldedSha256Error::VariantMismatch {
+                expected: Variant::Sha256,
+                found: Variant::Sha224
+            })
+        ));
+
+        // the rejected batch left the folding untouched
+        assert_eq!(session.folding_scheme.i, Fr::from(1u64));
+        assert_eq!(
+            digest_from_state(&session.folding_scheme.z_i),
+            sha256(b"abc")
+        );
+        let (_, nova_vp) = session.params;
+        assert!(N::verify(nova_vp, session.folding_scheme.ivc_proof()).is_ok());
+    }
+
+    #[test]
+    fn test_folding_session_tracks_completed_steps() {
+        use crate::folded_sha256::hasher::sha256;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let input: Vec<u8> = (0..150).map(|i| i as u8).collect();
+        let total_blocks = FoldConfig::num_blocks(input.len());
+        assert_eq!(total_blocks, 3);
+        let config = FoldConfig::default();
+
+        let mut session = FoldingSession::new(
+            FoldedSha256FCircuit::<Fr>::sha256(),
+            Variant::Sha256,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(session.steps_completed(), 0);
+
+        let blocks: Vec<Vec<Fr>> = BlockExternalInputs::<Fr, _>::new(input.clone()).collect();
+        for (batch, steps_completed) in [(&blocks[..2], 2), (&blocks[2..], 3)] {
+            assert!(!session.is_complete(total_blocks));
+            session
+                .prove_steps(Variant::Sha256, batch.to_vec(), &config, &mut rng)
+                .unwrap();
+            assert_eq!(session.steps_completed(), steps_completed);
+            assert_eq!(session.blocks_folded(), steps_completed);
+        }
+        assert!(session.is_complete(total_blocks));
+        assert!(!session.is_complete(total_blocks + 1));
+        assert_eq!(
+            digest_from_state(&session.folding_scheme.z_i),
+            sha256(&input)
+        );
+    }
+
+    #[test]
+    fn test_folding_session_counts_blocks_per_step() {
+        use crate::folded_sha256::hasher::sha256;
+        use crate::folded_sha256::multi_block::{
+            multi_block_external_inputs, FoldedSha256MultiBlockFCircuit,
+        };
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let input: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let total_blocks = FoldConfig::num_blocks(input.len());
+        assert_eq!(total_blocks, 4);
+        let config = FoldConfig {
+            blocks_per_step: 2,
+            ..Default::default()
+        };
+
+        let mut session = FoldingSession::new(
+            FoldedSha256MultiBlockFCircuit::<Fr>::new(config.blocks_per_step).unwrap(),
+            Variant::Sha256,
+            &mut rng,
+        )
+        .unwrap();
+        let steps: Vec<Vec<Fr>> =
+            multi_block_external_inputs(input.clone(), config.blocks_per_step).collect();
+        assert_eq!(steps.len(), 2);
+
+        // one step per batch, the session is complete exactly after the last one
+        for (step, blocks_folded) in steps.into_iter().zip([2, 4]) {
+            assert!(!session.is_complete(total_blocks));
+            session
+                .prove_steps(Variant::Sha256, [step], &config, &mut rng)
+                .unwrap();
+            assert_eq!(session.blocks_folded(), blocks_folded);
+        }
+        assert_eq!(session.steps_completed(), 2);
+        assert!(session.is_complete(total_blocks));
+        assert_eq!(
+            digest_from_state(&session.folding_scheme.z_i),
+            sha256(&input)
+        );
+    }
+
+    // The proof binds the final state to the initial one: folding from a tampered IV yields another
+    // digest, and the IVC proof cannot be passed off as a proof from `H` to the standard digest.
+    #[test]
+    fn test_tampered_initial_state_does_not_verify() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let standard_z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        let mut tampered_z_0 = standard_z_0.clone();
+        tampered_z_0[0] += Fr::from(1u64);
+
+        let (folding_scheme, (_, nova_vp)) = fold(
+            FoldedSha256FCircuit::<Fr>::sha256(),
+            tampered_z_0,
+            BlockExternalInputs::<Fr, _>::new(b"abc".to_vec()),
+            &FoldConfig::default(),
+            &mut rng,
+        )
+        .unwrap();
+
+        let mut standard_z_i = standard_z_0.clone();
+        for block in BlockExternalInputs::<Fr, _>::new(b"abc".to_vec()) {
+            standard_z_i = folding_scheme
+                .F
+                .step_native(0, standard_z_i, block)
+                .unwrap();
+        }
+        assert_ne!(folding_scheme.z_i, standard_z_i);
+
+        // the honest claim, from the tampered IV, verifies
+        let ivc_proof = folding_scheme.ivc_proof();
+        assert!(N::verify(nova_vp.clone(), ivc_proof.clone()).is_ok());
+
+        // claiming the standard IV and digest does not
+        let mut forged_proof = ivc_proof.clone();
+        forged_proof.z_0 = standard_z_0;
+        forged_proof.z_i = standard_z_i;
+        assert!(N::verify(nova_vp.clone(), forged_proof).is_err());
+
+        // neither does claiming the standard IV for the actual output
+        let mut forged_proof = ivc_proof;
+        forged_proof.z_0 = H.iter().map(|&x| Fr::from(x)).collect();
+        assert!(N::verify(nova_vp, forged_proof).is_err());
+    }
+
+    #[test]
+    fn test_preprocess_rejects_undersized_srs() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (kzg_pp, kzg_vp) = KZG::<'static, Bn254>::setup(&mut rng, 1 << 4).unwrap();
+
+        let result = preprocess(
+            FoldedSha256FCircuit::<Fr>::sha256(),
+            Some((kzg_pp, kzg_vp)),
+            &mut rng,
+        );
+        match result {
+            Err(FoldedSha256Error::Setup(Error::Other(message))) => {
+                assert!(message.contains("KZG SRS"), "{}", message)
+            }
+            _ => panic!("an undersized SRS must be reported as a setup error"),
+        }
+    }
+
+    #[test]
+    fn test_run_rejects_input_above_max_blocks() {
+        let config = FoldConfig {
+            max_blocks: Some(1),
+            ..Default::default()
+        };
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+
+        // 100 bytes need 2 blocks, the error is returned before the Nova setup and any prove_step
+        let result = run(vec![0u8; 100], &config, &mut rng);
+        assert!(matches!(
+            result,
+            Err(FoldedSha256Error::InputTooLarge {
+                num_blocks: 2,
+                max_blocks: 1
+            })
+        ));
+    }
 }
 
-#[allow(dead_code)]
-fn main() {
-    let input: Vec<u8> = b"abc".to_vec();
-    let block_sequence = sha256_msg_block_sequence(input);
+/// Runs the Nova setup and folds one compression round for each 512-bit block of `input`.
+///
+/// All the randomness (commitment scheme setup and folding steps) is drawn from `rng`, so passing
+/// a seeded RNG such as `ChaCha20Rng` makes the generated proof reproducible. `main` uses `OsRng`.
+///
+/// The input length is checked against `config` before any setup or folding work is done.
+pub fn run<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam)), FoldedSha256Error> {
+    config.check_input_len(input.len())?;
 
     let initial_state = vec![
         Fr::from(H[0]),
@@ -260,69 +1183,624 @@ fn main() {
         Fr::from(H[7]),
     ];
 
-    let F_circuit = FoldedSha256FCircuit::<Fr>::new(()).unwrap();
+    let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
 
-    let poseidon_config = poseidon_canonical_config::<Fr>();
-    let mut rng = rand::rngs::OsRng;
+    // the blocks are padded and converted lazily
+    fold(
+        F_circuit,
+        initial_state,
+        BlockExternalInputs::<Fr, _>::new(input),
+        config,
+        rng,
+    )
+}
 
-    type N = Nova<
-        Projective,
-        GVar,
-        Projective2,
-        GVar2,
-        FoldedSha256FCircuit<Fr>,
-        KZG<'static, Bn254>,
-        Pedersen<Projective2>,
-        false,
-    >;
+/// Runs the whole pipeline of the example on `input`: the Nova and Decider setups, the folding, the
+/// Decider proof and its verification. Returns the digest recovered from the final state and
+/// whether the proof verifies.
+///
+/// As with `run`, all the randomness is drawn from `rng`. The Decider setup and proof take much
+/// longer than the folding itself.
+pub fn prove_and_verify<R: RngCore + CryptoRng>(
+    input: &[u8],
+    rng: &mut R,
+) -> Result<(Digest, bool), FoldedSha256Error> {
+    let report = prove_and_verify_with(input, DeciderMode::Eth, rng)?;
+    Ok((report.digest, report.verified))
+}
 
-    println!("Prepare Nova ProverParams & VerifierParams");
-    let nova_preprocess_params = PreprocessorParam::new(poseidon_config, F_circuit);
-    let nova_params = N::preprocess(&mut rng, &nova_preprocess_params).unwrap();
+/// Durations of the phases of a folding, see `fold_timed`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FoldTimings {
+    /// The Nova preprocessing, i.e. the commitment schemes setup.
+    pub preprocess: Duration,
+    /// Each folding step, retries included, see `prove_step_timed`.
+    pub steps: Vec<Duration>,
+}
 
-    println!("Initialize FoldingScheme");
-    let mut folding_scheme = N::init(&nova_params, F_circuit, initial_state.clone()).unwrap();
+/// Outcome of `prove_and_verify_with`, with the durations and sizes for the caller to report.
+#[derive(Clone, Debug)]
+pub struct ProveAndVerifyReport {
+    /// The digest recovered from the final folding state.
+    pub digest: Digest,
+    /// Whether the final proof verifies.
+    pub verified: bool,
+    pub fold: FoldTimings,
+    /// The Decider setup, `None` if `decider_mode` has no Decider.
+    pub decider_preprocess: Option<Duration>,
+    /// The Decider proof, `None` if `decider_mode` has no Decider.
+    pub decider_prove: Option<Duration>,
+    /// The verification of the final proof.
+    pub verify: Duration,
+    /// Sizes of what the verifier receives, `None` if `decider_mode` has no Decider.
+    pub sizes: Option<ProofSizes>,
+}
 
-    // compute a step of the IVC
-    for (i, external_inputs_at_step) in block_sequence.iter().enumerate() {
-        let start = Instant::now();
-        folding_scheme
-            .prove_step(
-                rng,
-                external_inputs_at_step
-                    .clone()
-                    .iter()
-                    .map(|x| Fr::from(x.clone()))
-                    .collect(),
-                None,
-            )
-            .unwrap();
-        println!("Nova::prove_step {}: {:?}", i, start.elapsed());
+/// Same as `prove_and_verify`, with the final proof chosen by `decider_mode`, and returning the
+/// durations of each phase alongside.
+pub fn prove_and_verify_with<R: RngCore + CryptoRng>(
+    input: &[u8],
+    decider_mode: DeciderMode,
+    rng: &mut R,
+) -> Result<ProveAndVerifyReport, FoldedSha256Error> {
+    FoldConfig::default().check_input_len(input.len())?;
+    let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+    let (folding_scheme, nova_params, fold) = fold_timed(
+        F_circuit,
+        F_circuit.initial_state(),
+        BlockExternalInputs::<Fr, _>::new(input.to_vec()),
+        &FoldConfig::default(),
+        rng,
+    )?;
+
+    let mut report = ProveAndVerifyReport {
+        digest: digest_from_state(&folding_scheme.z_i),
+        verified: false,
+        fold,
+        decider_preprocess: None,
+        decider_prove: None,
+        verify: Duration::ZERO,
+        sizes: None,
+    };
+    match decider_mode {
+        DeciderMode::Eth => {
+            decider_eth_prove_and_verify(&folding_scheme, nova_params, &mut report, rng)?
+        }
+        DeciderMode::IvcOnly => {
+            let start = Instant::now();
+            report.verified = verify_ivc(nova_params.1, folding_scheme.ivc_proof()).is_ok();
+            report.verify = start.elapsed();
+        }
     }
+    Ok(report)
+}
 
-    println!("Run the Nova's IVC verifier");
-    let ivc_proof = folding_scheme.ivc_proof();
-    N::verify(
-        nova_params.1, // Nova's verifier params
-        ivc_proof,
+/// Verifies the IVC proof of a SHA-256 folding, e.g. `folding_scheme.ivc_proof()`, with the
+/// Nova verifier parameters `vp`.
+///
+/// This is the cheapest way to check the whole computation: Nova folds every step into the final
+/// running instance, so the verifier only checks it and the last incoming instance against the
+/// committed witnesses, whatever the number of steps. It needs no Decider setup, but the proof
+/// is not succinct (it carries the witnesses of these instances) nor zero-knowledge.
+pub fn verify_ivc(
+    vp: NovaVerifierParam,
+    ivc_proof: IVCProof<Projective, Projective2>,
+) -> Result<(), FoldedSha256Error> {
+    N::verify(vp, ivc_proof)?;
+    Ok(())
+}
+
+/// Runs the `DeciderEth` setup and prover on `folding_scheme`, and verifies the proof, recording
+/// the durations, the sizes and the outcome in `report`.
+fn decider_eth_prove_and_verify<R: RngCore + CryptoRng>(
+    folding_scheme: &N,
+    nova_params: (NovaProverParam, NovaVerifierParam),
+    report: &mut ProveAndVerifyReport,
+    rng: &mut R,
+) -> Result<(), FoldedSha256Error> {
+    let start = Instant::now();
+    let (decider_pp, decider_vp) = D::preprocess(&mut *rng, nova_params, folding_scheme.clone())?;
+    report.decider_preprocess = Some(start.elapsed());
+
+    let start = Instant::now();
+    let proof = D::prove(&mut *rng, decider_pp, folding_scheme.clone())?;
+    report.decider_prove = Some(start.elapsed());
+
+    let sha256_proof = Sha256Proof::new(folding_scheme, proof);
+    report.sizes = Some(sha256_proof.sizes(&decider_vp));
+
+    let start = Instant::now();
+    report.verified = sha256_proof.verify(decider_vp);
+    report.verify = start.elapsed();
+    Ok(())
+}
+
+/// Runs the Nova setup and folds one compression round for each of the caller's `blocks`, as is:
+/// unlike `run`, no padding is added, e.g. for data already blocked and padded by a protocol.
+///
+/// The folding only proves the compression chain over the given blocks. Nothing checks that they
+/// are a correctly padded message, so the final state is a SHA-256 digest only if the caller's
+/// padding is right, and a verifier cannot tell the padding from the message: a message and its
+/// padding folded with `run` and the same bytes folded here give the same proof statement.
+pub fn prove_from_blocks<R: RngCore + CryptoRng>(
+    blocks: impl IntoIterator<Item = [u8; BLOCK_LENGTH_BYTES]>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam)), FoldedSha256Error> {
+    let blocks: Vec<[u8; BLOCK_LENGTH_BYTES]> = blocks.into_iter().collect();
+    config.check_num_blocks(blocks.len())?;
+
+    let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+    let initial_state = F_circuit.initial_state();
+    fold(
+        F_circuit,
+        initial_state,
+        blocks.iter().map(ByteLayout::encode::<Fr>),
+        config,
+        rng,
     )
-    .unwrap();
-
-    // Convert the final state to the final hexadecimal string
-    let final_hash = folding_scheme
-        .z_i
-        .iter()
-        .flat_map(|x| {
-            let bytes = x.into_bigint().to_bytes_be();
-            // Take the last 4 bytes to avoid leading zeros
-            bytes[bytes.len() - 4..].to_vec()
+}
+
+/// Runs the Nova setup for `F_circuit` and folds one step per item of `external_inputs`, starting
+/// from the state `z_0`. This is the driver shared by the SHA-256 circuit and its variants.
+///
+/// Failing steps are retried according to `config.max_step_retries`. With the `self-check` feature,
+/// the state reported by the folding scheme is checked against `step_native` after each step.
+pub fn fold<FC: FCircuit<Fr>, R: RngCore + CryptoRng>(
+    F_circuit: FC,
+    z_0: Vec<Fr>,
+    external_inputs: impl IntoIterator<Item = Vec<Fr>>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(NovaFor<FC>, NovaParamsFor<FC>), FoldedSha256Error> {
+    let (folding_scheme, nova_params, _) =
+        fold_timed(F_circuit, z_0, external_inputs, config, rng)?;
+    Ok((folding_scheme, nova_params))
+}
+
+/// Same as `fold`, also returning the duration of the preprocessing and of each step, for the
+/// caller to report.
+pub fn fold_timed<FC: FCircuit<Fr>, R: RngCore + CryptoRng>(
+    F_circuit: FC,
+    z_0: Vec<Fr>,
+    external_inputs: impl IntoIterator<Item = Vec<Fr>>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(NovaFor<FC>, NovaParamsFor<FC>, FoldTimings), FoldedSha256Error> {
+    let start = Instant::now();
+    let nova_params = preprocess(F_circuit.clone(), None, rng)?;
+    let preprocess = start.elapsed();
+
+    let mut folding_scheme = NovaFor::<FC>::init(&nova_params, F_circuit, z_0)?;
+    let steps = prove_steps(&mut folding_scheme, external_inputs, config, rng)?;
+
+    Ok((
+        folding_scheme,
+        nova_params,
+        FoldTimings { preprocess, steps },
+    ))
+}
+
+/// Folds each of the independent `messages`, sharing a single Nova setup: the preprocessing,
+/// which dominates for small messages, is run once, and only the initialization is repeated per
+/// message. The messages are folded one after the other.
+///
+/// Returns one folding scheme per message, whose IVC proofs verify with the shared verifier
+/// parameters, and the parameters.
+pub fn prove_batch<R: RngCore + CryptoRng>(
+    messages: &[Vec<u8>],
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(Vec<N>, (NovaProverParam, NovaVerifierParam)), FoldedSha256Error> {
+    for message in messages {
+        config.check_input_len(message.len())?;
+    }
+
+    let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+    let nova_params = preprocess(F_circuit, None, rng)?;
+
+    let mut folding_schemes = Vec::with_capacity(messages.len());
+    for message in messages {
+        let mut folding_scheme = N::init(&nova_params, F_circuit, F_circuit.initial_state())?;
+        prove_steps(
+            &mut folding_scheme,
+            BlockExternalInputs::<Fr, _>::new(message.clone()),
+            config,
+            rng,
+        )?;
+        folding_schemes.push(folding_scheme);
+    }
+
+    Ok((folding_schemes, nova_params))
+}
+
+/// Runs the Nova setup and folds the message blocks received on `receiver` as they arrive, e.g.
+/// in a prover service fed by another thread. When the channel closes, the padding of the whole
+/// message, whose length is that of the received blocks, is folded as well.
+///
+/// The length of the message is only known at the end, so `config.max_blocks` is checked on the
+/// blocks received so far and the padding block still to come: the folding stops with
+/// `FoldedSha256Error::InputTooLarge` on the first block which would exceed it.
+pub fn fold_from_channel<R: RngCore + CryptoRng>(
+    receiver: Receiver<[u8; BLOCK_LENGTH_BYTES]>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam)), FoldedSha256Error> {
+    let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+    let nova_params = preprocess(F_circuit, None, rng)?;
+    let mut folding_scheme = N::init(&nova_params, F_circuit, F_circuit.initial_state())?;
+
+    let mut num_blocks = 0;
+    for block in receiver {
+        num_blocks += 1;
+        // the message is block-aligned, its padding takes a block of its own
+        config.check_num_blocks(num_blocks + 1)?;
+        prove_steps(
+            &mut folding_scheme,
+            [ByteLayout::encode(&block)],
+            config,
+            rng,
+        )?;
+    }
+    let len = (num_blocks * BLOCK_LENGTH_BYTES) as u64;
+    let padding = BlockExternalInputs::<Fr, _>::with_prefix_len(core::iter::empty(), len)
+        .map_err(|_| FoldedSha256Error::MessageTooLong)?;
+    prove_steps(&mut folding_scheme, padding, config, rng)?;
+
+    Ok((folding_scheme, nova_params))
+}
+
+/// Runs the Nova setup and folds the blocks of `input` until `predicate` holds on the
+/// intermediate state, e.g. for a search on the chaining values of a message.
+///
+/// The predicate is evaluated natively on the SHA-256 state after each folded block, and the
+/// folding stops at the first step where it returns `true`. It is not part of the circuit: the
+/// proof only covers the compression chain over the folded prefix of the blocks, and its final
+/// state is the chaining value after them, a SHA-256 digest only if every block was folded. A
+/// verifier learns nothing about the predicate. If it never holds, the whole padded message is
+/// folded as with `run`; either way `folding_scheme.i` is the number of folded blocks.
+///
+/// Also returns the index of the step after which the predicate held, `None` if it never did.
+pub fn fold_until<P: Fn(&[u32; 8]) -> bool, R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    predicate: P,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam), Option<usize>), FoldedSha256Error> {
+    config.check_input_len(input.len())?;
+
+    let F_circuit = FoldedSha256FCircuit::<Fr>::sha256();
+    let nova_params = preprocess(F_circuit, None, rng)?;
+    let mut folding_scheme = N::init(&nova_params, F_circuit, F_circuit.initial_state())?;
+
+    for (i, external_inputs) in BlockExternalInputs::<Fr, _>::new(input).enumerate() {
+        prove_steps(&mut folding_scheme, [external_inputs], config, rng)?;
+
+        let state: [u32; 8] = core::array::from_fn(|j| bigint_to_u32(folding_scheme.z_i[j]));
+        if predicate(&state) {
+            return Ok((folding_scheme, nova_params, Some(i)));
+        }
+    }
+
+    Ok((folding_scheme, nova_params, None))
+}
+
+/// Folds one step per item of `external_inputs` into `folding_scheme`, see `fold`, and returns
+/// the duration of each of them.
+fn prove_steps<FC: FCircuit<Fr>, R: RngCore + CryptoRng>(
+    folding_scheme: &mut NovaFor<FC>,
+    external_inputs: impl IntoIterator<Item = Vec<Fr>>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<Vec<Duration>, FoldedSha256Error> {
+    let mut durations = Vec::new();
+    // compute a step of the IVC
+    for external_inputs_at_step in external_inputs {
+        #[cfg(feature = "self-check")]
+        let (i, z_prev) = (
+            bigint_to_u32(folding_scheme.i) as usize,
+            folding_scheme.state(),
+        );
+
+        durations.push(prove_step_timed(
+            folding_scheme,
+            external_inputs_at_step.clone(),
+            config,
+            rng,
+        )?);
+
+        #[cfg(feature = "self-check")]
+        verify_step_native(
+            &folding_scheme.F,
+            i,
+            z_prev,
+            external_inputs_at_step,
+            &folding_scheme.state(),
+        )?;
+    }
+    Ok(durations)
+}
+
+/// Folds the next step of `folding_scheme` with `external_inputs`, retrying it as allowed by
+/// `config`, and returns the time it took (retries included) instead of printing it, for callers
+/// doing their own instrumentation.
+pub fn prove_step_timed<FC: FCircuit<Fr>, R: RngCore + CryptoRng>(
+    folding_scheme: &mut NovaFor<FC>,
+    external_inputs: Vec<Fr>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<Duration, FoldedSha256Error> {
+    let i = bigint_to_u32(folding_scheme.i) as usize;
+    let start = Instant::now();
+    retry_step(i, config.max_step_retries, || {
+        folding_scheme.prove_step(&mut *rng, external_inputs.clone(), None)
+    })?;
+    Ok(start.elapsed())
+}
+
+/// Snapshot of the committed instances of a folding after a step, e.g. for an aggregation layer
+/// built on top of the folding.
+///
+/// After the last step, `U_i` and `u_i` are the instances the Decider proves, and their
+/// commitments are those `D::verify` receives, see `proof::PublicInputsBundle`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct InstanceSnapshot {
+    /// Number of folded steps.
+    pub i: Fr,
+    /// Running instance, accumulating the steps folded so far: the commitments `cmE` to the
+    /// error term and `cmW` to the witness, the relaxation scalar `u` and the public inputs `x`.
+    pub U_i: CommittedInstance<Projective>,
+    /// Incoming instance of the last step, not folded into `U_i` yet. It is not relaxed: `cmE`
+    /// is zero and `u` is one.
+    pub u_i: CommittedInstance<Projective>,
+}
+
+impl InstanceSnapshot {
+    pub fn new<FC: FCircuit<Fr>>(folding_scheme: &NovaFor<FC>) -> Self {
+        Self {
+            i: folding_scheme.i,
+            U_i: folding_scheme.U_i.clone(),
+            u_i: folding_scheme.u_i.clone(),
+        }
+    }
+}
+
+/// Same as `prove_step_timed`, returning the snapshot of the instances after the step instead of
+/// its duration.
+pub fn prove_step_snapshot<FC: FCircuit<Fr>, R: RngCore + CryptoRng>(
+    folding_scheme: &mut NovaFor<FC>,
+    external_inputs: Vec<Fr>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<InstanceSnapshot, FoldedSha256Error> {
+    prove_step_timed(folding_scheme, external_inputs, config, rng)?;
+    Ok(InstanceSnapshot::new(folding_scheme))
+}
+
+/// Folding of a message whose blocks are fed in several batches, e.g. as they are received.
+///
+/// Nova folds a fixed step function from `z_0`, so the session is bound to the variant it was
+/// started with: a batch of blocks of another variant is rejected with
+/// `FoldedSha256Error::VariantMismatch` before any of its steps is proven.
+pub struct FoldingSession<FC: FCircuit<Fr>> {
+    pub variant: Variant,
+    pub folding_scheme: NovaFor<FC>,
+    pub params: NovaParamsFor<FC>,
+    blocks_folded: usize,
+}
+
+impl<FC: FCircuit<Fr>> FoldingSession<FC> {
+    /// Runs the Nova setup for `F_circuit` and initializes the folding from the initial state of
+    /// `variant`.
+    pub fn new<R: RngCore + CryptoRng>(
+        F_circuit: FC,
+        variant: Variant,
+        rng: &mut R,
+    ) -> Result<Self, FoldedSha256Error> {
+        let params = preprocess(F_circuit.clone(), None, rng)?;
+        let z_0 = variant
+            .initial_state()
+            .iter()
+            .map(|&x| Fr::from(x))
+            .collect();
+        let folding_scheme = NovaFor::<FC>::init(&params, F_circuit, z_0)?;
+        Ok(Self {
+            variant,
+            folding_scheme,
+            params,
+            blocks_folded: 0,
         })
-        .collect::<Vec<u8>>();
+    }
+
+    /// Number of folding steps proven so far, over all the batches.
+    pub fn steps_completed(&self) -> usize {
+        bigint_to_u32(self.folding_scheme.i) as usize
+    }
+
+    /// Number of blocks folded so far, over all the batches: `config.blocks_per_step` per step,
+    /// the no-op blocks completing the last step of a multi-block folding included.
+    pub fn blocks_folded(&self) -> usize {
+        self.blocks_folded
+    }
+
+    /// Whether the blocks folded so far cover the `total_blocks` blocks of the padded message,
+    /// i.e. the session can be finalized.
+    pub fn is_complete(&self, total_blocks: usize) -> bool {
+        self.blocks_folded >= total_blocks
+    }
+
+    /// Folds one step per item of `external_inputs`, which must be blocks of `variant`,
+    /// `config.blocks_per_step` blocks per step, and returns the duration of each step.
+    pub fn prove_steps<R: RngCore + CryptoRng>(
+        &mut self,
+        variant: Variant,
+        external_inputs: impl IntoIterator<Item = Vec<Fr>>,
+        config: &FoldConfig,
+        rng: &mut R,
+    ) -> Result<Vec<Duration>, FoldedSha256Error> {
+        if variant != self.variant {
+            return Err(FoldedSha256Error::VariantMismatch {
+                expected: self.variant,
+                found: variant,
+            });
+        }
+        let steps_before = self.steps_completed();
+        let durations = prove_steps(&mut self.folding_scheme, external_inputs, config, rng);
+        // the steps of a batch failing midway are folded all the same
+        self.blocks_folded +=
+            (self.steps_completed() - steps_before) * config.blocks_per_step.max(1);
+        durations
+    }
+}
+
+/// Runs the Nova preprocessing for `F_circuit`, returning `FoldedSha256Error::Setup` if it fails.
+///
+/// By default the KZG parameters are generated from `rng`. When `kzg_srs` is given (e.g. loaded
+/// from a trusted setup), it is used instead, after checking it has enough powers for the
+/// circuit: an undersized SRS is reported as a setup error instead of failing later, while
+/// committing to the first witness. See `srs::SrsCache` to expand a cached SRS instead.
+pub fn preprocess<FC: FCircuit<Fr>, R: RngCore + CryptoRng>(
+    F_circuit: FC,
+    kzg_srs: Option<(KZGProverParam, KZGVerifierParam)>,
+    rng: &mut R,
+) -> Result<NovaParamsFor<FC>, FoldedSha256Error> {
+    let poseidon_config = poseidon_canonical_config::<Fr>();
+    let mut nova_preprocess_params = PreprocessorParam::new(poseidon_config, F_circuit);
+
+    if let Some((kzg_pp, kzg_vp)) = kzg_srs {
+        let required = required_srs_degree(nova_preprocess_params.F.clone())?;
+        let available = kzg_pp.powers_of_g.len();
+        if available < required {
+            return Err(FoldedSha256Error::Setup(Error::Other(format!(
+                "the KZG SRS has {} powers, but the circuit needs {}",
+                available, required
+            ))));
+        }
+        nova_preprocess_params.cs_pp = Some(kzg_pp);
+        nova_preprocess_params.cs_vp = Some(kzg_vp);
+    }
+
+    NovaFor::<FC>::preprocess(&mut *rng, &nova_preprocess_params).map_err(FoldedSha256Error::Setup)
+}
+
+/// Number of constraints of one folding step for a variant and an external inputs layout, see
+/// `report_constraints`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConstraintCount {
+    pub variant: Variant,
+    /// `"bytes"` for `ByteLayout`, `"words"` for `WordLayout`.
+    pub layout: &'static str,
+    pub num_constraints: usize,
+}
+
+/// Synthesizes `generate_step_constraints` for each variant and external inputs layout,
+/// returning their constraint counts, e.g. to pick the cheapest settings. The counts do not
+/// include the Nova augmentation of the step circuit, which is the same for all of them.
+pub fn report_constraints() -> Result<Vec<ConstraintCount>, SynthesisError> {
+    let mut report = Vec::new();
+    for variant in [Variant::Sha256, Variant::Sha224] {
+        report.push(ConstraintCount {
+            variant,
+            layout: "bytes",
+            num_constraints: step_constraints::<ByteLayout>(variant)?,
+        });
+        report.push(ConstraintCount {
+            variant,
+            layout: "words",
+            num_constraints: step_constraints::<WordLayout>(variant)?,
+        });
+    }
+    Ok(report)
+}
+
+fn step_constraints<L: ExternalInputsLayout>(variant: Variant) -> Result<usize, SynthesisError> {
+    let circuit = FoldedSha256FCircuit::<Fr, L>::with_variant(variant);
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let z_i = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(circuit.initial_state()))?;
+    let external_inputs =
+        Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(L::encode(&[0u8; BLOCK_LENGTH_BYTES])))?;
+    circuit.generate_step_constraints(cs.clone(), 0, z_i, external_inputs)?;
+    Ok(cs.num_constraints())
+}
+
+/// Recomputes natively step `i` from `z_prev` and checks it matches the state `z_i` reported by
+/// the folding scheme, returning `FoldedSha256Error::SelfCheckFailed` otherwise.
+///
+/// This catches any divergence between `step_native` and `generate_step_constraints` right at the
+/// faulty step instead of at the final digest. `fold` runs it after each step when the
+/// `self-check` feature is enabled.
+pub fn verify_step_native<FC: FCircuit<Fr>>(
+    step_circuit: &FC,
+    i: usize,
+    z_prev: Vec<Fr>,
+    external_inputs: Vec<Fr>,
+    z_i: &[Fr],
+) -> Result<(), FoldedSha256Error> {
+    let expected = step_circuit.step_native(i, z_prev, external_inputs)?;
+    if expected != z_i {
+        return Err(FoldedSha256Error::SelfCheckFailed { step: i });
+    }
+    Ok(())
+}
+
+/// Verifier checking a folding as its steps arrive, from the IVC proof sent after each of them.
+///
+/// Nova has no cheaper per-step check than the full IVC verification: `verify_step` runs
+/// `N::verify` on each proof, which checks that the running instance is satisfiable and
+/// consistent with the claimed `i`, `z_0` and `z_i`, at the same cost as the final
+/// verification. A proof accepted after step `i` is thus already a complete proof that `z_i` is
+/// the state after `i` steps from `z_0`, so a stream can be cut at any point. On top of it, the
+/// verifier checks that each proof is for the next step and from the same `z_0` as the previous
+/// ones. Successive proofs are otherwise independent: the states in between are not carried over
+/// (a proof only exposes its own `z_i`), so no more is guaranteed than by the last proof alone.
+pub struct IncrementalVerifier {
+    nova_vp: NovaVerifierParam,
+    z_0: Vec<Fr>,
+    num_steps: usize,
+}
+
+impl IncrementalVerifier {
+    /// Verifier of a folding from `z_0` with the verifier parameters `nova_vp`.
+    pub fn new(nova_vp: NovaVerifierParam, z_0: Vec<Fr>) -> Self {
+        Self {
+            nova_vp,
+            z_0,
+            num_steps: 0,
+        }
+    }
 
-    let hex_string = final_hash
-        .iter()
-        .map(|byte| format!("{:02x}", byte))
-        .collect::<String>();
+    /// Number of steps verified so far.
+    pub fn num_steps(&self) -> usize {
+        self.num_steps
+    }
+
+    /// Verifies the IVC proof of the next step, returning `FoldedSha256Error::UnexpectedStep` if
+    /// it is not for the next step or starts from another `z_0`.
+    pub fn verify_step(
+        &mut self,
+        ivc_proof: IVCProof<Projective, Projective2>,
+    ) -> Result<(), FoldedSha256Error> {
+        let step = self.num_steps + 1;
+        if ivc_proof.i != Fr::from(step as u64) || ivc_proof.z_0 != self.z_0 {
+            return Err(FoldedSha256Error::UnexpectedStep { step });
+        }
+        N::verify(self.nova_vp.clone(), ivc_proof)?;
+        self.num_steps = step;
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+fn main() {
+    let input: Vec<u8> = b"abc".to_vec();
+    let mut rng = rand::rngs::OsRng;
+
+    let (folding_scheme, nova_params) = run(input, &FoldConfig::default(), &mut rng).unwrap();
+
+    println!("Run the Nova's IVC verifier");
+    let start = Instant::now();
+    verify_ivc(nova_params.1, folding_scheme.ivc_proof()).unwrap();
+    println!("verify_ivc took {:?}", start.elapsed());
 
-    println!("Final hash: {}", hex_string);
+    println!("Final hash: {}", Digest::from_state(&folding_scheme.z_i));
 }
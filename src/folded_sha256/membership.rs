@@ -0,0 +1,300 @@
+//! Proof that the SHA-256 digest of a private message is one of a public set of allowed
+//! digests, without revealing which.
+//!
+//! The last step checks the digest against the set, a constant of the circuit, and replaces it
+//! with zeros in the state, so that the public final state `z_n` does not contain it. The
+//! intermediate states are not public: Nova only exposes `z_0` and `z_n`.
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit::{
+    bytes_from_fp, compression_round_with_options, enforce_digest_in_set,
+};
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::{violation, FoldedSha256Error};
+use crate::folded_sha256::hasher::sha256;
+use crate::folded_sha256::layout::{ByteLayout, ExternalInputsLayout};
+use crate::folded_sha256::main::{bigint_to_u32, fold, NovaFor, NovaParamsFor, H, STATE_LEN};
+use crate::folded_sha256::utils::{
+    compress_block, sha256_msg_block_sequence, CompressionOptions, BLOCK_LENGTH_BYTES,
+};
+
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_ff::{One, Zero};
+use ark_grumpkin::Projective as Projective2;
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    select::CondSelectGadget,
+    uint32::UInt32,
+    R1CSVar,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::folding::nova::IVCProof;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::{Error, FoldingScheme};
+
+/// SHA-256 folding circuit proving the digest is one of `set`.
+///
+/// The state is `z_i = [H_0, ..., H_7, done]` and the external inputs are the 64 bytes of the
+/// block followed by a flag, 1 for the last block and 0 otherwise. No step can follow one with
+/// `done = 1`. The last step enforces that its output is in `set`, see
+/// `circuit::enforce_digest_in_set`, and outputs `set_membership_final_state`.
+#[derive(Clone, Debug)]
+pub struct FoldedSha256SetMembershipFCircuit {
+    set: Vec<[u8; 32]>,
+}
+
+impl FCircuit<Fr> for FoldedSha256SetMembershipFCircuit {
+    /// The allowed digests.
+    type Params = Vec<[u8; 32]>;
+
+    fn new(set: Self::Params) -> Result<Self, Error> {
+        Ok(Self { set })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + 1
+    }
+    fn external_inputs_len(&self) -> usize {
+        BLOCK_LENGTH_BYTES + 1
+    }
+
+    fn step_native(
+        &self,
+        _i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        if !z_i[STATE_LEN].is_zero() {
+            return Err(Error::Other(
+                "no block can be folded after the last one".to_string(),
+            ));
+        }
+        let state: [u32; 8] = core::array::from_fn(|j| bigint_to_u32(z_i[j]));
+        let block: [u8; BLOCK_LENGTH_BYTES] =
+            ByteLayout::decode(&external_inputs[..BLOCK_LENGTH_BYTES])
+                .map_err(|e| Error::Other(e.to_string()))?
+                .try_into()
+                .unwrap();
+        let h = compress_block(&state, &block);
+
+        let is_last = external_inputs[BLOCK_LENGTH_BYTES];
+        if is_last.is_zero() {
+            let mut z_i1: Vec<Fr> = h.iter().map(|&x| Fr::from(x)).collect();
+            z_i1.push(Fr::zero());
+            Ok(z_i1)
+        } else if is_last.is_one() {
+            let digest: Vec<u8> = h.iter().flat_map(|x| x.to_be_bytes()).collect();
+            if !self.set.iter().any(|element| element[..] == digest[..]) {
+                return Err(Error::Other(
+                    "the digest is not in the set of allowed digests".to_string(),
+                ));
+            }
+            Ok(set_membership_final_state())
+        } else {
+            Err(Error::Other(
+                "the last block flag must be 0 or 1".to_string(),
+            ))
+        }
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        _i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        if z_i.len() != self.state_len() || external_inputs.len() != self.external_inputs_len() {
+            return violation(SynthesisError::Unsatisfiable);
+        }
+        z_i[STATE_LEN].enforce_equal(&FpVar::zero())?;
+
+        // the state words are outputs of the previous step, hence already 32-bit
+        let mut state = z_i[..STATE_LEN]
+            .iter()
+            .map(|x| Ok(UInt32::from_fp(x)?.0))
+            .collect::<Result<Vec<UInt32<Fr>>, SynthesisError>>()?;
+        let data = bytes_from_fp(&external_inputs[..BLOCK_LENGTH_BYTES])?;
+        let h = compression_round_with_options(&mut state, &data, &CompressionOptions::default())?;
+
+        let flag = &external_inputs[BLOCK_LENGTH_BYTES];
+        let is_last = Boolean::new_witness(cs, || Ok(flag.value()?.is_one()))?;
+        FpVar::from(is_last.clone()).enforce_equal(flag)?;
+        enforce_digest_in_set(&h, &self.set, &is_last)?;
+
+        let mut z_i1 = h
+            .iter()
+            .map(|x| is_last.select(&FpVar::zero(), &x.to_fp()?))
+            .collect::<Result<Vec<FpVar<Fr>>, SynthesisError>>()?;
+        z_i1.push(FpVar::from(is_last));
+        Ok(z_i1)
+    }
+}
+
+/// The initial state of a set membership folding: the SHA-256 IV, not done.
+pub fn set_membership_initial_state() -> Vec<Fr> {
+    let mut z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    z_0.push(Fr::zero());
+    z_0
+}
+
+/// The final state of a set membership folding: the digest replaced by zeros, done.
+pub fn set_membership_final_state() -> Vec<Fr> {
+    let mut z_n = vec![Fr::zero(); STATE_LEN];
+    z_n.push(Fr::one());
+    z_n
+}
+
+/// Folds the SHA-256 compression of `input` with `FoldedSha256SetMembershipFCircuit`, proving
+/// its digest is one of `set` without revealing it.
+///
+/// Returns `FoldedSha256Error::DigestNotInSet` before any folding if it is not. The verifier
+/// parameters depend on `set`, see `verify_digest_in_set`.
+pub fn prove_digest_in_set<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    set: Vec<[u8; 32]>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256SetMembershipFCircuit>,
+        NovaParamsFor<FoldedSha256SetMembershipFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+    if !set.contains(&sha256(&input).0) {
+        return Err(FoldedSha256Error::DigestNotInSet);
+    }
+
+    let blocks = sha256_msg_block_sequence(input);
+    let num_blocks = blocks.len();
+    let external_inputs = blocks.into_iter().enumerate().map(move |(i, block)| {
+        let mut external_inputs = ByteLayout::encode::<Fr>(&block);
+        external_inputs.push(Fr::from((i + 1 == num_blocks) as u64));
+        external_inputs
+    });
+
+    fold(
+        FoldedSha256SetMembershipFCircuit::new(set)?,
+        set_membership_initial_state(),
+        external_inputs,
+        config,
+        rng,
+    )
+}
+
+/// Verifies the IVC proof produced by `prove_digest_in_set` with the verifier parameters of the
+/// set.
+///
+/// Returns `FoldedSha256Error::InvalidInitialState` if the proof does not start from
+/// `set_membership_initial_state`, and `FoldedSha256Error::DigestNotInSet` if it does not end
+/// with the last block, i.e. the membership was not checked.
+pub fn verify_digest_in_set(
+    vp: <NovaFor<FoldedSha256SetMembershipFCircuit> as FoldingScheme<
+        Projective,
+        Projective2,
+        FoldedSha256SetMembershipFCircuit,
+    >>::VerifierParam,
+    ivc_proof: IVCProof<Projective, Projective2>,
+) -> Result<(), FoldedSha256Error> {
+    if ivc_proof.z_0 != set_membership_initial_state() {
+        return Err(FoldedSha256Error::InvalidInitialState);
+    }
+    if ivc_proof.z_i != set_membership_final_state() {
+        return Err(FoldedSha256Error::DigestNotInSet);
+    }
+    NovaFor::<FoldedSha256SetMembershipFCircuit>::verify(vp, ivc_proof)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn allowed() -> Vec<[u8; 32]> {
+        vec![
+            sha256(b"alice").0,
+            sha256(&[b'b'; 100]).0,
+            sha256(b"carol").0,
+        ]
+    }
+
+    fn step_external_inputs(input: &[u8]) -> Vec<Vec<Fr>> {
+        let blocks = sha256_msg_block_sequence(input.to_vec());
+        let num_blocks = blocks.len();
+        blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let mut external_inputs = ByteLayout::encode::<Fr>(block);
+                external_inputs.push(Fr::from((i + 1 == num_blocks) as u64));
+                external_inputs
+            })
+            .collect()
+    }
+
+    /// Runs every step natively and in-circuit, returning the final native state and whether
+    /// every constraint system was satisfied.
+    fn run_steps(input: &[u8]) -> (Result<Vec<Fr>, Error>, bool) {
+        let circuit = FoldedSha256SetMembershipFCircuit::new(allowed()).unwrap();
+        let mut z_i = set_membership_initial_state();
+        let mut z_native = Ok(z_i.clone());
+        let mut satisfied = true;
+        for (i, external_inputs) in step_external_inputs(input).into_iter().enumerate() {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i.clone())).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs.clone())).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+            satisfied &= cs.is_satisfied().unwrap();
+
+            z_native = z_native.and_then(|z| circuit.step_native(i, z, external_inputs));
+            z_i = computed_z_i1Var.value().unwrap();
+        }
+        (z_native, satisfied)
+    }
+
+    #[test]
+    fn test_set_membership_f_circuit() {
+        let (z_native, satisfied) = run_steps(&[b'b'; 100]);
+        assert_eq!(z_native.unwrap(), set_membership_final_state());
+        assert!(satisfied);
+
+        let (z_native, satisfied) = run_steps(b"mallory");
+        assert!(z_native.is_err());
+        assert!(!satisfied);
+    }
+
+    #[test]
+    fn test_prove_digest_in_set() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, vp)) = prove_digest_in_set(
+            b"carol".to_vec(),
+            allowed(),
+            &FoldConfig::default(),
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(folding_scheme.z_i, set_membership_final_state());
+        verify_digest_in_set(vp, folding_scheme.ivc_proof()).unwrap();
+
+        assert!(matches!(
+            prove_digest_in_set(
+                b"mallory".to_vec(),
+                allowed(),
+                &FoldConfig::default(),
+                &mut rng
+            ),
+            Err(FoldedSha256Error::DigestNotInSet)
+        ));
+    }
+}
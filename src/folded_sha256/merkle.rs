@@ -0,0 +1,516 @@
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit::{bytes_from_fp, one_compression_round, words_to_bytes_be};
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{
+    bigint_to_u32, bytes_from_field, fold, NovaFor, NovaParamsFor, H, STATE_LEN,
+};
+use crate::folded_sha256::utils::{
+    sha256_msg_block_sequence, update_state_ref, BLOCK_LENGTH_BYTES,
+};
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig, PoseidonSponge},
+    Absorb, CryptographicSponge,
+};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+    uint32::UInt32,
+    uint8::UInt8,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::Error;
+
+/// Number of digests the node stack of `FoldedSha256MerkleFCircuit` can hold, which bounds the
+/// trees it can fold to `2^(MERKLE_STACK_DEPTH - 1)` leaves.
+pub const MERKLE_STACK_DEPTH: usize = 8;
+
+/// Operation performed by a folding step of `FoldedSha256MerkleFCircuit`, selected by the first
+/// external input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MerkleOp {
+    /// Compress the next block of the current leaf.
+    LeafBlock = 0,
+    /// Compress the last block of the current leaf and push the leaf digest onto the stack.
+    LeafLastBlock = 1,
+    /// Pop the two topmost digests `left` and `right` and push `SHA256(left || right)`.
+    Combine = 2,
+}
+
+/// Hash of the internal nodes of the tree folded by `FoldedSha256MerkleFCircuit`.
+#[derive(Clone, Debug)]
+pub enum MerkleNodeHash<F: PrimeField> {
+    /// `SHA256(left || right)` of the two child digests.
+    Sha256,
+    /// `Poseidon(left || right)` of the 8 state elements of each child, for a hybrid tree with
+    /// SHA-256 leaves. The node takes the first element of its stack slot, the 7 others being 0,
+    /// and the root is read with `hybrid_merkle_root_from_state`.
+    ///
+    /// The node combination then costs a few hundred constraints instead of two SHA-256
+    /// compressions, while the leaves keep their SHA-256 semantics. The root is not a SHA-256
+    /// Merkle root though: a verifier must recompute it with the same Poseidon configuration.
+    Poseidon(PoseidonConfig<F>),
+}
+
+/// Folding circuit proving a Merkle root where each leaf is `SHA256(chunk)` and each internal node
+/// is `SHA256(left || right)`, or `Poseidon(left || right)` (see `MerkleNodeHash`), all in a
+/// single IVC.
+///
+/// The state is `z_i = [cur_0..cur_7, stack_0[0..8], ..., stack_{D-1}[0..8]]`, where `cur` is the
+/// SHA-256 state of the leaf being hashed (`H` between leaves) and `stack_0` is the top of a node
+/// stack of depth `D = MERKLE_STACK_DEPTH`. The external inputs are `[op, w_0, ..., w_63]`, `op`
+/// being a `MerkleOp` and `w` the next leaf block (ignored by `Combine`).
+///
+/// Since the step function must be the same at every step, each step computes both the leaf
+/// compression and the node combination (with SHA-256, two compressions: the 64-byte
+/// `left || right` message and its constant padding block) and selects the result according to
+/// `op`.
+#[derive(Clone, Debug)]
+pub struct FoldedSha256MerkleFCircuit<F: PrimeField> {
+    node_hash: MerkleNodeHash<F>,
+}
+
+/// Padding block of a 64-byte message, i.e. of the `left || right` message of an internal node.
+fn node_padding_block() -> Vec<u8> {
+    sha256_msg_block_sequence(vec![0u8; BLOCK_LENGTH_BYTES])[1].to_vec()
+}
+
+/// Native `SHA256(left || right)` of two digests given as SHA-256 states.
+fn sha256_combine_native(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let message: Vec<u8> = left
+        .iter()
+        .chain(right.iter())
+        .flat_map(|x| x.to_be_bytes())
+        .collect();
+    let inner = update_state_ref(H.to_vec(), message).unwrap();
+    update_state_ref(inner, node_padding_block()).unwrap()
+}
+
+/// Native `Poseidon(left || right)` of two nodes given as stack slots.
+fn poseidon_combine_native<F: PrimeField + Absorb>(
+    poseidon_config: &PoseidonConfig<F>,
+    left: &[F],
+    right: &[F],
+) -> F {
+    let mut sponge = PoseidonSponge::<F>::new(poseidon_config);
+    sponge.absorb(&left.to_vec());
+    sponge.absorb(&right.to_vec());
+    sponge.squeeze_field_elements(1)[0]
+}
+
+/// State of the Merkle folding before the first step: `cur = H` and an empty stack.
+pub fn merkle_initial_state<F: PrimeField>() -> Vec<F> {
+    let mut z_0: Vec<F> = H.iter().map(|&x| F::from(x)).collect();
+    z_0.extend(vec![F::zero(); STATE_LEN * MERKLE_STACK_DEPTH]);
+    z_0
+}
+
+/// Builds the external inputs of each folding step for the tree over `chunks`, in postorder: every
+/// leaf is hashed block by block and pushed, then combined with its left sibling as soon as the
+/// latter is complete. The number of chunks must be a power of two, at most
+/// `2^(MERKLE_STACK_DEPTH - 1)`.
+pub fn merkle_external_inputs<F: PrimeField>(
+    chunks: &[Vec<u8>],
+) -> Result<Vec<Vec<F>>, FoldedSha256Error> {
+    if !chunks.len().is_power_of_two() || chunks.len() > 1 << (MERKLE_STACK_DEPTH - 1) {
+        return Err(FoldedSha256Error::InvalidMerkleTree {
+            num_leaves: chunks.len(),
+        });
+    }
+
+    let step = |op: MerkleOp, block: &[u8]| {
+        let mut external_inputs = vec![F::from(op as u64)];
+        external_inputs.extend(block.iter().map(|&x| F::from(x)));
+        external_inputs
+    };
+
+    let mut steps = vec![];
+    for (i, chunk) in chunks.iter().enumerate() {
+        let blocks = sha256_msg_block_sequence(chunk.clone());
+        for (j, block) in blocks.iter().enumerate() {
+            let op = if j + 1 == blocks.len() {
+                MerkleOp::LeafLastBlock
+            } else {
+                MerkleOp::LeafBlock
+            };
+            steps.push(step(op, block));
+        }
+        // leaf i completes as many subtrees as there are trailing zeros in i + 1
+        for _ in 0..(i + 1).trailing_zeros() {
+            steps.push(step(MerkleOp::Combine, &[0u8; BLOCK_LENGTH_BYTES]));
+        }
+    }
+    Ok(steps)
+}
+
+/// Returns the root, i.e. the top of the stack, from the final state of the Merkle folding.
+pub fn merkle_root_from_state<F: PrimeField>(z_i: &[F]) -> [u8; 32] {
+    let root: Vec<u8> = z_i[STATE_LEN..2 * STATE_LEN]
+        .iter()
+        .flat_map(|&x| bigint_to_u32(x).to_be_bytes())
+        .collect();
+    root.try_into().unwrap()
+}
+
+/// Returns the root from the final state of the Merkle folding with `MerkleNodeHash::Poseidon`.
+/// For a tree of a single leaf, which has no internal node, use `merkle_root_from_state`.
+pub fn hybrid_merkle_root_from_state<F: PrimeField>(z_i: &[F]) -> F {
+    z_i[STATE_LEN]
+}
+
+impl<F: PrimeField + Absorb> FoldedSha256MerkleFCircuit<F> {
+    /// Node combination of two stack slots, as a stack slot.
+    fn combine_native(&self, left: &[F], right: &[F]) -> Vec<F> {
+        match &self.node_hash {
+            MerkleNodeHash::Sha256 => {
+                let to_words = |x: &[F]| x.iter().map(|&x| bigint_to_u32(x)).collect::<Vec<_>>();
+                sha256_combine_native(&to_words(left), &to_words(right))
+                    .into_iter()
+                    .map(F::from)
+                    .collect()
+            }
+            MerkleNodeHash::Poseidon(poseidon_config) => {
+                let mut node = vec![F::zero(); STATE_LEN];
+                node[0] = poseidon_combine_native(poseidon_config, left, right);
+                node
+            }
+        }
+    }
+}
+
+impl<F: PrimeField + Absorb> FCircuit<F> for FoldedSha256MerkleFCircuit<F> {
+    type Params = MerkleNodeHash<F>;
+
+    fn new(params: Self::Params) -> Result<Self, Error> {
+        Ok(Self { node_hash: params })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN * (1 + MERKLE_STACK_DEPTH)
+    }
+    fn external_inputs_len(&self) -> usize {
+        1 + BLOCK_LENGTH_BYTES
+    }
+
+    fn step_native(
+        &self,
+        _i: usize,
+        z_i: Vec<F>,
+        external_inputs: Vec<F>,
+    ) -> Result<Vec<F>, Error> {
+        let cur: Vec<u32> = z_i[..STATE_LEN].iter().map(|&x| bigint_to_u32(x)).collect();
+        let mut stack: Vec<Vec<F>> = z_i[STATE_LEN..]
+            .chunks(STATE_LEN)
+            .map(|x| x.to_vec())
+            .collect();
+        let block = bytes_from_field(&external_inputs[1..])?;
+
+        let next_cur = if external_inputs[0] == F::from(MerkleOp::LeafBlock as u64) {
+            update_state_ref(cur, block).unwrap()
+        } else if external_inputs[0] == F::from(MerkleOp::LeafLastBlock as u64) {
+            let leaf = update_state_ref(cur, block).unwrap();
+            stack.insert(0, leaf.into_iter().map(F::from).collect());
+            stack.pop();
+            H.to_vec()
+        } else if external_inputs[0] == F::from(MerkleOp::Combine as u64) {
+            let right = stack.remove(0);
+            stack[0] = self.combine_native(&stack[0], &right);
+            stack.push(vec![F::zero(); STATE_LEN]);
+            H.to_vec()
+        } else {
+            return Err(Error::Other("unknown Merkle operation".to_string()));
+        };
+
+        Ok(next_cur
+            .into_iter()
+            .map(F::from)
+            .chain(stack.into_iter().flatten())
+            .collect())
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        _i: usize,
+        z_i: Vec<FpVar<F>>,
+        external_inputs: Vec<FpVar<F>>,
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let op = &external_inputs[0];
+        let is_leaf_block = op.is_eq(&FpVar::constant(F::from(MerkleOp::LeafBlock as u64)))?;
+        let is_leaf_last = op.is_eq(&FpVar::constant(F::from(MerkleOp::LeafLastBlock as u64)))?;
+        let is_combine = op.is_eq(&FpVar::constant(F::from(MerkleOp::Combine as u64)))?;
+        Boolean::kary_or(&[
+            is_leaf_block.clone(),
+            is_leaf_last.clone(),
+            is_combine.clone(),
+        ])?
+        .enforce_equal(&Boolean::TRUE)?;
+
+        let to_words = |vars: &[FpVar<F>]| {
+            vars.iter()
+                .map(|x| Ok(UInt32::from_fp(x)?.0))
+                .collect::<Result<Vec<UInt32<F>>, SynthesisError>>()
+        };
+        let to_fp = |words: Vec<UInt32<F>>| {
+            words
+                .iter()
+                .map(|x| x.to_fp())
+                .collect::<Result<Vec<FpVar<F>>, SynthesisError>>()
+        };
+        let stack = |d: usize| &z_i[STATE_LEN * (d + 1)..STATE_LEN * (d + 2)];
+        let h: Vec<UInt32<F>> = H.iter().map(|&x| UInt32::constant(x)).collect();
+
+        // leaf: compress the next block into the current leaf state
+        let data: Vec<UInt8<F>> = bytes_from_fp(&external_inputs[1..])?;
+        let leaf_state = to_fp(one_compression_round(
+            &mut to_words(&z_i[..STATE_LEN])?,
+            &data,
+        )?)?;
+
+        // node: hash of left || right, with left the second and right the first stack element
+        let node_digest = match &self.node_hash {
+            MerkleNodeHash::Sha256 => {
+                let mut message = words_to_bytes_be(&to_words(stack(1))?)?;
+                message.extend(words_to_bytes_be(&to_words(stack(0))?)?);
+                let mut inner = one_compression_round(&mut h.clone(), &message)?;
+                let padding: Vec<UInt8<F>> = node_padding_block()
+                    .into_iter()
+                    .map(UInt8::constant)
+                    .collect();
+                to_fp(one_compression_round(&mut inner, &padding)?)?
+            }
+            MerkleNodeHash::Poseidon(poseidon_config) => {
+                let mut sponge = PoseidonSpongeVar::<F>::new(cs, poseidon_config);
+                sponge.absorb(&stack(1).to_vec())?;
+                sponge.absorb(&stack(0).to_vec())?;
+                let mut node = vec![FpVar::zero(); STATE_LEN];
+                node[0] = sponge.squeeze_field_elements(1)?.remove(0);
+                node
+            }
+        };
+
+        let mut z_i1 = Vec::with_capacity(z_i.len());
+        for (k, leaf_word) in leaf_state.iter().enumerate() {
+            z_i1.push(is_leaf_block.select(leaf_word, &FpVar::constant(F::from(H[k])))?);
+        }
+        for d in 0..MERKLE_STACK_DEPTH {
+            for k in 0..STATE_LEN {
+                let pushed = if d == 0 {
+                    leaf_state[k].clone()
+                } else {
+                    stack(d - 1)[k].clone()
+                };
+                let combined = if d == 0 {
+                    node_digest[k].clone()
+                } else if d + 1 < MERKLE_STACK_DEPTH {
+                    stack(d + 1)[k].clone()
+                } else {
+                    FpVar::zero()
+                };
+                let unchanged = &stack(d)[k];
+                z_i1.push(is_leaf_last.select(&pushed, &is_combine.select(&combined, unchanged)?)?);
+            }
+        }
+
+        Ok(z_i1)
+    }
+}
+
+/// Folds the Merkle tree over `chunks` (see `merkle_external_inputs`), the root is recovered
+/// from the final state with `merkle_root_from_state`.
+pub fn prove_merkle<R: RngCore + CryptoRng>(
+    chunks: &[Vec<u8>],
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256MerkleFCircuit<Fr>>,
+        NovaParamsFor<FoldedSha256MerkleFCircuit<Fr>>,
+    ),
+    FoldedSha256Error,
+> {
+    prove_merkle_with_node_hash(chunks, MerkleNodeHash::Sha256, rng)
+}
+
+/// Same as `prove_merkle`, with the internal nodes hashed with `node_hash`.
+pub fn prove_merkle_with_node_hash<R: RngCore + CryptoRng>(
+    chunks: &[Vec<u8>],
+    node_hash: MerkleNodeHash<Fr>,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256MerkleFCircuit<Fr>>,
+        NovaParamsFor<FoldedSha256MerkleFCircuit<Fr>>,
+    ),
+    FoldedSha256Error,
+> {
+    let steps = merkle_external_inputs::<Fr>(chunks)?;
+    let F_circuit = FoldedSha256MerkleFCircuit::<Fr>::new(node_hash)?;
+    fold(
+        F_circuit,
+        merkle_initial_state(),
+        steps,
+        &FoldConfig::default(),
+        rng,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::hasher::sha256;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+    use rand_chacha::ChaCha20Rng;
+
+    fn chunks() -> Vec<Vec<u8>> {
+        vec![b"abc".to_vec(), vec![0u8; 100], vec![1u8; 64], vec![]]
+    }
+
+    // independent root of the 4-leaf tree, computed over the byte digests
+    fn reference_root(chunks: &[Vec<u8>]) -> [u8; 32] {
+        let leaves: Vec<[u8; 32]> = chunks.iter().map(|c| sha256(c).0).collect();
+        let node = |l: &[u8; 32], r: &[u8; 32]| sha256(&[l.as_slice(), r.as_slice()].concat()).0;
+        node(&node(&leaves[0], &leaves[1]), &node(&leaves[2], &leaves[3]))
+    }
+
+    #[test]
+    fn test_merkle_root_native() {
+        let circuit = FoldedSha256MerkleFCircuit::<Fr>::new(MerkleNodeHash::Sha256).unwrap();
+
+        let mut z_i = merkle_initial_state::<Fr>();
+        for (i, external_inputs) in merkle_external_inputs::<Fr>(&chunks())
+            .unwrap()
+            .into_iter()
+            .enumerate()
+        {
+            z_i = circuit.step_native(i, z_i, external_inputs).unwrap();
+        }
+
+        assert_eq!(merkle_root_from_state(&z_i), reference_root(&chunks()));
+    }
+
+    #[test]
+    fn test_merkle_f_circuit() {
+        let circuit = FoldedSha256MerkleFCircuit::<Fr>::new(MerkleNodeHash::Sha256).unwrap();
+        let steps = merkle_external_inputs::<Fr>(&chunks()).unwrap();
+
+        // check every kind of step against the native computation
+        let mut z_i = merkle_initial_state::<Fr>();
+        for (i, external_inputs) in steps.into_iter().enumerate() {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_i1 = circuit
+                .step_native(i, z_i.clone(), external_inputs.clone())
+                .unwrap();
+
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+
+            assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+            assert!(cs.is_satisfied().unwrap());
+            z_i = z_i1;
+        }
+    }
+
+    // independent root of the 4-leaf hybrid tree, Poseidon over the SHA-256 leaf words
+    fn reference_hybrid_root(chunks: &[Vec<u8>]) -> Fr {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let leaves: Vec<Vec<Fr>> = chunks
+            .iter()
+            .map(|c| {
+                sha256(c)
+                    .0
+                    .chunks(4)
+                    .map(|w| Fr::from(u32::from_be_bytes(w.try_into().unwrap())))
+                    .collect()
+            })
+            .collect();
+        let node = |l: &[Fr], r: &[Fr]| {
+            let mut sponge = PoseidonSponge::<Fr>::new(&poseidon_config);
+            sponge.absorb(&[l, r].concat());
+            let mut slot = vec![Fr::from(0u64); STATE_LEN];
+            slot[0] = sponge.squeeze_field_elements(1)[0];
+            slot
+        };
+        let root = node(&node(&leaves[0], &leaves[1]), &node(&leaves[2], &leaves[3]));
+        root[0]
+    }
+
+    #[test]
+    fn test_hybrid_merkle_f_circuit() {
+        let poseidon_nodes = FoldedSha256MerkleFCircuit::<Fr>::new(MerkleNodeHash::Poseidon(
+            poseidon_canonical_config::<Fr>(),
+        ))
+        .unwrap();
+        let sha256_nodes = FoldedSha256MerkleFCircuit::<Fr>::new(MerkleNodeHash::Sha256).unwrap();
+        let steps = merkle_external_inputs::<Fr>(&chunks()).unwrap();
+
+        // checks the step against the native computation and returns its number of constraints
+        let check_step = |circuit: &FoldedSha256MerkleFCircuit<Fr>,
+                          i: usize,
+                          z_i: Vec<Fr>,
+                          external_inputs: Vec<Fr>| {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_i1 = circuit
+                .step_native(i, z_i.clone(), external_inputs.clone())
+                .unwrap();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+            assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+            assert!(cs.is_satisfied().unwrap());
+            (z_i1, cs.num_constraints())
+        };
+
+        let mut z_i = merkle_initial_state::<Fr>();
+        let mut sha256_z_i = merkle_initial_state::<Fr>();
+        for (i, external_inputs) in steps.into_iter().enumerate() {
+            let sha256_constraints;
+            (sha256_z_i, sha256_constraints) =
+                check_step(&sha256_nodes, i, sha256_z_i, external_inputs.clone());
+            let poseidon_constraints;
+            (z_i, poseidon_constraints) = check_step(&poseidon_nodes, i, z_i, external_inputs);
+            assert!(poseidon_constraints < sha256_constraints);
+        }
+
+        assert_eq!(
+            hybrid_merkle_root_from_state(&z_i),
+            reference_hybrid_root(&chunks())
+        );
+    }
+
+    #[test]
+    fn test_merkle_rejects_invalid_leaf_count() {
+        assert!(matches!(
+            merkle_external_inputs::<Fr>(&chunks()[..3]),
+            Err(FoldedSha256Error::InvalidMerkleTree { num_leaves: 3 })
+        ));
+    }
+
+    // slow: folds the whole tree with Nova, use `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_prove_merkle() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, _) = prove_merkle(&chunks(), &mut rng).unwrap();
+        assert_eq!(
+            merkle_root_from_state(&folding_scheme.z_i),
+            reference_root(&chunks())
+        );
+    }
+}
@@ -0,0 +1,231 @@
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit::bytes_from_fp;
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{
+    bigint_to_u32, bytes_from_field, fold, FoldedSha256FCircuit, NovaFor, NovaParamsFor, H,
+    STATE_LEN,
+};
+use crate::folded_sha256::utils::{BlockExternalInputs, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_ff::{BigInteger, PrimeField};
+use ark_grumpkin::Projective as Projective2;
+use ark_r1cs_std::fields::{fp::FpVar, FieldVar};
+use ark_r1cs_std::uint8::UInt8;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::folding::nova::IVCProof;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::{Error, FoldingScheme};
+
+/// SHA-256 folding circuit which, alongside the compression, carries the length field of the
+/// last compressed block in an extra state element.
+///
+/// The state is `z_i = [H_0, ..., H_7, len_i]`, where `len_{i+1}` is the big-endian 64-bit integer
+/// in the last 8 bytes of the block `w_i`. Once the final padding block is folded it holds the
+/// length of the preimage in bits, so the verifier can read `message_len` from the final state.
+///
+/// The padding itself is not checked in-circuit: the length is only bound to the last block, i.e.
+/// to the hashed padded message. `message_len_from_state` checks that it is consistent with the
+/// number of folded steps, which is all a verifier can check without the preimage.
+#[derive(Clone, Copy, Debug)]
+pub struct FoldedSha256LengthFCircuit<F: PrimeField> {
+    sha256: FoldedSha256FCircuit<F>,
+}
+
+impl<F: PrimeField> FCircuit<F> for FoldedSha256LengthFCircuit<F> {
+    type Params = ();
+
+    fn new(_params: Self::Params) -> Result<Self, Error> {
+        Ok(Self {
+            sha256: FoldedSha256FCircuit::<F>::sha256(),
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + 1
+    }
+    fn external_inputs_len(&self) -> usize {
+        BLOCK_LENGTH_BYTES
+    }
+
+    fn step_native(&self, i: usize, z_i: Vec<F>, external_inputs: Vec<F>) -> Result<Vec<F>, Error> {
+        let mut z_i1 =
+            self.sha256
+                .step_native(i, z_i[..STATE_LEN].to_vec(), external_inputs.clone())?;
+
+        let length_bytes = bytes_from_field(&external_inputs[BLOCK_LENGTH_BYTES - 8..])?;
+        z_i1.push(F::from(u64::from_be_bytes(
+            length_bytes.try_into().unwrap(),
+        )));
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        i: usize,
+        z_i: Vec<FpVar<F>>,
+        external_inputs: Vec<FpVar<F>>,
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut z_i1 = self.sha256.generate_step_constraints(
+            cs,
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            external_inputs.clone(),
+        )?;
+
+        let length_bytes: Vec<UInt8<F>> =
+            bytes_from_fp(&external_inputs[BLOCK_LENGTH_BYTES - 8..])?;
+        let mut length = FpVar::zero();
+        for byte in length_bytes {
+            length = length * F::from(256u64) + byte.to_fp()?;
+        }
+        z_i1.push(length);
+
+        Ok(z_i1)
+    }
+}
+
+/// Returns the preimage length in bytes carried by the final state `z_i` of
+/// `FoldedSha256LengthFCircuit` after `num_steps` steps.
+///
+/// Returns `FoldedSha256Error::InvalidMessageLength` if the length is not a whole number of bytes
+/// or if its padded message does not take exactly `num_steps` blocks.
+pub fn message_len_from_state<F: PrimeField>(
+    z_i: &[F],
+    num_steps: usize,
+) -> Result<u64, FoldedSha256Error> {
+    // the element is the sum of 8 bytes, so it fits in the 8 least significant bytes
+    let bytes = z_i[STATE_LEN].into_bigint().to_bytes_le();
+    let length_in_bits = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+
+    let message_len = length_in_bits / 8;
+    if length_in_bits % 8 != 0 || FoldConfig::num_blocks(message_len as usize) != num_steps {
+        return Err(FoldedSha256Error::InvalidMessageLength {
+            length_in_bits,
+            num_steps,
+        });
+    }
+    Ok(message_len)
+}
+
+/// Folds the SHA-256 compression of `input`, carrying its length through the folding state. See
+/// `verify_message_len` to verify the resulting IVC proof.
+pub fn run_with_message_len<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256LengthFCircuit<Fr>>,
+        NovaParamsFor<FoldedSha256LengthFCircuit<Fr>>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+
+    let mut initial_state: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    initial_state.push(Fr::from(0u64));
+
+    fold(
+        FoldedSha256LengthFCircuit::<Fr>::new(())?,
+        initial_state,
+        BlockExternalInputs::<Fr, _>::new(input),
+        config,
+        rng,
+    )
+}
+
+/// Verifies the IVC proof produced by `run_with_message_len` and returns the length in bytes of
+/// the hashed message.
+pub fn verify_message_len(
+    vp: <NovaFor<FoldedSha256LengthFCircuit<Fr>> as FoldingScheme<
+        Projective,
+        Projective2,
+        FoldedSha256LengthFCircuit<Fr>,
+    >>::VerifierParam,
+    ivc_proof: IVCProof<Projective, Projective2>,
+) -> Result<u64, FoldedSha256Error> {
+    let num_steps = bigint_to_u32(ivc_proof.i) as usize;
+    let z_i = ivc_proof.z_i.clone();
+    NovaFor::<FoldedSha256LengthFCircuit<Fr>>::verify(vp, ivc_proof)?;
+    message_len_from_state(&z_i, num_steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn native_final_state(input: Vec<u8>) -> (Vec<Fr>, usize) {
+        let circuit = FoldedSha256LengthFCircuit::<Fr>::new(()).unwrap();
+        let mut z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        z_i.push(Fr::from(0u64));
+        let mut num_steps = 0;
+        for (i, block) in BlockExternalInputs::<Fr, _>::new(input).enumerate() {
+            z_i = circuit.step_native(i, z_i, block).unwrap();
+            num_steps += 1;
+        }
+        (z_i, num_steps)
+    }
+
+    #[test]
+    fn test_message_len_from_state() {
+        let (z_i, num_steps) = native_final_state(vec![7u8; 100]);
+        assert_eq!(num_steps, 2);
+        assert_eq!(message_len_from_state(&z_i, num_steps).unwrap(), 100);
+
+        // the length must be consistent with the number of folded blocks
+        assert!(matches!(
+            message_len_from_state(&z_i, 3),
+            Err(FoldedSha256Error::InvalidMessageLength {
+                length_in_bits: 800,
+                num_steps: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_length_f_circuit() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = FoldedSha256LengthFCircuit::<Fr>::new(()).unwrap();
+
+        let mut z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        z_i.push(Fr::from(0u64));
+        let external_inputs = BlockExternalInputs::<Fr, _>::new(b"abc".to_vec())
+            .next()
+            .unwrap();
+
+        let z_i1 = circuit
+            .step_native(0, z_i.clone(), external_inputs.clone())
+            .unwrap();
+        assert_eq!(z_i1[STATE_LEN], Fr::from(24u64));
+
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let externalInputsVar =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        let computed_z_i1Var = circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+            .unwrap();
+
+        assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    // slow: runs the Nova setup and folding, use `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_verify_reports_message_len() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, vp)) =
+            run_with_message_len(vec![7u8; 100], &FoldConfig::default(), &mut rng).unwrap();
+
+        let message_len = verify_message_len(vp, folding_scheme.ivc_proof()).unwrap();
+        assert_eq!(message_len, 100);
+    }
+}
@@ -1,3 +1,55 @@
+pub mod absorb;
+#[cfg(feature = "prover")]
+pub mod backend;
+#[cfg(feature = "prover")]
+pub mod checksum;
 pub mod circuit;
+#[cfg(feature = "prover")]
+pub mod collision;
+pub mod compose;
+pub mod config;
+pub mod constants;
+#[cfg(feature = "prover")]
+pub mod decider;
+pub mod digest;
+pub mod error;
+#[cfg(feature = "prover")]
+pub mod export;
+pub mod hasher;
+#[cfg(feature = "prover")]
+pub mod hkdf;
+pub mod layout;
+#[cfg(feature = "prover")]
 pub mod main;
+#[cfg(feature = "prover")]
+pub mod membership;
+#[cfg(feature = "prover")]
+pub mod merkle;
+#[cfg(feature = "prover")]
+pub mod message_len;
+#[cfg(feature = "prover")]
+pub mod multi_block;
+#[cfg(feature = "prover")]
+pub mod multi_claim;
+pub mod output;
+#[cfg(feature = "prover")]
+pub mod pedersen_commit;
+#[cfg(feature = "prover")]
+pub mod poseidon_commit;
+#[cfg(feature = "prover")]
+pub mod prf;
+pub mod profile;
+#[cfg(feature = "prover")]
+pub mod proof;
+#[cfg(feature = "prover")]
+pub mod public_prefix;
+#[cfg(feature = "prover")]
+pub mod replay;
+pub mod schedule;
+#[cfg(feature = "prover")]
+pub mod split;
+#[cfg(feature = "prover")]
+pub mod srs;
+#[cfg(feature = "prover")]
+pub mod storage_proof;
 pub mod utils;
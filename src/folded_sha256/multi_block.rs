@@ -0,0 +1,322 @@
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit;
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::layout::{ByteLayout, ExternalInputsLayout};
+use crate::folded_sha256::main::{
+    bigint_to_u32, fold, FoldedSha256FCircuit, NovaFor, NovaParamsFor, H, STATE_LEN,
+};
+use crate::folded_sha256::utils::{BlockExternalInputs, CompressionOptions, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::Fr;
+use ark_ff::{PrimeField, Zero};
+use ark_r1cs_std::alloc::AllocVar;
+use ark_r1cs_std::boolean::Boolean;
+use ark_r1cs_std::eq::EqGadget;
+use ark_r1cs_std::fields::{fp::FpVar, FieldVar};
+use ark_r1cs_std::uint32::UInt32;
+use ark_r1cs_std::R1CSVar;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::marker::PhantomData;
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::Error;
+
+/// SHA-256 folding circuit compressing up to `blocks_per_step` consecutive blocks at each step.
+///
+/// The state is the same as `FoldedSha256FCircuit`'s. The external inputs are the concatenation
+/// of `blocks_per_step` blocks, one byte per element, followed by the number `count` of them that
+/// are part of the message, between 1 and `blocks_per_step`. Only the first `count` blocks are
+/// compressed, the other ones are no-ops which leave the state unchanged (the final step of a
+/// message whose number of blocks is not a multiple of `blocks_per_step` is padded with them).
+///
+/// Folding `k` blocks per step divides the number of steps by `k`, at the cost of a `k` times
+/// larger step circuit.
+#[derive(Clone, Copy, Debug)]
+pub struct FoldedSha256MultiBlockFCircuit<F: PrimeField> {
+    blocks_per_step: usize,
+    _f: PhantomData<F>,
+}
+
+impl<F: PrimeField> FCircuit<F> for FoldedSha256MultiBlockFCircuit<F> {
+    /// Number of blocks per step.
+    type Params = usize;
+
+    fn new(blocks_per_step: Self::Params) -> Result<Self, Error> {
+        if blocks_per_step == 0 {
+            return Err(Error::Other(
+                "a folding step must compress at least one block".to_string(),
+            ));
+        }
+        Ok(Self {
+            blocks_per_step,
+            _f: PhantomData,
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN
+    }
+    fn external_inputs_len(&self) -> usize {
+        self.blocks_per_step * BLOCK_LENGTH_BYTES + 1
+    }
+
+    fn step_native(&self, i: usize, z_i: Vec<F>, external_inputs: Vec<F>) -> Result<Vec<F>, Error> {
+        let (blocks, count) = external_inputs.split_at(self.blocks_per_step * BLOCK_LENGTH_BYTES);
+        if count[0].is_zero() || count[0] > F::from(self.blocks_per_step as u64) {
+            return Err(Error::Other(format!(
+                "a folding step compresses between 1 and {} blocks",
+                self.blocks_per_step
+            )));
+        }
+        let count = bigint_to_u32(count[0]) as usize;
+
+        // the no-op blocks are still decoded, as they are in-circuit
+        let single_block = FoldedSha256FCircuit::<F>::sha256();
+        let mut z = z_i;
+        for (j, block) in blocks.chunks(BLOCK_LENGTH_BYTES).enumerate() {
+            let z_j = single_block.step_native(i, z.clone(), block.to_vec())?;
+            if j < count {
+                z = z_j;
+            }
+        }
+        Ok(z)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        _i: usize,
+        z_i: Vec<FpVar<F>>,
+        external_inputs: Vec<FpVar<F>>,
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let (blocks, count) = external_inputs.split_at(self.blocks_per_step * BLOCK_LENGTH_BYTES);
+        let count = &count[0];
+
+        // `active[j]` tells whether the j-th block is compressed. The flags are a run of
+        // `count` ones starting at the first block, which bounds `count` to [1, blocks_per_step]
+        let active = (0..self.blocks_per_step)
+            .map(|j| {
+                Boolean::new_witness(
+                    cs.clone(),
+                    || Ok(bigint_to_u32(count.value()?) as usize > j),
+                )
+            })
+            .collect::<Result<Vec<Boolean<F>>, SynthesisError>>()?;
+        active[0].enforce_equal(&Boolean::TRUE)?;
+        for j in 1..self.blocks_per_step {
+            Boolean::kary_and(&[active[j - 1].clone(), active[j].clone()])?
+                .enforce_equal(&active[j])?;
+        }
+        active
+            .iter()
+            .fold(FpVar::zero(), |sum, flag| sum + FpVar::from(flag.clone()))
+            .enforce_equal(count)?;
+
+        // the state stays as words between the blocks of the step
+        let mut state = z_i
+            .iter()
+            .map(|x| Ok(UInt32::from_fp(x)?.0))
+            .collect::<Result<Vec<UInt32<F>>, SynthesisError>>()?;
+        for (block, is_active) in blocks.chunks(BLOCK_LENGTH_BYTES).zip(&active) {
+            let block_words = ByteLayout::decode_words_in_circuit(block)?;
+            let compressed = circuit::compression_round_from_words(
+                &state,
+                &block_words,
+                &CompressionOptions::default(),
+            )?;
+            state = compressed
+                .iter()
+                .zip(&state)
+                .map(|(compressed, previous)| is_active.select(compressed, previous))
+                .collect::<Result<Vec<UInt32<F>>, SynthesisError>>()?;
+        }
+        state.iter().map(|x| x.to_fp()).collect()
+    }
+}
+
+/// Groups the padded blocks of `input` by `blocks_per_step`, as the external inputs of
+/// `FoldedSha256MultiBlockFCircuit`. If the number of blocks is not a multiple of
+/// `blocks_per_step`, the final step is completed with no-op (zero) blocks.
+///
+/// # Panics
+///
+/// Panics if `blocks_per_step` is 0.
+pub fn multi_block_external_inputs(
+    input: Vec<u8>,
+    blocks_per_step: usize,
+) -> impl Iterator<Item = Vec<Fr>> {
+    assert!(blocks_per_step > 0, "blocks_per_step must be at least 1");
+
+    let mut blocks = BlockExternalInputs::<Fr, _>::new(input);
+    std::iter::from_fn(move || {
+        let mut step: Vec<Fr> = Vec::with_capacity(blocks_per_step * BLOCK_LENGTH_BYTES + 1);
+        let mut count = 0u64;
+        for block in blocks.by_ref().take(blocks_per_step) {
+            step.extend(block);
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        step.resize(blocks_per_step * BLOCK_LENGTH_BYTES, Fr::from(0u64));
+        step.push(Fr::from(count));
+        Some(step)
+    })
+}
+
+/// Folds `input` with `config.blocks_per_step` blocks per step, see
+/// `FoldedSha256MultiBlockFCircuit` and `FoldConfig::auto_chunk`.
+pub fn run_multi_block<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256MultiBlockFCircuit<Fr>>,
+        NovaParamsFor<FoldedSha256MultiBlockFCircuit<Fr>>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+
+    let F_circuit = FoldedSha256MultiBlockFCircuit::<Fr>::new(config.blocks_per_step)?;
+    let initial_state: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+
+    fold(
+        F_circuit,
+        initial_state,
+        multi_block_external_inputs(input, config.blocks_per_step),
+        config,
+        rng,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::config::MAX_AUTO_BLOCKS_PER_STEP;
+    use crate::folded_sha256::digest::{digest_from_state, Digest};
+    use crate::folded_sha256::hasher::sha256;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn native_digest(input: Vec<u8>, blocks_per_step: usize) -> Digest {
+        let circuit = FoldedSha256MultiBlockFCircuit::<Fr>::new(blocks_per_step).unwrap();
+        let mut z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        for (i, external_inputs) in multi_block_external_inputs(input, blocks_per_step).enumerate()
+        {
+            z_i = circuit.step_native(i, z_i, external_inputs).unwrap();
+        }
+        digest_from_state(&z_i)
+    }
+
+    #[test]
+    fn test_auto_chunk_digests() {
+        for config in [
+            FoldConfig::auto_chunk(0),
+            FoldConfig::auto_chunk(usize::MAX),
+        ] {
+            let blocks_per_step = config.blocks_per_step;
+            // 3 * 64 * k - 9 bytes take exactly 3k blocks once padded
+            let input: Vec<u8> = (0..3 * (64 * blocks_per_step) - 9)
+                .map(|i| i as u8)
+                .collect();
+            assert_eq!(config.num_steps(input.len()), 3);
+            assert_eq!(
+                native_digest(input.clone(), blocks_per_step),
+                sha256(&input)
+            );
+        }
+        assert_eq!(
+            FoldConfig::auto_chunk(usize::MAX).blocks_per_step,
+            MAX_AUTO_BLOCKS_PER_STEP
+        );
+    }
+
+    #[test]
+    fn test_partial_chunk_digests() {
+        // 200 bytes take 4 blocks, i.e. steps of 3 and 1 blocks
+        let input: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let external_inputs: Vec<Vec<Fr>> = multi_block_external_inputs(input.clone(), 3).collect();
+        assert_eq!(external_inputs.len(), 2);
+        assert_eq!(external_inputs[0][3 * BLOCK_LENGTH_BYTES], Fr::from(3u64));
+        assert_eq!(external_inputs[1][3 * BLOCK_LENGTH_BYTES], Fr::from(1u64));
+
+        assert_eq!(native_digest(input.clone(), 3), sha256(&input));
+    }
+
+    #[test]
+    fn test_partial_chunk_f_circuit() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = FoldedSha256MultiBlockFCircuit::<Fr>::new(3).unwrap();
+        let z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        // 100 bytes take 2 blocks, the third one is a no-op
+        let external_inputs = multi_block_external_inputs(vec![1u8; 100], 3)
+            .next()
+            .unwrap();
+
+        let z_i1 = circuit
+            .step_native(0, z_i.clone(), external_inputs.clone())
+            .unwrap();
+        assert_eq!(digest_from_state(&z_i1), sha256(&[1u8; 100]));
+
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let externalInputsVar =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        let computed_z_i1Var = circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+            .unwrap();
+
+        assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_invalid_block_count_is_rejected() {
+        let circuit = FoldedSha256MultiBlockFCircuit::<Fr>::new(2).unwrap();
+        let z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        for count in [0u64, 3] {
+            let mut external_inputs = multi_block_external_inputs(vec![1u8; 100], 2)
+                .next()
+                .unwrap();
+            external_inputs[2 * BLOCK_LENGTH_BYTES] = Fr::from(count);
+            assert!(circuit
+                .step_native(0, z_i.clone(), external_inputs.clone())
+                .is_err());
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i.clone())).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+            circuit
+                .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+                .unwrap();
+            assert!(!cs.is_satisfied().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_multi_block_f_circuit() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = FoldedSha256MultiBlockFCircuit::<Fr>::new(2).unwrap();
+        let z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        let external_inputs = multi_block_external_inputs(vec![1u8; 100], 2)
+            .next()
+            .unwrap();
+
+        let z_i1 = circuit
+            .step_native(0, z_i.clone(), external_inputs.clone())
+            .unwrap();
+        assert_eq!(digest_from_state(&z_i1), sha256(&[1u8; 100]));
+
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let externalInputsVar =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        let computed_z_i1Var = circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+            .unwrap();
+
+        assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,238 @@
+//! Several claims about one input proven by a single fold: its SHA-256 digest, its length, and a
+//! Poseidon commitment to it.
+//!
+//! The public inputs of the proof are `z_0` and the final state
+//! `z_n = [H_0, ..., H_7, len, acc]`:
+//! - `z_n[0..8]`: the digest, as 8 big-endian words, see `digest::digest_from_state`;
+//! - `z_n[8]`: the length of the input in bits, see `message_len::FoldedSha256LengthFCircuit`;
+//! - `z_n[9]`: the Poseidon commitment to the padded input, see
+//!   `poseidon_commit::poseidon_commitment`.
+//!
+//! `z_0 = multi_claim_initial_state()` is `[H_0, ..., H_7, 0, 0]`.
+#![allow(non_snake_case)]
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::digest::{digest_from_state, Digest};
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{bigint_to_u32, fold, NovaFor, NovaParamsFor, H, STATE_LEN};
+use crate::folded_sha256::message_len::{message_len_from_state, FoldedSha256LengthFCircuit};
+use crate::folded_sha256::poseidon_commit::{absorb_block_native, absorb_block_var};
+use crate::folded_sha256::utils::{BlockExternalInputs, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_grumpkin::Projective as Projective2;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::folding::nova::IVCProof;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+use folding_schemes::{Error, FoldingScheme};
+
+/// Index of the length in the state.
+const LEN_INDEX: usize = STATE_LEN;
+/// Index of the Poseidon commitment in the state.
+const ACC_INDEX: usize = STATE_LEN + 1;
+
+/// SHA-256 folding circuit carrying the length and the Poseidon commitment of the input
+/// alongside the compression, with the state layout of the module documentation.
+///
+/// The compression and the length are those of `FoldedSha256LengthFCircuit`, the commitment is
+/// accumulated as in `poseidon_commit::FoldedSha256PoseidonFCircuit`, from the same external
+/// inputs.
+#[derive(Clone, Debug)]
+pub struct FoldedSha256MultiClaimFCircuit {
+    length: FoldedSha256LengthFCircuit<Fr>,
+    poseidon_config: PoseidonConfig<Fr>,
+}
+
+impl FCircuit<Fr> for FoldedSha256MultiClaimFCircuit {
+    type Params = PoseidonConfig<Fr>;
+
+    fn new(poseidon_config: Self::Params) -> Result<Self, Error> {
+        Ok(Self {
+            length: FoldedSha256LengthFCircuit::<Fr>::new(())?,
+            poseidon_config,
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + 2
+    }
+    fn external_inputs_len(&self) -> usize {
+        BLOCK_LENGTH_BYTES
+    }
+
+    fn step_native(
+        &self,
+        i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let mut z_i1 =
+            self.length
+                .step_native(i, z_i[..ACC_INDEX].to_vec(), external_inputs.clone())?;
+        z_i1.push(absorb_block_native(
+            &self.poseidon_config,
+            z_i[ACC_INDEX],
+            &external_inputs,
+        ));
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        let mut z_i1 = self.length.generate_step_constraints(
+            cs.clone(),
+            i,
+            z_i[..ACC_INDEX].to_vec(),
+            external_inputs.clone(),
+        )?;
+        z_i1.push(absorb_block_var(
+            cs,
+            &self.poseidon_config,
+            &z_i[ACC_INDEX],
+            &external_inputs,
+        )?);
+        Ok(z_i1)
+    }
+}
+
+/// The claims proven by `prove_multi_claim`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiClaim {
+    pub digest: Digest,
+    /// Length of the input in bytes.
+    pub len: u64,
+    /// Poseidon commitment to the padded input, with the `poseidon_canonical_config`.
+    pub commitment: Fr,
+}
+
+/// The initial state of the multi-claim folding.
+pub fn multi_claim_initial_state() -> Vec<Fr> {
+    let mut z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    z_0.push(Fr::from(0u64));
+    z_0.push(Fr::from(0u64));
+    z_0
+}
+
+/// Folds the SHA-256 compression of `input` together with its length and its Poseidon
+/// commitment, with the `poseidon_canonical_config`. See `verify_multi_claim`.
+pub fn prove_multi_claim<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256MultiClaimFCircuit>,
+        NovaParamsFor<FoldedSha256MultiClaimFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+
+    fold(
+        FoldedSha256MultiClaimFCircuit::new(poseidon_canonical_config::<Fr>())?,
+        multi_claim_initial_state(),
+        BlockExternalInputs::<Fr, _>::new(input),
+        config,
+        rng,
+    )
+}
+
+/// Verifies the IVC proof produced by `prove_multi_claim` and returns its claims, to be compared
+/// by the caller to the expected ones.
+///
+/// Returns `FoldedSha256Error::InvalidInitialState` if the proof does not start from
+/// `multi_claim_initial_state`, and `FoldedSha256Error::InvalidMessageLength` if the length is
+/// not consistent with the number of folded blocks.
+pub fn verify_multi_claim(
+    vp: <NovaFor<FoldedSha256MultiClaimFCircuit> as FoldingScheme<
+        Projective,
+        Projective2,
+        FoldedSha256MultiClaimFCircuit,
+    >>::VerifierParam,
+    ivc_proof: IVCProof<Projective, Projective2>,
+) -> Result<MultiClaim, FoldedSha256Error> {
+    if ivc_proof.z_0 != multi_claim_initial_state() {
+        return Err(FoldedSha256Error::InvalidInitialState);
+    }
+    let num_steps = bigint_to_u32(ivc_proof.i) as usize;
+    let z_i = ivc_proof.z_i.clone();
+    NovaFor::<FoldedSha256MultiClaimFCircuit>::verify(vp, ivc_proof)?;
+
+    Ok(MultiClaim {
+        digest: digest_from_state(&z_i[..STATE_LEN]),
+        len: message_len_from_state(&z_i[..=LEN_INDEX], num_steps)?,
+        commitment: z_i[ACC_INDEX],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::hasher::sha256;
+    use crate::folded_sha256::poseidon_commit::poseidon_commitment;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_multi_claim_f_circuit() {
+        let input: Vec<u8> = (0..130).map(|i| (i * 5) as u8).collect();
+        let circuit = FoldedSha256MultiClaimFCircuit::new(poseidon_canonical_config()).unwrap();
+
+        let mut z_i = multi_claim_initial_state();
+        for (i, block) in BlockExternalInputs::<Fr, _>::new(input.clone()).enumerate() {
+            let z_i1 = circuit.step_native(i, z_i.clone(), block.clone()).unwrap();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+            assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+            assert!(cs.is_satisfied().unwrap());
+
+            z_i = z_i1;
+        }
+        assert_eq!(digest_from_state(&z_i[..STATE_LEN]), sha256(&input));
+        assert_eq!(z_i[LEN_INDEX], Fr::from(8 * input.len() as u64));
+        assert_eq!(
+            z_i[ACC_INDEX],
+            poseidon_commitment(&poseidon_canonical_config(), input)
+        );
+    }
+
+    // slow: runs the Nova setup and folding, use `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_prove_multi_claim() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let input: Vec<u8> = (0..130).map(|i| (i * 5) as u8).collect();
+        let (folding_scheme, (_, vp)) =
+            prove_multi_claim(input.clone(), &FoldConfig::default(), &mut rng).unwrap();
+
+        let claim = verify_multi_claim(vp.clone(), folding_scheme.ivc_proof()).unwrap();
+        assert_eq!(
+            claim,
+            MultiClaim {
+                digest: sha256(&input),
+                len: input.len() as u64,
+                commitment: poseidon_commitment(&poseidon_canonical_config(), input),
+            }
+        );
+
+        let mut forged_proof = folding_scheme.ivc_proof();
+        forged_proof.z_i[ACC_INDEX] += Fr::from(1u64);
+        assert!(verify_multi_claim(vp, forged_proof).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! Public outputs derived from the final folding state.
+//!
+//! The folding state is always the full SHA-256 state, the output mode decides what a protocol
+//! exposes from it once the last block is folded: the digest, a prefix of it, or only whether it
+//! equals an expected digest.
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit::words_to_bytes_be;
+use crate::folded_sha256::digest::digest_from_state;
+
+use ark_ff::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, uint32::UInt32, uint8::UInt8};
+use ark_relations::r1cs::SynthesisError;
+use ark_std::fmt::Debug;
+
+/// Maps the final state `z_n` (the 8 SHA-256 state words) to the public outputs.
+///
+/// `outputs` and `generate_output_constraints` must agree, and both return `num_outputs`
+/// elements.
+pub trait OutputMode: Clone + Copy + Debug + Default {
+    fn num_outputs(&self) -> usize;
+
+    fn outputs<F: PrimeField>(&self, z_n: &[F]) -> Result<Vec<F>, &'static str>;
+
+    fn generate_output_constraints<F: PrimeField>(
+        &self,
+        z_n: &[FpVar<F>],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError>;
+}
+
+/// The 8 words of the digest, i.e. the final state itself.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RawDigest;
+
+impl OutputMode for RawDigest {
+    fn num_outputs(&self) -> usize {
+        8
+    }
+
+    fn outputs<F: PrimeField>(&self, z_n: &[F]) -> Result<Vec<F>, &'static str> {
+        Ok(z_n.to_vec())
+    }
+
+    fn generate_output_constraints<F: PrimeField>(
+        &self,
+        z_n: &[FpVar<F>],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(z_n.to_vec())
+    }
+}
+
+/// The first 4 words of the digest, i.e. its first 16 bytes (see `digest::digest128`), for
+/// compact commitments. The collision resistance is reduced to the 64-bit birthday bound.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Digest128;
+
+impl OutputMode for Digest128 {
+    fn num_outputs(&self) -> usize {
+        4
+    }
+
+    fn outputs<F: PrimeField>(&self, z_n: &[F]) -> Result<Vec<F>, &'static str> {
+        Ok(z_n[..4].to_vec())
+    }
+
+    fn generate_output_constraints<F: PrimeField>(
+        &self,
+        z_n: &[FpVar<F>],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        Ok(z_n[..4].to_vec())
+    }
+}
+
+/// The first `k` bytes of the digest, one byte per output, `k` being at most 32. Defaults to the
+/// whole digest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Truncated(pub usize);
+
+impl Default for Truncated {
+    fn default() -> Self {
+        Truncated(32)
+    }
+}
+
+impl OutputMode for Truncated {
+    fn num_outputs(&self) -> usize {
+        self.0
+    }
+
+    fn outputs<F: PrimeField>(&self, z_n: &[F]) -> Result<Vec<F>, &'static str> {
+        if self.0 > 32 {
+            return Err("a digest cannot be truncated to more than 32 bytes");
+        }
+        Ok(digest_from_state(z_n).0[..self.0]
+            .iter()
+            .map(|&x| F::from(x))
+            .collect())
+    }
+
+    fn generate_output_constraints<F: PrimeField>(
+        &self,
+        z_n: &[FpVar<F>],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        if self.0 > 32 {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        digest_bytes(z_n)?[..self.0]
+            .iter()
+            .map(|byte| byte.to_fp())
+            .collect()
+    }
+}
+
+/// A single output, 1 if the digest equals the expected one (a constant of the circuit) and 0
+/// otherwise.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EqualsPublic(pub [u8; 32]);
+
+impl OutputMode for EqualsPublic {
+    fn num_outputs(&self) -> usize {
+        1
+    }
+
+    fn outputs<F: PrimeField>(&self, z_n: &[F]) -> Result<Vec<F>, &'static str> {
+        Ok(vec![F::from(digest_from_state(z_n) == self.0)])
+    }
+
+    fn generate_output_constraints<F: PrimeField>(
+        &self,
+        z_n: &[FpVar<F>],
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let expected = UInt8::constant_vec(&self.0);
+        let is_equal = digest_bytes(z_n)?.is_eq(&expected)?;
+        Ok(vec![FpVar::from(is_equal)])
+    }
+}
+
+/// The digest bytes of the state words. The words are those output by the compression, hence
+/// already 32-bit, so `from_fp` does not need to check the remaining bits.
+fn digest_bytes<F: PrimeField>(z_n: &[FpVar<F>]) -> Result<Vec<UInt8<F>>, SynthesisError> {
+    let words = z_n
+        .iter()
+        .map(|x| Ok(UInt32::from_fp(x)?.0))
+        .collect::<Result<Vec<UInt32<F>>, SynthesisError>>()?;
+    words_to_bytes_be(&words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::constants::sha256::H;
+    use crate::folded_sha256::digest::digest128;
+    use crate::folded_sha256::hasher::sha256;
+    use crate::folded_sha256::utils::{compress_block, sha256_msg_block_sequence};
+    use ark_bn254::Fr;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn final_state() -> Vec<Fr> {
+        let block = sha256_msg_block_sequence(b"abc".to_vec())[0];
+        compress_block(&H, &block)
+            .iter()
+            .map(|&x| Fr::from(x))
+            .collect()
+    }
+
+    /// Exposes the outputs of `mode` as public inputs, returning their number.
+    fn check_outputs<M: OutputMode>(mode: M, expected: Vec<Fr>) -> usize {
+        let z_n = final_state();
+        let outputs = mode.outputs(&z_n).unwrap();
+        assert_eq!(outputs, expected);
+        assert_eq!(outputs.len(), mode.num_outputs());
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_nVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_n)).unwrap();
+        let outputsVar = Vec::<FpVar<Fr>>::new_input(cs.clone(), || Ok(outputs.clone())).unwrap();
+        let computed_outputsVar = mode.generate_output_constraints(&z_nVar).unwrap();
+        assert_eq!(computed_outputsVar.value().unwrap(), outputs);
+        computed_outputsVar.enforce_equal(&outputsVar).unwrap();
+        assert!(cs.is_satisfied().unwrap());
+
+        // the constant one is an instance variable too
+        cs.num_instance_variables() - 1
+    }
+
+    #[test]
+    fn test_output_modes() {
+        let digest = sha256(b"abc").0;
+
+        assert_eq!(check_outputs(RawDigest, final_state()), 8);
+        assert_eq!(
+            check_outputs(
+                Truncated(20),
+                digest[..20].iter().map(|&x| Fr::from(x)).collect()
+            ),
+            20
+        );
+        assert_eq!(check_outputs(EqualsPublic(digest), vec![Fr::from(1u64)]), 1);
+        assert_eq!(
+            check_outputs(EqualsPublic([0u8; 32]), vec![Fr::from(0u64)]),
+            1
+        );
+
+        assert_eq!(check_outputs(Digest128, final_state()[..4].to_vec()), 4);
+        // the 4 words are the first 16 bytes of the digest
+        assert_eq!(digest128(&final_state()), digest[..16]);
+
+        assert!(Truncated(33).outputs(&final_state()).is_err());
+    }
+}
@@ -0,0 +1,418 @@
+#![allow(non_snake_case)]
+use crate::folded_sha256::circuit::bytes_from_fp;
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{
+    bytes_from_field, fold, FoldedSha256FCircuit, NovaFor, NovaParamsFor, H, STATE_LEN,
+};
+use crate::folded_sha256::utils::{BlockExternalInputs, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::commitment::{
+    constraints::CommitmentGadget,
+    pedersen::{
+        constraints::{CommGadget, ParametersVar, RandomnessVar},
+        Commitment, Parameters, Randomness, Window,
+    },
+    CommitmentScheme,
+};
+use ark_ec::{CurveGroup, PrimeGroup};
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
+use ark_r1cs_std::{
+    alloc::AllocVar, convert::ToBitsGadget, convert::ToBytesGadget, fields::fp::FpVar,
+    groups::CurveVar, uint8::UInt8,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::fmt;
+use ark_std::rand::{CryptoRng, RngCore};
+use ark_std::UniformRand;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::Error;
+
+type GrumpkinScalar = <Projective2 as PrimeGroup>::ScalarField;
+
+/// Windows of the Pedersen commitment, sized for the 96 bytes committed at each step (the previous
+/// commitment and the block).
+#[derive(Clone)]
+pub struct PedersenWindow;
+
+impl Window for PedersenWindow {
+    const WINDOW_SIZE: usize = 4;
+    const NUM_WINDOWS: usize = 192;
+}
+
+pub type PedersenParameters = Parameters<Projective2>;
+
+type PedersenCommitment = Commitment<Projective2, PedersenWindow>;
+type PedersenCommGadget = CommGadget<Projective2, GVar2, PedersenWindow>;
+
+/// Samples the Pedersen generators, which must be fixed before folding and shared with whoever
+/// opens the commitment.
+pub fn pedersen_setup<R: RngCore>(rng: &mut R) -> Result<PedersenParameters, FoldedSha256Error> {
+    PedersenCommitment::setup(rng)
+        .map_err(|e| FoldedSha256Error::FoldingScheme(Error::Other(e.to_string())))
+}
+
+/// Number of external inputs of `FoldedSha256PedersenFCircuit`: the block, then the randomness.
+const EXTERNAL_INPUTS_LEN: usize = BLOCK_LENGTH_BYTES + 1;
+
+/// SHA-256 folding circuit which, alongside the compression, computes a Pedersen commitment to
+/// the hashed blocks over Grumpkin, whose base field is the BN254 scalar field.
+///
+/// The state is `z_i = [H_0, ..., H_7, c_i]`, with `c_0 = 0` and `c_{i+1}` the x-coordinate of
+/// `Commit(c_i || w_i; r_i)`, where `c_i` is serialized as 32 little-endian bytes. The external
+/// inputs are the 64 bytes of the block `w_i` followed by the randomness `r_i`, so the randomness
+/// stays private to the prover and the final `c_n`, exposed as public output, only opens with the
+/// (padded) preimage and `r_0, ..., r_{n-1}`: see `pedersen_commitment`.
+///
+/// The commitment chains whole blocks, so it can only be opened to the full preimage.
+#[derive(Clone)]
+pub struct FoldedSha256PedersenFCircuit {
+    pedersen_params: PedersenParameters,
+}
+
+impl fmt::Debug for FoldedSha256PedersenFCircuit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FoldedSha256PedersenFCircuit")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Converts the randomness external input, an element of the BN254 scalar field, to a Grumpkin
+/// scalar. It is smaller than the Grumpkin scalar field modulus, so the integer is preserved.
+fn randomness_scalar(r: Fr) -> GrumpkinScalar {
+    GrumpkinScalar::from_le_bytes_mod_order(&r.into_bigint().to_bytes_le())
+}
+
+/// Computes natively one step of the commitment chain, `x(Commit(c || block; r))`.
+fn commit_block_native(
+    pedersen_params: &PedersenParameters,
+    c: Fr,
+    block: &[u8],
+    r: Fr,
+) -> Result<Fr, Error> {
+    let mut input = c.into_bigint().to_bytes_le();
+    input.extend_from_slice(block);
+
+    // the randomness is added as `r * G_r` on top of the commitment without randomness, as the
+    // circuit does
+    let message_commitment =
+        PedersenCommitment::commit(pedersen_params, &input, &Randomness(GrumpkinScalar::zero()))
+            .map_err(|e| Error::Other(e.to_string()))?;
+    let commitment = (Projective2::from(message_commitment)
+        + pedersen_params.randomness_generator[0] * randomness_scalar(r))
+    .into_affine();
+
+    Ok(commitment.x)
+}
+
+impl FCircuit<Fr> for FoldedSha256PedersenFCircuit {
+    type Params = PedersenParameters;
+
+    fn new(params: Self::Params) -> Result<Self, Error> {
+        Ok(Self {
+            pedersen_params: params,
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + 1
+    }
+    fn external_inputs_len(&self) -> usize {
+        EXTERNAL_INPUTS_LEN
+    }
+
+    fn step_native(
+        &self,
+        i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let block = &external_inputs[..BLOCK_LENGTH_BYTES];
+        let mut z_i1 = FoldedSha256FCircuit::<Fr>::sha256().step_native(
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            block.to_vec(),
+        )?;
+
+        z_i1.push(commit_block_native(
+            &self.pedersen_params,
+            z_i[STATE_LEN],
+            &bytes_from_field(block)?,
+            external_inputs[BLOCK_LENGTH_BYTES],
+        )?);
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        let block = &external_inputs[..BLOCK_LENGTH_BYTES];
+        let mut z_i1 = FoldedSha256FCircuit::<Fr>::sha256().generate_step_constraints(
+            cs.clone(),
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            block.to_vec(),
+        )?;
+
+        let mut input: Vec<UInt8<Fr>> = z_i[STATE_LEN].to_bytes_le()?;
+        input.extend(bytes_from_fp(block)?);
+
+        let params_var =
+            ParametersVar::<Projective2, GVar2>::new_constant(cs.clone(), &self.pedersen_params)?;
+        let zero_randomness = RandomnessVar::<Fr>::new_constant(
+            cs.clone(),
+            Randomness::<Projective2>(GrumpkinScalar::zero()),
+        )?;
+        let message_commitment = PedersenCommGadget::commit(&params_var, &input, &zero_randomness)?;
+
+        let randomness_generator = GVar2::constant(self.pedersen_params.randomness_generator[0]);
+        let randomness_bits = external_inputs[BLOCK_LENGTH_BYTES].to_bits_le()?;
+        let commitment =
+            message_commitment + randomness_generator.scalar_mul_le(randomness_bits.iter())?;
+
+        z_i1.push(commitment.to_affine()?.x);
+        Ok(z_i1)
+    }
+}
+
+/// Computes natively the commitment `c_n` exposed by `FoldedSha256PedersenFCircuit` after folding
+/// all the padded blocks of `input`, with the randomness `randomness[i]` at step `i`.
+///
+/// This is how the commitment is opened: the opening is `(input, randomness)`.
+pub fn pedersen_commitment(
+    pedersen_params: &PedersenParameters,
+    input: Vec<u8>,
+    randomness: &[Fr],
+) -> Result<Fr, FoldedSha256Error> {
+    let num_blocks = FoldConfig::num_blocks(input.len());
+    if randomness.len() != num_blocks {
+        return Err(FoldedSha256Error::FoldingScheme(Error::Other(format!(
+            "{} randomness elements given for {} blocks",
+            randomness.len(),
+            num_blocks
+        ))));
+    }
+
+    let mut c = Fr::zero();
+    for (block, r) in BlockExternalInputs::<Fr, _>::new(input).zip(randomness) {
+        c = commit_block_native(pedersen_params, c, &bytes_from_field(&block)?, *r)?;
+    }
+    Ok(c)
+}
+
+/// Folds the SHA-256 compression of `input` together with a Pedersen commitment to it, drawing
+/// the randomness of each step from `rng`.
+///
+/// Returns the randomness alongside the folding scheme: together with `input` it is the opening
+/// of the commitment, the last element of the final state.
+pub fn run_with_pedersen_commitment<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    pedersen_params: PedersenParameters,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256PedersenFCircuit>,
+        NovaParamsFor<FoldedSha256PedersenFCircuit>,
+        Vec<Fr>,
+    ),
+    FoldedSha256Error,
+> {
+    let num_blocks = FoldConfig::num_blocks(input.len());
+    let randomness: Vec<Fr> = (0..num_blocks).map(|_| Fr::rand(&mut *rng)).collect();
+
+    let (folding_scheme, nova_params) =
+        run_with_pedersen_blinding(input, pedersen_params, randomness.clone(), config, rng)?;
+    Ok((folding_scheme, nova_params, randomness))
+}
+
+/// Same as `run_with_pedersen_commitment`, with the caller's blinding factor `blinding[i]` as
+/// the randomness of step `i`, one per padded block, e.g. derived from a protocol's own secret.
+///
+/// The blinding is what makes the commitment of each step, and the `c_n` exposed as public
+/// output, hiding: as long as it is uniformly random and kept secret, `c_n` reveals nothing about
+/// `input`. A reused or predictable blinding loses this, e.g. the same input folded twice with the
+/// same blinding gives the same `c_n`. It does not affect the SHA-256 part of the state, whose
+/// final value is the digest, nor make the Nova commitments to the step witnesses hiding.
+pub fn run_with_pedersen_blinding<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    pedersen_params: PedersenParameters,
+    blinding: Vec<Fr>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256PedersenFCircuit>,
+        NovaParamsFor<FoldedSha256PedersenFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+    let num_blocks = FoldConfig::num_blocks(input.len());
+    if blinding.len() != num_blocks {
+        return Err(FoldedSha256Error::FoldingScheme(Error::Other(format!(
+            "{} blinding factors given for {} blocks",
+            blinding.len(),
+            num_blocks
+        ))));
+    }
+
+    let mut initial_state: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    initial_state.push(Fr::zero());
+
+    let F_circuit = FoldedSha256PedersenFCircuit::new(pedersen_params)?;
+    let external_inputs =
+        BlockExternalInputs::<Fr, _>::new(input)
+            .zip(blinding)
+            .map(|(mut block, r)| {
+                block.push(r);
+                block
+            });
+
+    fold(F_circuit, initial_state, external_inputs, config, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::utils::{sha256_msg_block_sequence, update_state_ref};
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_pedersen_commitment_opens_to_input() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let pedersen_params = pedersen_setup(&mut rng).unwrap();
+        let circuit = FoldedSha256PedersenFCircuit::new(pedersen_params.clone()).unwrap();
+
+        let input: Vec<u8> = (0..100).map(|i| i as u8).collect();
+        let randomness: Vec<Fr> = (0..2).map(|_| Fr::rand(&mut rng)).collect();
+
+        let mut z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        z_i.push(Fr::zero());
+        for (i, (mut block, r)) in BlockExternalInputs::<Fr, _>::new(input.clone())
+            .zip(randomness.clone())
+            .enumerate()
+        {
+            block.push(r);
+            z_i = circuit.step_native(i, z_i, block).unwrap();
+        }
+
+        assert_eq!(
+            z_i[STATE_LEN],
+            pedersen_commitment(&pedersen_params, input.clone(), &randomness).unwrap()
+        );
+        // it does not open to another input, nor with other randomness
+        let mut other_input = input.clone();
+        other_input[42] ^= 1;
+        assert_ne!(
+            z_i[STATE_LEN],
+            pedersen_commitment(&pedersen_params, other_input, &randomness).unwrap()
+        );
+        assert_ne!(
+            z_i[STATE_LEN],
+            pedersen_commitment(
+                &pedersen_params,
+                input.clone(),
+                &[randomness[1], randomness[0]]
+            )
+            .unwrap()
+        );
+        assert!(pedersen_commitment(&pedersen_params, input.clone(), &randomness[..1]).is_err());
+
+        // the SHA-256 part of the state is unaffected by the commitment
+        let mut state = H.to_vec();
+        for block in sha256_msg_block_sequence(input) {
+            state = update_state_ref(state, block.to_vec()).unwrap();
+        }
+        let expected_state: Vec<Fr> = state.iter().map(|&x| Fr::from(x)).collect();
+        assert_eq!(z_i[..STATE_LEN].to_vec(), expected_state);
+    }
+
+    #[test]
+    fn test_pedersen_f_circuit() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit = FoldedSha256PedersenFCircuit::new(pedersen_setup(&mut rng).unwrap()).unwrap();
+
+        let mut z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        z_i.push(Fr::rand(&mut rng));
+        let mut external_inputs = BlockExternalInputs::<Fr, _>::new(b"abc".to_vec())
+            .next()
+            .unwrap();
+        external_inputs.push(Fr::rand(&mut rng));
+
+        let z_i1 = circuit
+            .step_native(0, z_i.clone(), external_inputs.clone())
+            .unwrap();
+
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let externalInputsVar =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        let computed_z_i1Var = circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+            .unwrap();
+
+        assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_blinding_changes_the_commitments() {
+        use folding_schemes::folding::traits::CommittedInstanceOps;
+        use folding_schemes::FoldingScheme;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let pedersen_params = pedersen_setup(&mut rng).unwrap();
+        let prove_with_blinding = |blinding: Fr| {
+            let mut rng = ChaCha20Rng::seed_from_u64(1);
+            let (folding_scheme, (_, nova_vp)) = run_with_pedersen_blinding(
+                b"abc".to_vec(),
+                pedersen_params.clone(),
+                vec![blinding],
+                &FoldConfig::default(),
+                &mut rng,
+            )
+            .unwrap();
+            assert!(NovaFor::<FoldedSha256PedersenFCircuit>::verify(
+                nova_vp,
+                folding_scheme.ivc_proof()
+            )
+            .is_ok());
+            folding_scheme
+        };
+
+        let folding_scheme_a = prove_with_blinding(Fr::from(1u64));
+        let folding_scheme_b = prove_with_blinding(Fr::from(2u64));
+        assert_eq!(
+            folding_scheme_a.z_i[..STATE_LEN],
+            folding_scheme_b.z_i[..STATE_LEN]
+        );
+        assert_ne!(
+            folding_scheme_a.z_i[STATE_LEN],
+            folding_scheme_b.z_i[STATE_LEN]
+        );
+        assert_ne!(
+            folding_scheme_a.U_i.get_commitments(),
+            folding_scheme_b.U_i.get_commitments()
+        );
+
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        assert!(run_with_pedersen_blinding(
+            b"abc".to_vec(),
+            pedersen_params,
+            vec![],
+            &FoldConfig::default(),
+            &mut rng
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,287 @@
+#![allow(non_snake_case)]
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{
+    fold, FoldedSha256FCircuit, NovaFor, NovaParamsFor, H, STATE_LEN,
+};
+use crate::folded_sha256::utils::BlockExternalInputs;
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig, PoseidonSponge},
+    Absorb, CryptographicSponge,
+};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+use folding_schemes::Error;
+
+/// SHA-256 folding circuit which, alongside the compression, accumulates a Poseidon commitment to
+/// the hashed blocks in an extra state element.
+///
+/// The state is `z_i = [H_0, ..., H_7, acc_i]`, with `acc_0 = 0` and
+/// `acc_{i+1} = Poseidon(acc_i, w_i[0], ..., w_i[63])`, so the final state holds both the SHA-256
+/// state and a single field element committing to the (padded) preimage, which can be linked to
+/// other arithmetic-friendly constraints.
+#[derive(Clone, Debug)]
+pub struct FoldedSha256PoseidonFCircuit<F: PrimeField> {
+    poseidon_config: PoseidonConfig<F>,
+}
+
+/// Absorbs one block of external inputs into the running Poseidon commitment `acc`.
+pub(crate) fn absorb_block_native<F: PrimeField + Absorb>(
+    poseidon_config: &PoseidonConfig<F>,
+    acc: F,
+    block: &[F],
+) -> F {
+    let mut sponge = PoseidonSponge::<F>::new(poseidon_config);
+    sponge.absorb(&acc);
+    sponge.absorb(&block.to_vec());
+    sponge.squeeze_field_elements(1)[0]
+}
+
+/// In-circuit `absorb_block_native`.
+pub(crate) fn absorb_block_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    poseidon_config: &PoseidonConfig<F>,
+    acc: &FpVar<F>,
+    block: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut sponge = PoseidonSpongeVar::<F>::new(cs, poseidon_config);
+    sponge.absorb(acc)?;
+    sponge.absorb(&block.to_vec())?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
+
+/// Computes natively the Poseidon commitment accumulated by `FoldedSha256PoseidonFCircuit` over
+/// all the padded blocks of `input`.
+pub fn poseidon_commitment<F: PrimeField + Absorb>(
+    poseidon_config: &PoseidonConfig<F>,
+    input: Vec<u8>,
+) -> F {
+    BlockExternalInputs::<F, _>::new(input).fold(F::zero(), |acc, block| {
+        absorb_block_native(poseidon_config, acc, &block)
+    })
+}
+
+/// Poseidon hash of the 8 big-endian words of `digest`, a single field element standing for the
+/// 256-bit digest, e.g. in public inputs.
+pub fn digest_poseidon_commitment<F: PrimeField + Absorb>(
+    poseidon_config: &PoseidonConfig<F>,
+    digest: &[u8; 32],
+) -> F {
+    let words: Vec<F> = digest
+        .chunks(4)
+        .map(|w| F::from(u32::from_be_bytes(w.try_into().unwrap())))
+        .collect();
+    let mut sponge = PoseidonSponge::<F>::new(poseidon_config);
+    sponge.absorb(&words);
+    sponge.squeeze_field_elements(1)[0]
+}
+
+/// In-circuit `digest_poseidon_commitment` of the 8 digest words `digest`, e.g. the first 8
+/// elements of a SHA-256 state.
+pub fn digest_poseidon_commitment_var<F: PrimeField + Absorb>(
+    cs: ConstraintSystemRef<F>,
+    poseidon_config: &PoseidonConfig<F>,
+    digest: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+    assert_eq!(digest.len(), STATE_LEN);
+    let mut sponge = PoseidonSpongeVar::<F>::new(cs, poseidon_config);
+    sponge.absorb(&digest.to_vec())?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
+
+/// Enforces that `commitment`, e.g. a public input, is the `digest_poseidon_commitment` of the 8
+/// digest words `digest`. This costs one Poseidon permutation, instead of exposing the 8 words.
+pub fn enforce_digest_poseidon_commitment<F: PrimeField + Absorb>(
+    cs: ConstraintSystemRef<F>,
+    poseidon_config: &PoseidonConfig<F>,
+    digest: &[FpVar<F>],
+    commitment: &FpVar<F>,
+) -> Result<(), SynthesisError> {
+    digest_poseidon_commitment_var(cs, poseidon_config, digest)?.enforce_equal(commitment)
+}
+
+impl<F: PrimeField + Absorb> FCircuit<F> for FoldedSha256PoseidonFCircuit<F> {
+    type Params = PoseidonConfig<F>;
+
+    fn new(params: Self::Params) -> Result<Self, Error> {
+        Ok(Self {
+            poseidon_config: params,
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + 1
+    }
+    fn external_inputs_len(&self) -> usize {
+        64
+    }
+
+    fn step_native(&self, i: usize, z_i: Vec<F>, external_inputs: Vec<F>) -> Result<Vec<F>, Error> {
+        let mut z_i1 = FoldedSha256FCircuit::<F>::sha256().step_native(
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            external_inputs.clone(),
+        )?;
+        z_i1.push(absorb_block_native(
+            &self.poseidon_config,
+            z_i[STATE_LEN],
+            &external_inputs,
+        ));
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<F>,
+        i: usize,
+        z_i: Vec<FpVar<F>>,
+        external_inputs: Vec<FpVar<F>>,
+    ) -> Result<Vec<FpVar<F>>, SynthesisError> {
+        let mut z_i1 = FoldedSha256FCircuit::<F>::sha256().generate_step_constraints(
+            cs.clone(),
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            external_inputs.clone(),
+        )?;
+
+        z_i1.push(absorb_block_var(
+            cs,
+            &self.poseidon_config,
+            &z_i[STATE_LEN],
+            &external_inputs,
+        )?);
+
+        Ok(z_i1)
+    }
+}
+
+/// Folds the SHA-256 compression of `input` together with a Poseidon commitment to it, using the
+/// same `poseidon_canonical_config` as the folding scheme. The last element of the final state is
+/// the commitment, see `poseidon_commitment`.
+pub fn run_with_poseidon_commitment<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256PoseidonFCircuit<Fr>>,
+        NovaParamsFor<FoldedSha256PoseidonFCircuit<Fr>>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+
+    let mut initial_state: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    initial_state.push(Fr::from(0u64));
+
+    let F_circuit = FoldedSha256PoseidonFCircuit::<Fr>::new(poseidon_canonical_config::<Fr>())?;
+
+    fold(
+        F_circuit,
+        initial_state,
+        BlockExternalInputs::<Fr, _>::new(input),
+        config,
+        rng,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::utils::{sha256_msg_block_sequence, update_state_ref};
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    #[test]
+    fn test_poseidon_commitment_matches_standalone() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let circuit = FoldedSha256PoseidonFCircuit::<Fr>::new(poseidon_config.clone()).unwrap();
+        let input: Vec<u8> = (0..100).map(|i| i as u8).collect();
+
+        let mut z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        z_i.push(Fr::from(0u64));
+        for (i, block) in BlockExternalInputs::<Fr, _>::new(input.clone()).enumerate() {
+            z_i = circuit.step_native(i, z_i, block).unwrap();
+        }
+
+        assert_eq!(
+            z_i[STATE_LEN],
+            poseidon_commitment(&poseidon_config, input.clone())
+        );
+
+        // the SHA-256 part of the state is unaffected by the commitment
+        let mut state = H.to_vec();
+        for block in sha256_msg_block_sequence(input) {
+            state = update_state_ref(state, block.to_vec()).unwrap();
+        }
+        let expected_state: Vec<Fr> = state.iter().map(|&x| Fr::from(x)).collect();
+        assert_eq!(z_i[..STATE_LEN].to_vec(), expected_state);
+    }
+
+    #[test]
+    fn test_poseidon_f_circuit() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let circuit =
+            FoldedSha256PoseidonFCircuit::<Fr>::new(poseidon_canonical_config::<Fr>()).unwrap();
+
+        let mut z_i: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+        z_i.push(Fr::from(42u64));
+        let external_inputs = BlockExternalInputs::<Fr, _>::new(b"abc".to_vec())
+            .next()
+            .unwrap();
+
+        let z_i1 = circuit
+            .step_native(0, z_i.clone(), external_inputs.clone())
+            .unwrap();
+
+        let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+        let externalInputsVar =
+            Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(external_inputs)).unwrap();
+        let computed_z_i1Var = circuit
+            .generate_step_constraints(cs.clone(), 0, z_iVar, externalInputsVar)
+            .unwrap();
+
+        assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_digest_poseidon_commitment() {
+        let poseidon_config = poseidon_canonical_config::<Fr>();
+        let digest = crate::folded_sha256::hasher::sha256(b"abc").0;
+        let commitment = digest_poseidon_commitment(&poseidon_config, &digest);
+        let words: Vec<Fr> = digest
+            .chunks(4)
+            .map(|w| Fr::from(u32::from_be_bytes(w.try_into().unwrap())))
+            .collect();
+
+        for (expected, satisfied) in [(commitment, true), (commitment + Fr::from(1u64), false)] {
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let digestVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(words.clone())).unwrap();
+            let commitmentVar = FpVar::new_input(cs.clone(), || Ok(expected)).unwrap();
+            let computed =
+                digest_poseidon_commitment_var(cs.clone(), &poseidon_config, &digestVar).unwrap();
+            assert_eq!(computed.value().unwrap(), commitment);
+            enforce_digest_poseidon_commitment(
+                cs.clone(),
+                &poseidon_config,
+                &digestVar,
+                &commitmentVar,
+            )
+            .unwrap();
+            assert_eq!(cs.is_satisfied().unwrap(), satisfied);
+        }
+        assert_ne!(
+            commitment,
+            digest_poseidon_commitment(&poseidon_config, &[0u8; 32])
+        );
+    }
+}
@@ -0,0 +1,282 @@
+//! Keyed SHA-256: the compression of the padded message chained from a secret 256-bit key
+//! instead of the standard IV `H`, i.e. `PRF_k(m) = SHA-256 with H := k`.
+//!
+//! This is non-standard and is not HMAC. It is a (weak) PRF only under the assumption that the
+//! compression function is one: like any secret-prefix MAC built on Merkle–Damgård, it is
+//! vulnerable to length extension, `PRF_k(m)` giving `PRF_k(m || pad(m) || m')` for any `m'`
+//! without the key. Use HMAC when a standard MAC is required.
+//!
+//! The key is a witness of every step, and only its Poseidon hash is public, in `z_0`, to bind
+//! the proof to one key. The Nova IVC proof carries the witnesses of the folded instances, so it
+//! reveals the key: only a zero-knowledge Decider proof of it (e.g. Groth16) hides the key.
+#![allow(non_snake_case)]
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{fold, FoldedSha256FCircuit, NovaFor, NovaParamsFor, STATE_LEN};
+use crate::folded_sha256::utils::{compress_block, sha256_msg_block_sequence, BlockExternalInputs};
+
+use ark_bn254::Fr;
+use ark_crypto_primitives::sponge::{
+    constraints::CryptographicSpongeVar,
+    poseidon::{constraints::PoseidonSpongeVar, PoseidonConfig, PoseidonSponge},
+    CryptographicSponge,
+};
+use ark_ff::{One, Zero};
+use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, uint32::UInt32};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+use folding_schemes::Error;
+
+/// Index of the Poseidon hash of the key in the state.
+const KEY_HASH_INDEX: usize = STATE_LEN;
+/// Index of the flag telling whether the first block has been compressed.
+const STARTED_INDEX: usize = STATE_LEN + 1;
+
+/// The key as the 8 big-endian words of the initial SHA-256 state.
+fn key_words(key: &[u8; 32]) -> [u32; 8] {
+    core::array::from_fn(|j| u32::from_be_bytes(key[4 * j..4 * j + 4].try_into().unwrap()))
+}
+
+/// `PRF_k(input)`: the SHA-256 of `input` with the key `k` as initial state, computed natively.
+pub fn prf(key: &[u8; 32], input: &[u8]) -> [u8; 32] {
+    let state = sha256_msg_block_sequence(input.to_vec())
+        .iter()
+        .fold(key_words(key), |state, block| compress_block(&state, block));
+    let mut output = [0u8; 32];
+    for (chunk, x) in output.chunks_mut(4).zip(state) {
+        chunk.copy_from_slice(&x.to_be_bytes());
+    }
+    output
+}
+
+/// Poseidon hash of the 8 key words, the public binding of the key in `z_0`.
+///
+/// It hides a uniformly random key, but not a guessable one: anyone can hash candidate keys.
+pub fn key_hash(poseidon_config: &PoseidonConfig<Fr>, key: &[u8; 32]) -> Fr {
+    let words: Vec<Fr> = key_words(key).iter().map(|&x| Fr::from(x)).collect();
+    let mut sponge = PoseidonSponge::<Fr>::new(poseidon_config);
+    sponge.absorb(&words);
+    sponge.squeeze_field_elements(1)[0]
+}
+
+/// SHA-256 folding circuit of `PRF_k`, holding the secret key `k`.
+///
+/// The state is `z_i = [H_0, ..., H_7, key_hash, started]`, with `z_0 = prf_initial_state`:
+/// the 8 state words are ignored by the first step, whose chaining value is the key, and
+/// `started` is 0 in `z_0` and 1 afterwards. Every step allocates the key as a witness and
+/// enforces its Poseidon hash is `key_hash`, which is copied unchanged, so the final state holds
+/// `PRF_k(m)` for the key committed in `z_0`.
+#[derive(Clone, Debug)]
+pub struct FoldedSha256PrfFCircuit {
+    key: [u32; 8],
+    poseidon_config: PoseidonConfig<Fr>,
+}
+
+impl FCircuit<Fr> for FoldedSha256PrfFCircuit {
+    /// The secret key. The verifier does not need it: the verifier parameters do not depend on
+    /// it, only the witnesses do.
+    type Params = [u8; 32];
+
+    fn new(key: Self::Params) -> Result<Self, Error> {
+        Ok(Self {
+            key: key_words(&key),
+            poseidon_config: poseidon_canonical_config::<Fr>(),
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + 2
+    }
+    fn external_inputs_len(&self) -> usize {
+        FoldedSha256FCircuit::<Fr>::sha256().external_inputs_len()
+    }
+
+    fn step_native(
+        &self,
+        i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let key: Vec<Fr> = self.key.iter().map(|&x| Fr::from(x)).collect();
+        let mut sponge = PoseidonSponge::<Fr>::new(&self.poseidon_config);
+        sponge.absorb(&key);
+        if sponge.squeeze_field_elements::<Fr>(1)[0] != z_i[KEY_HASH_INDEX] {
+            return Err(Error::Other(
+                "the key does not match the key hash of the state".to_string(),
+            ));
+        }
+
+        let chaining = if z_i[STARTED_INDEX].is_zero() {
+            key
+        } else {
+            z_i[..STATE_LEN].to_vec()
+        };
+        let mut z_i1 =
+            FoldedSha256FCircuit::<Fr>::sha256().step_native(i, chaining, external_inputs)?;
+        z_i1.push(z_i[KEY_HASH_INDEX]);
+        z_i1.push(Fr::one());
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        // the key words are range-checked by their allocation as `UInt32`
+        let key = self
+            .key
+            .iter()
+            .map(|&x| UInt32::new_witness(cs.clone(), || Ok(x))?.to_fp())
+            .collect::<Result<Vec<FpVar<Fr>>, SynthesisError>>()?;
+        let mut sponge = PoseidonSpongeVar::<Fr>::new(cs.clone(), &self.poseidon_config);
+        sponge.absorb(&key)?;
+        sponge.squeeze_field_elements(1)?[0].enforce_equal(&z_i[KEY_HASH_INDEX])?;
+
+        // started is 0 or 1, so the chaining value is the key or the previous state
+        let started = &z_i[STARTED_INDEX];
+        let chaining = key
+            .iter()
+            .zip(&z_i[..STATE_LEN])
+            .map(|(k, h)| k + started * (h - k))
+            .collect::<Vec<FpVar<Fr>>>();
+        let mut z_i1 = FoldedSha256FCircuit::<Fr>::sha256().generate_step_constraints(
+            cs,
+            i,
+            chaining,
+            external_inputs,
+        )?;
+        z_i1.push(z_i[KEY_HASH_INDEX].clone());
+        z_i1.push(FpVar::Constant(Fr::one()));
+        Ok(z_i1)
+    }
+}
+
+/// The initial state of the `PRF_k` folding: no SHA-256 state, the Poseidon hash of the key, not
+/// started. It does not contain the key.
+pub fn prf_initial_state(key: &[u8; 32]) -> Vec<Fr> {
+    let mut z_0 = vec![Fr::zero(); STATE_LEN];
+    z_0.push(key_hash(&poseidon_canonical_config::<Fr>(), key));
+    z_0.push(Fr::zero());
+    z_0
+}
+
+/// Folds `PRF_k(input)`, which the final state holds as the 8 words of a digest, see
+/// `digest::digest_from_state`.
+pub fn prove_prf<R: RngCore + CryptoRng>(
+    key: [u8; 32],
+    input: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256PrfFCircuit>,
+        NovaParamsFor<FoldedSha256PrfFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+    fold(
+        FoldedSha256PrfFCircuit::new(key)?,
+        prf_initial_state(&key),
+        BlockExternalInputs::<Fr, _>::new(input),
+        config,
+        rng,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::digest::digest_from_state;
+    use crate::folded_sha256::hasher::sha256;
+    use crate::folded_sha256::main::H;
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    const KEY: [u8; 32] = [0x42; 32];
+
+    #[test]
+    fn test_prf() {
+        let input = b"message".to_vec();
+        assert_ne!(prf(&KEY, &input), prf(&[0x43; 32], &input));
+        assert_ne!(prf(&KEY, &input), prf(&KEY, b"massage"));
+
+        // the IV as key gives back SHA-256
+        let mut iv = [0u8; 32];
+        for (chunk, x) in iv.chunks_mut(4).zip(H) {
+            chunk.copy_from_slice(&x.to_be_bytes());
+        }
+        assert_eq!(prf(&iv, &input), sha256(&input));
+    }
+
+    #[test]
+    fn test_prf_f_circuit() {
+        let input: Vec<u8> = (0..100).map(|i| i as u8).collect();
+        let circuit = FoldedSha256PrfFCircuit::new(KEY).unwrap();
+
+        let mut z_i = prf_initial_state(&KEY);
+        for (i, block) in BlockExternalInputs::<Fr, _>::new(input.clone()).enumerate() {
+            let z_i1 = circuit.step_native(i, z_i.clone(), block.clone()).unwrap();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+            assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+            assert!(cs.is_satisfied().unwrap());
+
+            z_i = z_i1;
+        }
+        assert_eq!(digest_from_state(&z_i[..STATE_LEN]), prf(&KEY, &input));
+    }
+
+    #[test]
+    fn test_key_must_match_the_committed_hash() {
+        let circuit = FoldedSha256PrfFCircuit::new([0x43; 32]).unwrap();
+        let z_0 = prf_initial_state(&KEY);
+        let block = BlockExternalInputs::<Fr, _>::new(b"abc".to_vec())
+            .next()
+            .unwrap();
+        assert!(circuit.step_native(0, z_0.clone(), block.clone()).is_err());
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_0Var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_0)).unwrap();
+        let externalInputsVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+        circuit
+            .generate_step_constraints(cs.clone(), 0, z_0Var, externalInputsVar)
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    #[test]
+    fn test_key_is_not_in_the_public_inputs() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let input = b"message".to_vec();
+        let (folding_scheme, _) =
+            prove_prf(KEY, input.clone(), &FoldConfig::default(), &mut rng).unwrap();
+        let (other, _) =
+            prove_prf([0x43; 32], input.clone(), &FoldConfig::default(), &mut rng).unwrap();
+
+        let ivc_proof = folding_scheme.ivc_proof();
+        assert_eq!(
+            digest_from_state(&ivc_proof.z_i[..STATE_LEN]),
+            prf(&KEY, &input)
+        );
+        assert_ne!(other.z_i[..STATE_LEN], ivc_proof.z_i[..STATE_LEN]);
+
+        let key_words: Vec<Fr> = key_words(&KEY).iter().map(|&x| Fr::from(x)).collect();
+        for x in ivc_proof.z_0.iter().chain(&ivc_proof.z_i) {
+            assert!(!key_words.contains(x));
+        }
+    }
+}
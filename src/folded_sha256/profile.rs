@@ -0,0 +1,130 @@
+//! Constraint-cost instrumentation of the compression circuit, behind the `constraint-profile`
+//! feature.
+//!
+//! With the feature, `circuit::one_compression_round` and its variants snapshot
+//! `cs.num_constraints()` around each part of the compression, log the breakdown of every
+//! compression and accumulate it per thread until `take_profile`. Without it the snapshots are
+//! skipped and `take_profile` always returns an empty profile.
+use ark_ff::PrimeField;
+use ark_relations::r1cs::ConstraintSystemRef;
+use std::cell::RefCell;
+use std::fmt;
+
+/// Part of the compression function the constraints are attributed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Expansion of the message schedule `w[16..64]`, σ0 and σ1 included.
+    Schedule,
+    /// `Ch(e, f, g)`.
+    Ch,
+    /// `Maj(a, b, c)`.
+    Maj,
+    /// `Σ0(a)` and `Σ1(e)`.
+    BigSigma,
+    /// The modular additions of the rounds.
+    RoundAdditions,
+    /// The additions of the initial state.
+    FeedForward,
+}
+
+/// Number of constraints generated by each phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConstraintProfile {
+    pub schedule: usize,
+    pub ch: usize,
+    pub maj: usize,
+    pub big_sigma: usize,
+    pub round_additions: usize,
+    pub feed_forward: usize,
+}
+
+impl ConstraintProfile {
+    pub fn total(&self) -> usize {
+        self.schedule
+            + self.ch
+            + self.maj
+            + self.big_sigma
+            + self.round_additions
+            + self.feed_forward
+    }
+
+    fn add(&mut self, phase: Phase, num_constraints: usize) {
+        let count = match phase {
+            Phase::Schedule => &mut self.schedule,
+            Phase::Ch => &mut self.ch,
+            Phase::Maj => &mut self.maj,
+            Phase::BigSigma => &mut self.big_sigma,
+            Phase::RoundAdditions => &mut self.round_additions,
+            Phase::FeedForward => &mut self.feed_forward,
+        };
+        *count += num_constraints;
+    }
+}
+
+impl fmt::Display for ConstraintProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "schedule:        {}", self.schedule)?;
+        writeln!(f, "ch:              {}", self.ch)?;
+        writeln!(f, "maj:             {}", self.maj)?;
+        writeln!(f, "Σ0, Σ1:          {}", self.big_sigma)?;
+        writeln!(f, "round additions: {}", self.round_additions)?;
+        writeln!(f, "feed-forward:    {}", self.feed_forward)?;
+        write!(f, "total:           {}", self.total())
+    }
+}
+
+thread_local! {
+    /// Profile of the compression being synthesized and of all of them since `take_profile`.
+    static PROFILE: RefCell<(ConstraintProfile, ConstraintProfile)> = RefCell::default();
+}
+
+/// Returns the constraints accumulated by the compressions synthesized on this thread since the
+/// last call, and resets them.
+pub fn take_profile() -> ConstraintProfile {
+    PROFILE.with(|profile| std::mem::take(&mut profile.borrow_mut().1))
+}
+
+/// Snapshot of the number of constraints of `cs`, the constraints generated since then being
+/// attributed to a phase by `record`.
+pub(crate) struct Mark<F: PrimeField> {
+    cs: ConstraintSystemRef<F>,
+    num_constraints: usize,
+}
+
+impl<F: PrimeField> Mark<F> {
+    pub(crate) fn new(cs: ConstraintSystemRef<F>) -> Self {
+        let num_constraints = if cfg!(feature = "constraint-profile") {
+            cs.num_constraints()
+        } else {
+            0
+        };
+        Self {
+            cs,
+            num_constraints,
+        }
+    }
+
+    /// Attributes the constraints generated since the previous snapshot to `phase`.
+    pub(crate) fn record(&mut self, phase: Phase) {
+        if !cfg!(feature = "constraint-profile") {
+            return;
+        }
+        let num_constraints = self.cs.num_constraints();
+        let delta = num_constraints - self.num_constraints;
+        self.num_constraints = num_constraints;
+        PROFILE.with(|profile| {
+            let (current, total) = &mut *profile.borrow_mut();
+            current.add(phase, delta);
+            total.add(phase, delta);
+        });
+    }
+}
+
+/// Logs the breakdown of the compression just synthesized.
+pub(crate) fn end_compression() {
+    if !cfg!(feature = "constraint-profile") {
+        return;
+    }
+    let current = PROFILE.with(|profile| std::mem::take(&mut profile.borrow_mut().0));
+    println!("compression constraints:\n{}", current);
+}
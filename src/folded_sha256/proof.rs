@@ -0,0 +1,602 @@
+use crate::folded_sha256::config::{FoldConfig, Variant};
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{DeciderProof, DeciderVerifierParam, D, N};
+#[cfg(feature = "dump-on-failure")]
+use crate::folded_sha256::utils::BLOCK_LENGTH_BYTES;
+
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use folding_schemes::folding::traits::CommittedInstanceOps;
+use folding_schemes::Decider;
+#[cfg(feature = "dump-on-failure")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "dump-on-failure")]
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "dump-on-failure")]
+use std::{fs, io};
+
+/// The public data of a folded SHA-256 computation the Decider verifier checks a proof against:
+/// the number of folded steps `i`, the initial and final states `z_0` and `z_i`, and the
+/// commitments of the running and incoming instances.
+///
+/// It is extracted from the prover's folding scheme once, after which a standalone verifier only
+/// needs its serialization, the Decider proof and the verifier parameters.
+///
+/// With the `serde` feature, it also (de)serializes with serde, each field element and
+/// commitment as the hex string of its compressed encoding, see `hex_canonical`.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PublicInputsBundle {
+    #[cfg_attr(feature = "serde", serde(with = "hex_canonical"))]
+    pub i: Fr,
+    #[cfg_attr(feature = "serde", serde(with = "hex_canonical::vec"))]
+    pub z_0: Vec<Fr>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_canonical::vec"))]
+    pub z_i: Vec<Fr>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_canonical::vec"))]
+    pub running_commitments: Vec<Projective>,
+    #[cfg_attr(feature = "serde", serde(with = "hex_canonical::vec"))]
+    pub incoming_commitments: Vec<Projective>,
+}
+
+impl PublicInputsBundle {
+    pub fn new(folding_scheme: &N) -> Self {
+        Self {
+            i: folding_scheme.i,
+            z_0: folding_scheme.z_0.clone(),
+            z_i: folding_scheme.z_i.clone(),
+            running_commitments: folding_scheme.U_i.get_commitments(),
+            incoming_commitments: folding_scheme.u_i.get_commitments(),
+        }
+    }
+
+    /// Runs the Decider verifier on `proof`, returning `false` if it is invalid or malformed.
+    /// With the `dump-on-failure` feature, `verify_or_dump` also dumps a failure.
+    pub fn verify(&self, vp: DeciderVerifierParam, proof: &DeciderProof) -> bool {
+        self.decider_verify(vp, proof)
+    }
+
+    /// Runs the Decider verifier on `proof` and, if it fails, writes a `FailureDump` of the
+    /// public inputs, the `blocks` of the message (if known, e.g. by a prover checking its own
+    /// proof) and the proof into `dir`, for offline analysis. Returns the path of the dump, or
+    /// `None` if the proof verifies.
+    #[cfg(feature = "dump-on-failure")]
+    pub fn verify_or_dump(
+        &self,
+        vp: DeciderVerifierParam,
+        proof: &DeciderProof,
+        blocks: &[[u8; BLOCK_LENGTH_BYTES]],
+        dir: &Path,
+    ) -> io::Result<Option<PathBuf>> {
+        if self.decider_verify(vp, proof) {
+            return Ok(None);
+        }
+        self.dump(proof, blocks, dir).map(Some)
+    }
+
+    #[cfg(feature = "dump-on-failure")]
+    fn dump(
+        &self,
+        proof: &DeciderProof,
+        blocks: &[[u8; BLOCK_LENGTH_BYTES]],
+        dir: &Path,
+    ) -> io::Result<PathBuf> {
+        let dump = FailureDump {
+            public_inputs: self.clone(),
+            blocks: blocks.to_vec(),
+            proof: proof.clone(),
+        };
+        let mut bytes = Vec::new();
+        dump.serialize_compressed(&mut bytes)
+            .map_err(io::Error::other)?;
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let path = dir.join(format!("folded_sha256-failure-{}.bin", nanos));
+        fs::write(&path, bytes)?;
+        Ok(path)
+    }
+
+    fn decider_verify(&self, vp: DeciderVerifierParam, proof: &DeciderProof) -> bool {
+        D::verify(
+            vp,
+            self.i,
+            self.z_0.clone(),
+            self.z_i.clone(),
+            &self.running_commitments,
+            &self.incoming_commitments,
+            proof,
+        )
+        .unwrap_or(false)
+    }
+}
+
+/// Inputs of a failed verification, written by `PublicInputsBundle::verify_or_dump` with the
+/// `dump-on-failure` feature. `blocks` is empty when the verifier does not know the message.
+#[cfg(feature = "dump-on-failure")]
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FailureDump {
+    pub public_inputs: PublicInputsBundle,
+    pub blocks: Vec<[u8; BLOCK_LENGTH_BYTES]>,
+    pub proof: DeciderProof,
+}
+
+/// Everything a verifier needs to check a folded SHA-256 computation, as a single transferable
+/// object: the public inputs and the Decider proof.
+///
+/// Once built, the proof no longer depends on the prover's folding scheme.
+///
+/// The compact binary format is `CanonicalSerialize`, see `to_bytes`. With the `serde` feature,
+/// the proof also (de)serializes with serde, e.g. to human-readable JSON with `serde_json`, the
+/// Decider proof being the hex string of its compressed encoding.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sha256Proof {
+    pub public_inputs: PublicInputsBundle,
+    #[cfg_attr(feature = "serde", serde(with = "hex_canonical"))]
+    pub proof: DeciderProof,
+}
+
+/// Compressed serialized sizes, in bytes, of what a verifier receives, e.g. to compare the
+/// transmission or on-chain cost of different commitment schemes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofSizes {
+    /// The Decider proof.
+    pub proof: usize,
+    /// The Decider verifier parameters.
+    pub verifier_key: usize,
+    /// The public data bundled with the proof: `i`, `z_0`, `z_i` and the instance commitments.
+    pub public_inputs: usize,
+}
+
+impl ProofSizes {
+    pub fn total(&self) -> usize {
+        self.proof + self.verifier_key + self.public_inputs
+    }
+}
+
+impl Sha256Proof {
+    /// Bundles the Decider `proof` with the public data of the `folding_scheme` it was generated from.
+    pub fn new(folding_scheme: &N, proof: DeciderProof) -> Self {
+        Self {
+            public_inputs: PublicInputsBundle::new(folding_scheme),
+            proof,
+        }
+    }
+
+    /// Serialized sizes of this proof and of the verifier parameters `vp` it is checked with.
+    pub fn sizes(&self, vp: &DeciderVerifierParam) -> ProofSizes {
+        let proof = self.proof.compressed_size();
+        ProofSizes {
+            proof,
+            verifier_key: vp.compressed_size(),
+            public_inputs: self.public_inputs.compressed_size(),
+        }
+    }
+
+    /// Runs the Decider verifier, returning `false` if the proof is invalid or malformed.
+    pub fn verify(&self, vp: DeciderVerifierParam) -> bool {
+        self.public_inputs.verify(vp, &self.proof)
+    }
+
+    /// Same as `PublicInputsBundle::verify_or_dump` for this proof: returns the path of the
+    /// dump written into `dir` if the proof does not verify, `None` if it does.
+    #[cfg(feature = "dump-on-failure")]
+    pub fn verify_or_dump(
+        &self,
+        vp: DeciderVerifierParam,
+        blocks: &[[u8; BLOCK_LENGTH_BYTES]],
+        dir: &Path,
+    ) -> io::Result<Option<PathBuf>> {
+        self.public_inputs
+            .verify_or_dump(vp, &self.proof, blocks, dir)
+    }
+
+    /// Serializes the proof, compressed, after the `meta` header of the settings it was
+    /// generated with.
+    pub fn to_bytes(&self, meta: &ProofMeta) -> Result<Vec<u8>, FoldedSha256Error> {
+        let mut bytes = meta.to_bytes().to_vec();
+        self.serialize_compressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Deserializes a proof written by `to_bytes`, checking its header against the settings
+    /// `expected` by the verifier first.
+    ///
+    /// A proof generated with other settings is rejected with
+    /// `FoldedSha256Error::ProofMetaMismatch` before it is even deserialized, instead of failing
+    /// the verification with no indication of the cause.
+    pub fn from_bytes(bytes: &[u8], expected: &ProofMeta) -> Result<Self, FoldedSha256Error> {
+        let found = ProofMeta::from_bytes(bytes)?;
+        if found != *expected {
+            return Err(FoldedSha256Error::ProofMetaMismatch {
+                expected: *expected,
+                found,
+            });
+        }
+        Ok(Self::deserialize_compressed(&bytes[PROOF_META_LEN..])?)
+    }
+}
+
+/// Packing of the message blocks into the external inputs, see `layout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackingLayout {
+    /// `layout::ByteLayout`.
+    Bytes,
+    /// `layout::WordLayout`.
+    Words,
+}
+
+/// Cycle of curves of the folding, the primary curve first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveCycle {
+    Bn254Grumpkin,
+}
+
+/// First bytes of a serialized `Sha256Proof`.
+pub const PROOF_MAGIC: [u8; 4] = *b"FS2P";
+
+/// Version of the `ProofMeta` encoding.
+pub const PROOF_META_VERSION: u8 = 1;
+
+/// Length of the encoded `ProofMeta`: the magic, the version, the variant, the layout, the
+/// 8-byte big-endian `blocks_per_step` and the curve cycle.
+pub const PROOF_META_LEN: usize = PROOF_MAGIC.len() + 4 + 8;
+
+/// Settings a proof was generated with, written in front of the serialized proof by
+/// `Sha256Proof::to_bytes`.
+///
+/// A proof only verifies with the parameters of the same settings, and a mismatch otherwise
+/// shows up as an unexplained verification failure (or a deserialization error), so
+/// `Sha256Proof::from_bytes` compares them to those of the verifier first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofMeta {
+    pub variant: Variant,
+    pub layout: PackingLayout,
+    pub blocks_per_step: usize,
+    pub curve_cycle: CurveCycle,
+}
+
+impl ProofMeta {
+    /// Metadata of a proof of `variant` folded with `layout` and `config`, on the curve cycle of
+    /// `main::N`.
+    pub fn new(variant: Variant, layout: PackingLayout, config: &FoldConfig) -> Self {
+        Self {
+            variant,
+            layout,
+            blocks_per_step: config.blocks_per_step,
+            curve_cycle: CurveCycle::Bn254Grumpkin,
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; PROOF_META_LEN] {
+        let mut bytes = [0u8; PROOF_META_LEN];
+        bytes[..4].copy_from_slice(&PROOF_MAGIC);
+        bytes[4] = PROOF_META_VERSION;
+        bytes[5] = match self.variant {
+            Variant::Sha256 => 0,
+            Variant::Sha224 => 1,
+        };
+        bytes[6] = match self.layout {
+            PackingLayout::Bytes => 0,
+            PackingLayout::Words => 1,
+        };
+        bytes[7..15].copy_from_slice(&(self.blocks_per_step as u64).to_be_bytes());
+        bytes[15] = match self.curve_cycle {
+            CurveCycle::Bn254Grumpkin => 0,
+        };
+        bytes
+    }
+
+    /// Decodes the header at the start of `bytes`, returning
+    /// `FoldedSha256Error::InvalidProofMeta` if it is truncated, of another version, or has an
+    /// unknown field value.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, FoldedSha256Error> {
+        if bytes.len() < PROOF_META_LEN
+            || bytes[..4] != PROOF_MAGIC
+            || bytes[4] != PROOF_META_VERSION
+        {
+            return Err(FoldedSha256Error::InvalidProofMeta);
+        }
+        let variant = match bytes[5] {
+            0 => Variant::Sha256,
+            1 => Variant::Sha224,
+            _ => return Err(FoldedSha256Error::InvalidProofMeta),
+        };
+        let layout = match bytes[6] {
+            0 => PackingLayout::Bytes,
+            1 => PackingLayout::Words,
+            _ => return Err(FoldedSha256Error::InvalidProofMeta),
+        };
+        let blocks_per_step = u64::from_be_bytes(bytes[7..15].try_into().unwrap());
+        let curve_cycle = match bytes[15] {
+            0 => CurveCycle::Bn254Grumpkin,
+            _ => return Err(FoldedSha256Error::InvalidProofMeta),
+        };
+        Ok(Self {
+            variant,
+            layout,
+            blocks_per_step: usize::try_from(blocks_per_step)
+                .map_err(|_| FoldedSha256Error::InvalidProofMeta)?,
+            curve_cycle,
+        })
+    }
+}
+
+/// Serde adapters encoding an arkworks value as the hex string of its compressed
+/// `CanonicalSerialize` encoding, for `#[serde(with = "hex_canonical")]`.
+#[cfg(feature = "serde")]
+mod hex_canonical {
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: CanonicalSerialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&to_hex(value)?)
+    }
+
+    pub fn deserialize<'de, T: CanonicalDeserialize, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        from_hex(&String::deserialize(deserializer)?)
+    }
+
+    fn to_hex<T: CanonicalSerialize, E: serde::ser::Error>(value: &T) -> Result<String, E> {
+        let mut bytes = Vec::new();
+        value.serialize_compressed(&mut bytes).map_err(E::custom)?;
+        Ok(hex::encode(bytes))
+    }
+
+    fn from_hex<T: CanonicalDeserialize, E: de::Error>(s: &str) -> Result<T, E> {
+        let bytes = hex::decode(s).map_err(E::custom)?;
+        T::deserialize_compressed(&bytes[..]).map_err(E::custom)
+    }
+
+    /// Same as `hex_canonical` for a sequence, as a sequence of hex strings.
+    pub mod vec {
+        use super::{from_hex, to_hex};
+        use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+        use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<T: CanonicalSerialize, S: Serializer>(
+            values: &[T],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            let mut seq = serializer.serialize_seq(Some(values.len()))?;
+            for value in values {
+                seq.serialize_element(&to_hex::<T, S::Error>(value)?)?;
+            }
+            seq.end()
+        }
+
+        pub fn deserialize<'de, T: CanonicalDeserialize, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<T>, D::Error> {
+            Vec::<String>::deserialize(deserializer)?
+                .iter()
+                .map(|s| from_hex(s))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::main::run;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    // the Decider proof of "abc" and its verifier parameters. It runs the full Decider (Groth16)
+    // setup, so the tests calling it are ignored, use `cargo test -- --ignored`
+    fn abc_decider_proof() -> (N, DeciderVerifierParam, DeciderProof) {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, nova_params) =
+            run(b"abc".to_vec(), &FoldConfig::default(), &mut rng).unwrap();
+        let (decider_pp, decider_vp) =
+            D::preprocess(&mut rng, nova_params, folding_scheme.clone()).unwrap();
+        let proof = D::prove(&mut rng, decider_pp, folding_scheme.clone()).unwrap();
+        (folding_scheme, decider_vp, proof)
+    }
+
+    #[test]
+    #[ignore]
+    fn test_sha256_proof_round_trip_then_verify() {
+        let (folding_scheme, decider_vp, proof) = abc_decider_proof();
+        let sha256_proof = Sha256Proof::new(&folding_scheme, proof);
+
+        let mut bytes = Vec::new();
+        sha256_proof.serialize_compressed(&mut bytes).unwrap();
+        let deserialized = Sha256Proof::deserialize_compressed(&bytes[..]).unwrap();
+
+        assert!(deserialized.verify(decider_vp));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_public_inputs_json_round_trip() {
+        use crate::folded_sha256::main::H;
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, _) = run(b"abc".to_vec(), &FoldConfig::default(), &mut rng).unwrap();
+        let public_inputs = PublicInputsBundle::new(&folding_scheme);
+
+        let json = serde_json::to_value(&public_inputs).unwrap();
+        let mut i = Vec::new();
+        public_inputs.i.serialize_compressed(&mut i).unwrap();
+        assert_eq!(json["i"], hex::encode(i));
+        assert_eq!(json["z_0"].as_array().unwrap().len(), H.len());
+
+        let deserialized: PublicInputsBundle = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, public_inputs);
+        assert!(serde_json::from_str::<PublicInputsBundle>(r#"{"i":"zz"}"#).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    #[ignore]
+    fn test_sha256_proof_json_round_trip_then_verify() {
+        let (folding_scheme, decider_vp, proof) = abc_decider_proof();
+        let sha256_proof = Sha256Proof::new(&folding_scheme, proof);
+
+        let json = serde_json::to_string_pretty(&sha256_proof).unwrap();
+        let deserialized: Sha256Proof = serde_json::from_str(&json).unwrap();
+
+        assert!(deserialized.verify(decider_vp));
+    }
+
+    #[test]
+    fn test_proof_meta_round_trip() {
+        let meta = ProofMeta::new(
+            Variant::Sha224,
+            PackingLayout::Words,
+            &FoldConfig::default(),
+        );
+        let bytes = meta.to_bytes();
+        assert_eq!(&bytes[..4], b"FS2P");
+        assert_eq!(ProofMeta::from_bytes(&bytes).unwrap(), meta);
+
+        assert!(matches!(
+            ProofMeta::from_bytes(&bytes[..PROOF_META_LEN - 1]),
+            Err(FoldedSha256Error::InvalidProofMeta)
+        ));
+        let mut unknown_variant = bytes;
+        unknown_variant[5] = 2;
+        assert!(matches!(
+            ProofMeta::from_bytes(&unknown_variant),
+            Err(FoldedSha256Error::InvalidProofMeta)
+        ));
+    }
+
+    #[test]
+    fn test_mismatched_meta_is_rejected_before_deserializing() {
+        let sha256 = ProofMeta::new(
+            Variant::Sha256,
+            PackingLayout::Bytes,
+            &FoldConfig::default(),
+        );
+        let sha224 = ProofMeta {
+            variant: Variant::Sha224,
+            ..sha256
+        };
+        // the header is checked first, whatever follows it
+        let mut bytes = sha256.to_bytes().to_vec();
+        bytes.extend([0xff; 32]);
+
+        let error = Sha256Proof::from_bytes(&bytes, &sha224).unwrap_err();
+        assert!(matches!(
+            error,
+            FoldedSha256Error::ProofMetaMismatch { expected, found }
+                if expected == sha224 && found == sha256
+        ));
+        assert!(error.to_string().contains("metadata mismatch"));
+        assert!(matches!(
+            Sha256Proof::from_bytes(&bytes, &sha256),
+            Err(FoldedSha256Error::Serialization(_))
+        ));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_sha256_proof_rejected_by_sha224_verifier() {
+        let (folding_scheme, decider_vp, proof) = abc_decider_proof();
+        let config = FoldConfig::default();
+
+        let sha256 = ProofMeta::new(Variant::Sha256, PackingLayout::Bytes, &config);
+        let bytes = Sha256Proof::new(&folding_scheme, proof)
+            .to_bytes(&sha256)
+            .unwrap();
+        assert!(Sha256Proof::from_bytes(&bytes, &sha256)
+            .unwrap()
+            .verify(decider_vp));
+
+        let sha224 = ProofMeta::new(Variant::Sha224, PackingLayout::Bytes, &config);
+        assert!(matches!(
+            Sha256Proof::from_bytes(&bytes, &sha224),
+            Err(FoldedSha256Error::ProofMetaMismatch { .. })
+        ));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_standalone_verifier_from_serialized_public_inputs() {
+        let (folding_scheme, decider_vp, proof) = abc_decider_proof();
+        let public_inputs = PublicInputsBundle::new(&folding_scheme);
+
+        // the verifier only receives bytes, the folding scheme stays with the prover
+        let mut public_inputs_bytes = Vec::new();
+        public_inputs
+            .serialize_compressed(&mut public_inputs_bytes)
+            .unwrap();
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+        let mut vp_bytes = Vec::new();
+        decider_vp.serialize_compressed(&mut vp_bytes).unwrap();
+        drop(folding_scheme);
+
+        let public_inputs =
+            PublicInputsBundle::deserialize_compressed(&public_inputs_bytes[..]).unwrap();
+        let proof = DeciderProof::deserialize_compressed(&proof_bytes[..]).unwrap();
+        let vp = DeciderVerifierParam::deserialize_compressed(&vp_bytes[..]).unwrap();
+        assert!(public_inputs.verify(vp.clone(), &proof));
+
+        let mut tampered = public_inputs.clone();
+        tampered.i += Fr::from(1u64);
+        assert!(!tampered.verify(vp, &proof));
+    }
+
+    #[cfg(feature = "dump-on-failure")]
+    #[test]
+    #[ignore]
+    fn test_corrupted_proof_is_dumped() {
+        use crate::folded_sha256::utils::sha256_msg_block_sequence;
+
+        let (folding_scheme, decider_vp, proof) = abc_decider_proof();
+        let public_inputs = PublicInputsBundle::new(&folding_scheme);
+        let blocks = sha256_msg_block_sequence(b"abc".to_vec());
+
+        let dir = std::env::temp_dir().join("folded_sha256-dump-test");
+        fs::create_dir_all(&dir).unwrap();
+        assert!(public_inputs
+            .verify_or_dump(decider_vp.clone(), &proof, &blocks, &dir)
+            .unwrap()
+            .is_none());
+
+        let mut corrupted = public_inputs.clone();
+        corrupted.z_i[0] += Fr::from(1u64);
+        let path = corrupted
+            .verify_or_dump(decider_vp, &proof, &blocks, &dir)
+            .unwrap()
+            .expect("the corrupted proof must not verify");
+
+        let dump = FailureDump::deserialize_compressed(&fs::read(&path).unwrap()[..]).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(dump.public_inputs, corrupted);
+        assert_eq!(dump.blocks, blocks);
+    }
+
+    #[test]
+    #[ignore]
+    fn test_reported_sizes_match_serialized_lengths() {
+        let (folding_scheme, decider_vp, proof) = abc_decider_proof();
+        let sha256_proof = Sha256Proof::new(&folding_scheme, proof.clone());
+        let sizes = sha256_proof.sizes(&decider_vp);
+
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+        let mut vp_bytes = Vec::new();
+        decider_vp.serialize_compressed(&mut vp_bytes).unwrap();
+        let mut sha256_proof_bytes = Vec::new();
+        sha256_proof
+            .serialize_compressed(&mut sha256_proof_bytes)
+            .unwrap();
+
+        assert_eq!(sizes.proof, proof_bytes.len());
+        assert_eq!(sizes.verifier_key, vp_bytes.len());
+        assert_eq!(sizes.proof + sizes.public_inputs, sha256_proof_bytes.len());
+        assert_eq!(sizes.total(), sha256_proof_bytes.len() + vp_bytes.len());
+    }
+}
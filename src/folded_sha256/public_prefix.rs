@@ -0,0 +1,470 @@
+//! SHA-256 folding of a message whose first bytes are public, e.g. a known protocol header.
+//!
+//! The step circuit cannot allocate public inputs of its own: the external inputs are allocated
+//! as witnesses by the Nova augmented circuit, whose only public input is the hash of the
+//! instance. The public prefix is therefore passed in the initial state `z_0`, which the verifier
+//! supplies, and the first step enforces that the block it compresses starts with it.
+//!
+//! `FoldedSha256PublicBlocksFCircuit` instead makes the first `k` whole blocks public, for
+//! `H(public_prefix || secret)`, through a Poseidon commitment to them in the state, which the
+//! verifier recomputes from the prefix: the state stays the same size whatever `k`. Note that
+//! the Nova IVC proof carries the witnesses of the folded instances, secret blocks included: only
+//! a zero-knowledge Decider proof hides them.
+#![allow(non_snake_case)]
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::digest::{digest_from_state, Digest};
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{
+    fold, FoldedSha256FCircuit, NovaFor, NovaParamsFor, H, STATE_LEN,
+};
+use crate::folded_sha256::poseidon_commit::{absorb_block_native, absorb_block_var};
+use crate::folded_sha256::utils::{BlockExternalInputs, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_crypto_primitives::sponge::poseidon::PoseidonConfig;
+use ark_ff::{One, Zero};
+use ark_grumpkin::Projective as Projective2;
+use ark_r1cs_std::{
+    fields::{fp::FpVar, FieldVar},
+    select::CondSelectGadget,
+};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::folding::nova::IVCProof;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+use folding_schemes::{Error, FoldingScheme};
+
+/// SHA-256 folding circuit binding the first `prefix_len` bytes of the message to the state.
+///
+/// The state is `z_i = [H_0, ..., H_7, p_0, ..., p_{k-1}, started]`, where `p` is the public
+/// prefix of `k = prefix_len` bytes, at most one block, and `started` is 0 in `z_0` and 1 after
+/// the first step. The step is the same at every step, so it enforces
+/// `(1 - started) * (w[j] - p_j) = 0` for each byte `j < k` of its block, which only binds the
+/// first block, and copies `p` unchanged.
+///
+/// A verifier checks that `z_0` is `public_prefix_initial_state` of the expected prefix; a proof
+/// from a `z_0` with `started = 1` would not bind the prefix.
+#[derive(Clone, Copy, Debug)]
+pub struct FoldedSha256PublicPrefixFCircuit {
+    prefix_len: usize,
+}
+
+impl FCircuit<Fr> for FoldedSha256PublicPrefixFCircuit {
+    /// Length of the public prefix in bytes.
+    type Params = usize;
+
+    fn new(prefix_len: Self::Params) -> Result<Self, Error> {
+        if prefix_len > BLOCK_LENGTH_BYTES {
+            return Err(Error::Other(format!(
+                "a public prefix of {} bytes does not fit in the first block",
+                prefix_len
+            )));
+        }
+        Ok(Self { prefix_len })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + self.prefix_len + 1
+    }
+    fn external_inputs_len(&self) -> usize {
+        BLOCK_LENGTH_BYTES
+    }
+
+    fn step_native(
+        &self,
+        i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let (prefix, started) = z_i[STATE_LEN..].split_at(self.prefix_len);
+        if started[0].is_zero() && external_inputs[..self.prefix_len] != *prefix {
+            return Err(Error::Other(
+                "the first block does not start with the public prefix".to_string(),
+            ));
+        }
+
+        let mut z_i1 = FoldedSha256FCircuit::<Fr>::sha256().step_native(
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            external_inputs,
+        )?;
+        z_i1.extend_from_slice(prefix);
+        z_i1.push(Fr::one());
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        let (prefix, started) = z_i[STATE_LEN..].split_at(self.prefix_len);
+        let first = FpVar::one() - &started[0];
+        for (byte, p) in external_inputs.iter().zip(prefix) {
+            (byte - p).mul_equals(&first, &FpVar::zero())?;
+        }
+
+        // the compression range-checks the bytes of the block
+        let mut z_i1 = FoldedSha256FCircuit::<Fr>::sha256().generate_step_constraints(
+            cs,
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            external_inputs,
+        )?;
+        z_i1.extend_from_slice(prefix);
+        z_i1.push(FpVar::one());
+        Ok(z_i1)
+    }
+}
+
+/// The initial state of the folding of a message starting with the public `prefix`.
+pub fn public_prefix_initial_state(prefix: &[u8]) -> Vec<Fr> {
+    let mut z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    z_0.extend(prefix.iter().map(|&x| Fr::from(x)));
+    z_0.push(Fr::zero());
+    z_0
+}
+
+/// Folds the SHA-256 compression of `input`, its first `prefix_len` bytes being public in `z_0`.
+pub fn run_with_public_prefix<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    prefix_len: usize,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256PublicPrefixFCircuit>,
+        NovaParamsFor<FoldedSha256PublicPrefixFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+    if prefix_len > input.len() {
+        return Err(FoldedSha256Error::FoldingScheme(Error::Other(format!(
+            "the public prefix of {} bytes is longer than the input",
+            prefix_len
+        ))));
+    }
+
+    let F_circuit = FoldedSha256PublicPrefixFCircuit::new(prefix_len)?;
+    let z_0 = public_prefix_initial_state(&input[..prefix_len]);
+    fold(
+        F_circuit,
+        z_0,
+        BlockExternalInputs::<Fr, _>::new(input),
+        config,
+        rng,
+    )
+}
+
+/// Index of the Poseidon commitment to the public blocks in the state.
+const ACC_INDEX: usize = STATE_LEN;
+/// Index of the number of public blocks left to fold.
+const REMAINING_INDEX: usize = STATE_LEN + 1;
+
+/// SHA-256 folding circuit whose first `k` blocks are public, the others private.
+///
+/// The state is `z_i = [H_0, ..., H_7, acc_i, r_i]`, starting from `acc_0 = 0` and `r_0 = k`. Each
+/// step with `r_i > 0` absorbs its block into `acc`, as `acc_{i+1} = Poseidon(acc_i, w_i)` (see
+/// `poseidon_commit`), and decrements `r`; the other steps leave both unchanged. A verifier who
+/// knows the public prefix checks `z_0` and recomputes `acc_n`, see `verify_with_public_blocks`,
+/// while the private blocks only affect the digest.
+#[derive(Clone, Debug)]
+pub struct FoldedSha256PublicBlocksFCircuit {
+    poseidon_config: PoseidonConfig<Fr>,
+}
+
+impl FCircuit<Fr> for FoldedSha256PublicBlocksFCircuit {
+    type Params = PoseidonConfig<Fr>;
+
+    fn new(poseidon_config: Self::Params) -> Result<Self, Error> {
+        Ok(Self { poseidon_config })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + 2
+    }
+    fn external_inputs_len(&self) -> usize {
+        BLOCK_LENGTH_BYTES
+    }
+
+    fn step_native(
+        &self,
+        i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let mut z_i1 = FoldedSha256FCircuit::<Fr>::sha256().step_native(
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            external_inputs.clone(),
+        )?;
+        let (acc, remaining) = (z_i[ACC_INDEX], z_i[REMAINING_INDEX]);
+        if remaining.is_zero() {
+            z_i1.extend([acc, remaining]);
+        } else {
+            z_i1.push(absorb_block_native(
+                &self.poseidon_config,
+                acc,
+                &external_inputs,
+            ));
+            z_i1.push(remaining - Fr::one());
+        }
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        let mut z_i1 = FoldedSha256FCircuit::<Fr>::sha256().generate_step_constraints(
+            cs.clone(),
+            i,
+            z_i[..STATE_LEN].to_vec(),
+            external_inputs.clone(),
+        )?;
+
+        let (acc, remaining) = (&z_i[ACC_INDEX], &z_i[REMAINING_INDEX]);
+        let is_public = !remaining.is_zero()?;
+        let absorbed = absorb_block_var(cs, &self.poseidon_config, acc, &external_inputs)?;
+
+        z_i1.push(is_public.select(&absorbed, acc)?);
+        z_i1.push(remaining - FpVar::from(is_public));
+        Ok(z_i1)
+    }
+}
+
+/// The initial state of a folding whose first `num_public_blocks` blocks are public.
+pub fn public_blocks_initial_state(num_public_blocks: usize) -> Vec<Fr> {
+    let mut z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    z_0.push(Fr::zero());
+    z_0.push(Fr::from(num_public_blocks as u64));
+    z_0
+}
+
+/// The Poseidon commitment accumulated by `FoldedSha256PublicBlocksFCircuit` over the public
+/// `prefix`, a whole number of blocks, or `FoldedSha256Error::UnalignedSplit` otherwise.
+pub fn public_blocks_commitment(
+    poseidon_config: &PoseidonConfig<Fr>,
+    prefix: &[u8],
+) -> Result<Fr, FoldedSha256Error> {
+    if prefix.len() % BLOCK_LENGTH_BYTES != 0 {
+        return Err(FoldedSha256Error::UnalignedSplit {
+            prefix_len: prefix.len(),
+        });
+    }
+    Ok(prefix
+        .chunks(BLOCK_LENGTH_BYTES)
+        .fold(Fr::zero(), |acc, block| {
+            let block: Vec<Fr> = block.iter().map(|&x| Fr::from(x)).collect();
+            absorb_block_native(poseidon_config, acc, &block)
+        }))
+}
+
+/// Folds the SHA-256 compression of `input`, its first `num_public_blocks` blocks being public
+/// and the rest private, with the `poseidon_canonical_config`.
+///
+/// The public blocks must be message blocks, not padding: returns
+/// `FoldedSha256Error::UnalignedSplit` if `input` is shorter than them.
+pub fn prove_with_public_blocks<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    num_public_blocks: usize,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256PublicBlocksFCircuit>,
+        NovaParamsFor<FoldedSha256PublicBlocksFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(input.len())?;
+    let prefix_len = num_public_blocks * BLOCK_LENGTH_BYTES;
+    if prefix_len > input.len() {
+        return Err(FoldedSha256Error::UnalignedSplit { prefix_len });
+    }
+
+    fold(
+        FoldedSha256PublicBlocksFCircuit::new(poseidon_canonical_config::<Fr>())?,
+        public_blocks_initial_state(num_public_blocks),
+        BlockExternalInputs::<Fr, _>::new(input),
+        config,
+        rng,
+    )
+}
+
+/// Verifies the IVC proof produced by `prove_with_public_blocks` for the public `prefix`, and
+/// returns the digest of `prefix || secret`.
+///
+/// Returns `FoldedSha256Error::InvalidInitialState` if the proof does not start from the initial
+/// state of `prefix`, and `FoldedSha256Error::PublicPrefixMismatch` if its public blocks are not
+/// `prefix`.
+pub fn verify_with_public_blocks(
+    vp: <NovaFor<FoldedSha256PublicBlocksFCircuit> as FoldingScheme<
+        Projective,
+        Projective2,
+        FoldedSha256PublicBlocksFCircuit,
+    >>::VerifierParam,
+    ivc_proof: IVCProof<Projective, Projective2>,
+    prefix: &[u8],
+) -> Result<Digest, FoldedSha256Error> {
+    let commitment = public_blocks_commitment(&poseidon_canonical_config::<Fr>(), prefix)?;
+    if ivc_proof.z_0 != public_blocks_initial_state(prefix.len() / BLOCK_LENGTH_BYTES) {
+        return Err(FoldedSha256Error::InvalidInitialState);
+    }
+    if ivc_proof.z_i[ACC_INDEX] != commitment || !ivc_proof.z_i[REMAINING_INDEX].is_zero() {
+        return Err(FoldedSha256Error::PublicPrefixMismatch);
+    }
+    let digest = digest_from_state(&ivc_proof.z_i[..STATE_LEN]);
+    NovaFor::<FoldedSha256PublicBlocksFCircuit>::verify(vp, ivc_proof)?;
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::hasher::sha256;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    const HEADER: &[u8] = b"HDR1";
+
+    fn message() -> Vec<u8> {
+        let mut message = HEADER.to_vec();
+        message.extend(vec![9u8; 80]);
+        message
+    }
+
+    #[test]
+    fn test_public_prefix_f_circuit() {
+        let circuit = FoldedSha256PublicPrefixFCircuit::new(HEADER.len()).unwrap();
+
+        let mut z_i = public_prefix_initial_state(HEADER);
+        for (i, block) in BlockExternalInputs::<Fr, _>::new(message()).enumerate() {
+            let z_i1 = circuit.step_native(i, z_i.clone(), block.clone()).unwrap();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+            assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+            assert!(cs.is_satisfied().unwrap());
+
+            z_i = z_i1;
+        }
+        assert_eq!(digest_from_state(&z_i[..STATE_LEN]), sha256(&message()));
+    }
+
+    #[test]
+    fn test_first_block_must_start_with_the_prefix() {
+        let circuit = FoldedSha256PublicPrefixFCircuit::new(HEADER.len()).unwrap();
+        let z_0 = public_prefix_initial_state(b"HDR2");
+        let block = BlockExternalInputs::<Fr, _>::new(message()).next().unwrap();
+        assert!(circuit.step_native(0, z_0.clone(), block.clone()).is_err());
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let z_0Var = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_0)).unwrap();
+        let externalInputsVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+        circuit
+            .generate_step_constraints(cs.clone(), 0, z_0Var, externalInputsVar)
+            .unwrap();
+        assert!(!cs.is_satisfied().unwrap());
+    }
+
+    fn public_and_secret() -> (Vec<u8>, Vec<u8>) {
+        let prefix: Vec<u8> = (0..2 * BLOCK_LENGTH_BYTES).map(|i| i as u8).collect();
+        (prefix, b"the secret part of the message".to_vec())
+    }
+
+    #[test]
+    fn test_public_blocks_f_circuit() {
+        let (mut input, secret) = public_and_secret();
+        input.extend(&secret);
+        let circuit = FoldedSha256PublicBlocksFCircuit::new(poseidon_canonical_config()).unwrap();
+
+        let mut z_i = public_blocks_initial_state(2);
+        for (i, block) in BlockExternalInputs::<Fr, _>::new(input.clone()).enumerate() {
+            let z_i1 = circuit.step_native(i, z_i.clone(), block.clone()).unwrap();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+            assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+            assert!(cs.is_satisfied().unwrap());
+
+            z_i = z_i1;
+        }
+        assert_eq!(digest_from_state(&z_i[..STATE_LEN]), sha256(&input));
+        assert_eq!(
+            z_i[ACC_INDEX],
+            public_blocks_commitment(&poseidon_canonical_config(), &input[..128]).unwrap()
+        );
+        assert!(z_i[REMAINING_INDEX].is_zero());
+    }
+
+    #[test]
+    fn test_prove_with_public_blocks() {
+        let (prefix, secret) = public_and_secret();
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let prove = |secret: &[u8], rng: &mut ChaCha20Rng| {
+            let input = [&prefix[..], secret].concat();
+            prove_with_public_blocks(input, 2, &FoldConfig::default(), rng).unwrap()
+        };
+
+        let (folding_scheme, (_, vp)) = prove(&secret, &mut rng);
+        let digest =
+            verify_with_public_blocks(vp.clone(), folding_scheme.ivc_proof(), &prefix).unwrap();
+        assert_eq!(digest, sha256(&[&prefix[..], &secret[..]].concat()));
+
+        // the public inputs only depend on the secret through the digest
+        let (other, _) = prove(b"another secret", &mut rng);
+        assert_eq!(other.z_0, folding_scheme.z_0);
+        assert_eq!(other.z_i[STATE_LEN..], folding_scheme.z_i[STATE_LEN..]);
+
+        let mut wrong_prefix = prefix.clone();
+        wrong_prefix[0] ^= 1;
+        assert!(matches!(
+            verify_with_public_blocks(vp.clone(), folding_scheme.ivc_proof(), &wrong_prefix),
+            Err(FoldedSha256Error::PublicPrefixMismatch)
+        ));
+        assert!(matches!(
+            verify_with_public_blocks(vp, folding_scheme.ivc_proof(), &prefix[..64]),
+            Err(FoldedSha256Error::InvalidInitialState)
+        ));
+    }
+
+    #[test]
+    fn test_public_prefix_is_in_the_public_inputs() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, vp)) =
+            run_with_public_prefix(message(), HEADER.len(), &FoldConfig::default(), &mut rng)
+                .unwrap();
+
+        let ivc_proof = folding_scheme.ivc_proof();
+        assert_eq!(ivc_proof.z_0, public_prefix_initial_state(HEADER));
+        assert_eq!(
+            ivc_proof.z_i[STATE_LEN..STATE_LEN + HEADER.len()].to_vec(),
+            public_prefix_initial_state(HEADER)[STATE_LEN..STATE_LEN + HEADER.len()].to_vec()
+        );
+        assert_eq!(
+            digest_from_state(&ivc_proof.z_i[..STATE_LEN]),
+            sha256(&message())
+        );
+        assert!(NovaFor::<FoldedSha256PublicPrefixFCircuit>::verify(vp, ivc_proof).is_ok());
+    }
+}
@@ -0,0 +1,257 @@
+//! Recording and replay of the randomness of a fold, to reproduce a proof byte for byte.
+//!
+//! Given the same input, settings and randomness, the setup and the folding are deterministic:
+//! `record_fold` runs `main::run` with an rng that records every byte it draws into a
+//! `FoldTranscript`, and `replay_fold` runs it again with an rng serving those bytes back, e.g. to
+//! bisect a nondeterminism between two builds.
+//!
+//! A transcript holds the input and all the randomness of the setup and of the blinding of the
+//! proof: it is as sensitive as the input, and must not be reused to produce proofs for others.
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{run, NovaProverParam, NovaVerifierParam, N};
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{self, CryptoRng, RngCore};
+use std::num::NonZeroU32;
+use std::path::Path;
+use std::{fs, io};
+
+/// Input, settings and randomness of a recorded fold.
+#[derive(Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FoldTranscript {
+    pub input: Vec<u8>,
+    pub blocks_per_step: u64,
+    pub max_step_retries: u64,
+    /// Every byte drawn from the rng, in order.
+    pub randomness: Vec<u8>,
+}
+
+impl FoldTranscript {
+    /// The folding settings of the recorded fold. `max_blocks` only rejects inputs before any
+    /// randomness is drawn, so it is not recorded.
+    pub fn config(&self) -> FoldConfig {
+        FoldConfig {
+            blocks_per_step: self.blocks_per_step as usize,
+            max_step_retries: self.max_step_retries as usize,
+            ..Default::default()
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        self.serialize_compressed(&mut bytes)
+            .map_err(io::Error::other)?;
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::deserialize_compressed(&fs::read(path)?[..])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Rng drawing from `inner` and recording every byte it returns.
+pub struct RecordingRng<'a, R> {
+    inner: &'a mut R,
+    recorded: Vec<u8>,
+}
+
+impl<'a, R: RngCore> RecordingRng<'a, R> {
+    pub fn new(inner: &'a mut R) -> Self {
+        Self {
+            inner,
+            recorded: Vec::new(),
+        }
+    }
+
+    /// The bytes drawn so far.
+    pub fn into_recorded(self) -> Vec<u8> {
+        self.recorded
+    }
+}
+
+impl<R: RngCore> RngCore for RecordingRng<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest);
+        self.recorded.extend_from_slice(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.inner.try_fill_bytes(dest)?;
+        self.recorded.extend_from_slice(dest);
+        Ok(())
+    }
+}
+
+impl<R: CryptoRng> CryptoRng for RecordingRng<'_, R> {}
+
+/// Rng serving back recorded bytes, in order.
+///
+/// It is only as random as the recording. Drawing more bytes than recorded means the replayed
+/// fold diverged from the recorded one: `fill_bytes` panics, `try_fill_bytes` returns an error.
+pub struct ReplayRng<'a> {
+    recorded: &'a [u8],
+}
+
+impl<'a> ReplayRng<'a> {
+    pub fn new(recorded: &'a [u8]) -> Self {
+        Self { recorded }
+    }
+
+    /// Number of recorded bytes not drawn yet, 0 after a faithful replay.
+    pub fn remaining(&self) -> usize {
+        self.recorded.len()
+    }
+}
+
+impl RngCore for ReplayRng<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("the replayed fold draws more randomness than recorded")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        if dest.len() > self.recorded.len() {
+            return Err(rand::Error::from(
+                NonZeroU32::new(rand::Error::CUSTOM_START).unwrap(),
+            ));
+        }
+        let (drawn, rest) = self.recorded.split_at(dest.len());
+        dest.copy_from_slice(drawn);
+        self.recorded = rest;
+        Ok(())
+    }
+}
+
+impl CryptoRng for ReplayRng<'_> {}
+
+/// Runs `main::run` on `input`, recording its randomness, drawn from `rng`, into the returned
+/// transcript.
+pub fn record_fold<R: RngCore + CryptoRng>(
+    input: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam), FoldTranscript), FoldedSha256Error> {
+    let mut recording = RecordingRng::new(rng);
+    let (folding_scheme, nova_params) = run(input.clone(), config, &mut recording)?;
+    let transcript = FoldTranscript {
+        input,
+        blocks_per_step: config.blocks_per_step as u64,
+        max_step_retries: config.max_step_retries as u64,
+        randomness: recording.into_recorded(),
+    };
+    Ok((folding_scheme, nova_params, transcript))
+}
+
+/// Re-runs the fold recorded in `transcript`, producing the same parameters and folding scheme,
+/// hence byte-identical proofs.
+///
+/// Returns `FoldedSha256Error::ReplayDiverged` if the replayed fold does not consume all the
+/// recorded randomness: it did not draw the same bytes as the recorded one, so its proofs differ.
+pub fn replay_fold(
+    transcript: &FoldTranscript,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam)), FoldedSha256Error> {
+    let mut replay = ReplayRng::new(&transcript.randomness);
+    let folded = run(transcript.input.clone(), &transcript.config(), &mut replay)?;
+    if replay.remaining() != 0 {
+        return Err(FoldedSha256Error::ReplayDiverged {
+            unused: replay.remaining(),
+        });
+    }
+    Ok(folded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use folding_schemes::FoldingScheme;
+
+    fn serialized_ivc_proof(folding_scheme: &N) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        folding_scheme
+            .ivc_proof()
+            .serialize_compressed(&mut bytes)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_record_then_replay_is_byte_identical() {
+        // a non-deterministic rng: the replay must not depend on reseeding it
+        let (folding_scheme, (_, nova_vp), transcript) = record_fold(
+            b"abc".to_vec(),
+            &FoldConfig::default(),
+            &mut rand::rngs::OsRng,
+        )
+        .unwrap();
+        assert!(!transcript.randomness.is_empty());
+
+        let path = std::env::temp_dir().join("folded_sha256-transcript-test.bin");
+        transcript.save(&path).unwrap();
+        let loaded = FoldTranscript::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded, transcript);
+
+        let (replayed, (_, replayed_vp)) = replay_fold(&loaded).unwrap();
+        assert_eq!(
+            serialized_ivc_proof(&replayed),
+            serialized_ivc_proof(&folding_scheme)
+        );
+        let (mut vp_bytes, mut replayed_vp_bytes) = (Vec::new(), Vec::new());
+        nova_vp.serialize_compressed(&mut vp_bytes).unwrap();
+        replayed_vp
+            .serialize_compressed(&mut replayed_vp_bytes)
+            .unwrap();
+        assert_eq!(replayed_vp_bytes, vp_bytes);
+    }
+
+    #[test]
+    fn test_replay_rejects_unused_randomness() {
+        let (_, _, mut transcript) = record_fold(
+            b"abc".to_vec(),
+            &FoldConfig::default(),
+            &mut rand::rngs::OsRng,
+        )
+        .unwrap();
+        transcript.randomness.extend([0u8; 3]);
+        assert!(matches!(
+            replay_fold(&transcript),
+            Err(FoldedSha256Error::ReplayDiverged { unused: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_replay_rng_rejects_exhausted_transcript() {
+        let mut replay = ReplayRng::new(&[1, 2, 3]);
+        let mut bytes = [0u8; 2];
+        replay.try_fill_bytes(&mut bytes).unwrap();
+        assert_eq!(bytes, [1, 2]);
+        assert_eq!(replay.remaining(), 1);
+        assert!(replay.try_fill_bytes(&mut bytes).is_err());
+    }
+}
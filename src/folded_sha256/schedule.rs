@@ -0,0 +1,229 @@
+//! Message schedule of the SHA-2 compression functions, generic over the word type so that the
+//! same code expands the schedule natively (`u32`) and in-circuit (`UInt32`).
+//!
+//! The in-circuit expansion is where most of the constraints outside of the rounds are spent,
+//! see `profile::Phase::Schedule`, so it is kept apart from the rounds of
+//! `circuit::one_compression_round` to be optimized on its own.
+use ark_ff::PrimeField;
+use ark_r1cs_std::{uint32::UInt32, uint8::UInt8};
+use ark_relations::r1cs::SynthesisError;
+
+/// Word of a SHA-2 message schedule, along with the parameters of its σ functions.
+///
+/// The operations are those of the schedule recurrence. Only the additions can fail, when
+/// synthesized.
+pub trait ShaWord: Clone + Sized {
+    /// Byte type the words are loaded from.
+    type Byte: Clone;
+
+    /// Number of bytes of a word.
+    const BYTES: usize;
+    /// Number of words of the expanded schedule, i.e. of rounds.
+    const SCHEDULE_LEN: usize;
+    /// Rotations and shift of σ0.
+    const SMALL_SIGMA0: (usize, usize, usize);
+    /// Rotations and shift of σ1.
+    const SMALL_SIGMA1: (usize, usize, usize);
+
+    /// Big-endian word of `Self::BYTES` bytes.
+    fn from_bytes_be(bytes: &[Self::Byte]) -> Result<Self, SynthesisError>;
+    fn rotr(&self, n: usize) -> Self;
+    fn shr(&self, n: usize) -> Self;
+    fn xor(&self, other: &Self) -> Self;
+    /// Sum of `words` modulo `2^(8 * Self::BYTES)`.
+    fn wrapping_add_many(words: &[Self]) -> Result<Self, SynthesisError>;
+}
+
+impl ShaWord for u32 {
+    type Byte = u8;
+
+    const BYTES: usize = 4;
+    const SCHEDULE_LEN: usize = 64;
+    const SMALL_SIGMA0: (usize, usize, usize) = (7, 18, 3);
+    const SMALL_SIGMA1: (usize, usize, usize) = (17, 19, 10);
+
+    fn from_bytes_be(bytes: &[u8]) -> Result<Self, SynthesisError> {
+        Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+    fn rotr(&self, n: usize) -> Self {
+        self.rotate_right(n as u32)
+    }
+    fn shr(&self, n: usize) -> Self {
+        self >> n
+    }
+    fn xor(&self, other: &Self) -> Self {
+        self ^ other
+    }
+    fn wrapping_add_many(words: &[Self]) -> Result<Self, SynthesisError> {
+        Ok(words.iter().fold(0u32, |sum, x| sum.wrapping_add(*x)))
+    }
+}
+
+impl<F: PrimeField> ShaWord for UInt32<F> {
+    type Byte = UInt8<F>;
+
+    const BYTES: usize = 4;
+    const SCHEDULE_LEN: usize = 64;
+    const SMALL_SIGMA0: (usize, usize, usize) = (7, 18, 3);
+    const SMALL_SIGMA1: (usize, usize, usize) = (17, 19, 10);
+
+    fn from_bytes_be(bytes: &[UInt8<F>]) -> Result<Self, SynthesisError> {
+        UInt32::from_bytes_be(bytes)
+    }
+    fn rotr(&self, n: usize) -> Self {
+        self.rotate_right(n)
+    }
+    fn shr(&self, n: usize) -> Self {
+        self >> n
+    }
+    fn xor(&self, other: &Self) -> Self {
+        self ^ other
+    }
+    fn wrapping_add_many(words: &[Self]) -> Result<Self, SynthesisError> {
+        UInt32::wrapping_add_many(words)
+    }
+}
+
+pub fn small_sigma0<W: ShaWord>(x: &W) -> W {
+    let (r1, r2, s) = W::SMALL_SIGMA0;
+    x.rotr(r1).xor(&x.rotr(r2)).xor(&x.shr(s))
+}
+
+pub fn small_sigma1<W: ShaWord>(x: &W) -> W {
+    let (r1, r2, s) = W::SMALL_SIGMA1;
+    x.rotr(r1).xor(&x.rotr(r2)).xor(&x.shr(s))
+}
+
+/// Expands the 16 words of a block into the `W::SCHEDULE_LEN` words of its message schedule,
+/// `w[i] = w[i-16] + σ0(w[i-15]) + w[i-7] + σ1(w[i-2])`.
+pub fn expand_schedule<W: ShaWord>(block_words: &[W]) -> Result<Vec<W>, SynthesisError> {
+    assert_eq!(block_words.len(), 16);
+
+    let mut w = block_words.to_vec();
+    for i in 16..W::SCHEDULE_LEN {
+        w.push(W::wrapping_add_many(&[
+            w[i - 16].clone(),
+            small_sigma0(&w[i - 15]),
+            w[i - 7].clone(),
+            small_sigma1(&w[i - 2]),
+        ])?);
+    }
+    Ok(w)
+}
+
+/// Message schedule of the block `data`, loaded as 16 big-endian words.
+pub fn message_schedule<W: ShaWord>(data: &[W::Byte]) -> Result<Vec<W>, SynthesisError> {
+    assert_eq!(data.len(), 16 * W::BYTES);
+
+    let block_words = data
+        .chunks(W::BYTES)
+        .map(W::from_bytes_be)
+        .collect::<Result<Vec<W>, SynthesisError>>()?;
+    expand_schedule(&block_words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::utils::sha256_msg_block_sequence;
+    use ark_bn254::Fr;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    fn block() -> Vec<u8> {
+        (0..64).map(|i| (i * 37 + 11) as u8).collect()
+    }
+
+    // W_16 to W_63 of the one-block message "abc", FIPS 180-2, appendix B.1
+    const ABC_EXPANDED_WORDS: [u32; 48] = [
+        0x61626380, 0x000f0000, 0x7da86405, 0x600003c6, 0x3e9d7b78, 0x0183fc00, 0x12dcbfdb,
+        0xe2e2c38e, 0xc8215c1a, 0xb73679a2, 0xe5bc3909, 0x32663c5b, 0x9d209d67, 0xec8726cb,
+        0x702138a4, 0xd3b7973b, 0x93f5997f, 0x3b68ba73, 0xaff4ffc1, 0xf10a5c62, 0x0a8b3996,
+        0x72af830a, 0x9409e33e, 0x24641522, 0x9f47bf94, 0xf0a64f5a, 0x3e246a79, 0x27333ba3,
+        0x0c4763f2, 0x840abf27, 0x7a290d5d, 0x065c43da, 0xfb3e89cb, 0xcc7617db, 0xb9e66c34,
+        0xa9993667, 0x84badedd, 0xc21462bc, 0x1487472c, 0xb20f7a99, 0xef57b9cd, 0xebe6b238,
+        0x9fe3095e, 0x78bc8d4b, 0xa43fcf15, 0x668b2ff8, 0xeeaba2cc, 0x12b1edeb,
+    ];
+
+    #[test]
+    fn test_native_schedule() {
+        let block = sha256_msg_block_sequence(b"abc".to_vec())[0];
+        let w = message_schedule::<u32>(&block).unwrap();
+        assert_eq!(w.len(), 64);
+        // the first 16 words are the block itself
+        assert_eq!(w[0], 0x61626380);
+        assert!(w[1..15].iter().all(|&x| x == 0));
+        assert_eq!(w[15], 24);
+        assert_eq!(w[16..], ABC_EXPANDED_WORDS);
+
+        // the σ functions of FIPS 180-4, section 4.1.2
+        let x = 0x12345678u32;
+        assert_eq!(
+            small_sigma0(&x),
+            x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3)
+        );
+        assert_eq!(
+            small_sigma1(&x),
+            x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10)
+        );
+    }
+
+    #[test]
+    fn test_circuit_schedule_matches_native() {
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let data: Vec<UInt8<Fr>> = block()
+            .iter()
+            .map(|&x| UInt8::new_witness(cs.clone(), || Ok(x)).unwrap())
+            .collect();
+
+        let w = message_schedule::<UInt32<Fr>>(&data).unwrap();
+        assert_eq!(w.len(), 64);
+        assert_eq!(
+            w.value().unwrap(),
+            message_schedule::<u32>(&block()).unwrap()
+        );
+        assert!(cs.is_satisfied().unwrap());
+
+        // a constant block expands to constants, without any constraint
+        let constant_data = UInt8::<Fr>::constant_vec(&block());
+        let w = message_schedule::<UInt32<Fr>>(&constant_data).unwrap();
+        assert_eq!(
+            w.value().unwrap(),
+            message_schedule::<u32>(&block()).unwrap()
+        );
+        assert!(w.cs().is_none());
+    }
+
+    #[test]
+    fn test_rotations_and_shifts_match_u32() {
+        use ark_std::rand::{RngCore, SeedableRng};
+        use rand_chacha::ChaCha20Rng;
+
+        // the rotations of σ0, σ1 (schedule), Σ0 and Σ1 (rounds), and the shifts of σ0 and σ1
+        const ROTATIONS: [usize; 10] = [7, 18, 17, 19, 2, 13, 22, 6, 11, 25];
+        const SHIFTS: [usize; 2] = [3, 10];
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let values: Vec<u32> = [0, 1, 0x8000_0000, u32::MAX]
+            .into_iter()
+            .chain((0..32).map(|_| rng.next_u32()))
+            .collect();
+
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        for x in values {
+            let x_var = UInt32::new_witness(cs.clone(), || Ok(x)).unwrap();
+            for n in ROTATIONS {
+                assert_eq!(
+                    x_var.rotate_right(n).value().unwrap(),
+                    x.rotate_right(n as u32)
+                );
+                assert_eq!(x_var.rotr(n).value().unwrap(), x.rotr(n));
+            }
+            for n in SHIFTS {
+                assert_eq!((&x_var >> n).value().unwrap(), x >> n);
+                assert_eq!(x_var.shr(n).value().unwrap(), x.shr(n));
+            }
+        }
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
@@ -0,0 +1,199 @@
+//! Folding of a message split between two parties at a block boundary.
+//!
+//! Party A folds its prefix without padding with `fold_prefix`, and hands the resulting
+//! `Midstate` over. Party B finishes the hash with `finalize_from_midstate`, starting its own
+//! folding from the midstate as `z_0` and padding the suffix as the end of the whole message.
+//! Neither party learns the other's half of the message: they only share the midstate.
+//!
+//! A fixed prefix block, such as a domain separation context, is the same situation with a public
+//! prefix: `hash_with_context` compresses it natively and only folds the message.
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{
+    bigint_to_u32, fold, FoldedSha256FCircuit, NovaProverParam, NovaVerifierParam, H, N,
+};
+use crate::folded_sha256::utils::{compress_block, BlockExternalInputs, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::Fr;
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::frontend::FCircuit;
+
+/// State of the compression function after the first `prefix_len` bytes of a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Midstate {
+    pub state: [u32; 8],
+    pub prefix_len: u64,
+}
+
+impl Midstate {
+    /// Reads the midstate from the final state `z_i` of the folding of a `prefix_len` bytes
+    /// prefix.
+    pub fn from_state(z_i: &[Fr], prefix_len: u64) -> Self {
+        let mut state = [0u32; 8];
+        for (word, x) in state.iter_mut().zip(z_i) {
+            *word = bigint_to_u32(*x);
+        }
+        Self { state, prefix_len }
+    }
+
+    /// Midstate after the 64-byte context block `ctx`, i.e. the initial state of the folding of
+    /// `SHA256(ctx || msg)`.
+    pub fn with_context_block(ctx: [u8; BLOCK_LENGTH_BYTES]) -> Self {
+        Self {
+            state: compress_block(&H, &ctx),
+            prefix_len: BLOCK_LENGTH_BYTES as u64,
+        }
+    }
+
+    fn z_0(&self) -> Vec<Fr> {
+        self.state.iter().map(|&x| Fr::from(x)).collect()
+    }
+}
+
+/// Folds the `prefix` of a message, which must be made of whole blocks, without any padding.
+///
+/// Returns the folding scheme, whose final state is the midstate (see `Midstate::from_state`),
+/// and its parameters.
+pub fn fold_prefix<R: RngCore + CryptoRng>(
+    prefix: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam)), FoldedSha256Error> {
+    if prefix.is_empty() || prefix.len() % BLOCK_LENGTH_BYTES != 0 {
+        return Err(FoldedSha256Error::UnalignedSplit {
+            prefix_len: prefix.len(),
+        });
+    }
+    config.check_num_blocks(prefix.len() / BLOCK_LENGTH_BYTES)?;
+
+    let initial_state: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    let blocks: Vec<Vec<Fr>> = prefix
+        .chunks(BLOCK_LENGTH_BYTES)
+        .map(|block| block.iter().map(|&x| Fr::from(x)).collect())
+        .collect();
+
+    fold(
+        FoldedSha256FCircuit::<Fr>::sha256(),
+        initial_state,
+        blocks,
+        config,
+        rng,
+    )
+}
+
+/// Folds the `remaining` bytes of a message from the `midstate` left by `fold_prefix`, as `z_0`,
+/// padding them as the end of a message of `midstate.prefix_len + remaining.len()` bytes. The
+/// final state holds the digest of the whole message.
+pub fn finalize_from_midstate<R: RngCore + CryptoRng>(
+    midstate: &Midstate,
+    remaining: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam)), FoldedSha256Error> {
+    if midstate.prefix_len % BLOCK_LENGTH_BYTES as u64 != 0 {
+        return Err(FoldedSha256Error::UnalignedSplit {
+            prefix_len: midstate.prefix_len as usize,
+        });
+    }
+    config.check_input_len(remaining.len())?;
+    let blocks = BlockExternalInputs::<Fr, _>::with_prefix_len(remaining, midstate.prefix_len)
+        .map_err(|_| FoldedSha256Error::MessageTooLong)?;
+
+    fold(
+        FoldedSha256FCircuit::<Fr>::sha256(),
+        midstate.z_0(),
+        blocks,
+        config,
+        rng,
+    )
+}
+
+/// Folds `SHA256(ctx || msg)`, where the context block `ctx` is compressed natively into the
+/// initial state: only the blocks of `msg` are folded. The verifier must check `z_0` is the
+/// midstate of the expected context, see `Midstate::with_context_block`.
+pub fn hash_with_context<R: RngCore + CryptoRng>(
+    ctx: [u8; BLOCK_LENGTH_BYTES],
+    msg: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<(N, (NovaProverParam, NovaVerifierParam)), FoldedSha256Error> {
+    finalize_from_midstate(&Midstate::with_context_block(ctx), msg, config, rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::digest::digest_from_state;
+    use crate::folded_sha256::hasher::sha256;
+    use crate::folded_sha256::main::run;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn message() -> Vec<u8> {
+        (0..128).map(|i| b'a' + (i % 26) as u8).collect()
+    }
+
+    #[test]
+    fn test_split_message_matches_single_party_digest() {
+        let message = message();
+        let (prefix, remaining) = message.split_at(64);
+
+        let mut rng_a = ChaCha20Rng::seed_from_u64(1);
+        let (folding_scheme_a, _) =
+            fold_prefix(prefix.to_vec(), &FoldConfig::default(), &mut rng_a).unwrap();
+        let midstate = Midstate::from_state(&folding_scheme_a.z_i, prefix.len() as u64);
+
+        let mut rng_b = ChaCha20Rng::seed_from_u64(2);
+        let (folding_scheme_b, _) = finalize_from_midstate(
+            &midstate,
+            remaining.to_vec(),
+            &FoldConfig::default(),
+            &mut rng_b,
+        )
+        .unwrap();
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (single_party, _) = run(message.clone(), &FoldConfig::default(), &mut rng).unwrap();
+
+        assert_eq!(digest_from_state(&folding_scheme_b.z_i), sha256(&message));
+        assert_eq!(folding_scheme_b.z_i, single_party.z_i);
+    }
+
+    #[test]
+    fn test_hash_with_context() {
+        let ctx: [u8; BLOCK_LENGTH_BYTES] = core::array::from_fn(|i| (i * 3 + 1) as u8);
+        let msg: Vec<u8> = (0..100).map(|i| (i * 7) as u8).collect();
+        let mut ctx_msg = ctx.to_vec();
+        ctx_msg.extend(&msg);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, _) =
+            hash_with_context(ctx, msg.clone(), &FoldConfig::default(), &mut rng).unwrap();
+
+        assert_eq!(digest_from_state(&folding_scheme.z_i), sha256(&ctx_msg));
+        // the context block is not folded
+        assert_eq!(
+            folding_scheme.i,
+            Fr::from(FoldConfig::num_blocks(msg.len()) as u64)
+        );
+        assert_eq!(folding_scheme.z_0, Midstate::with_context_block(ctx).z_0());
+    }
+
+    #[test]
+    fn test_unaligned_split_is_rejected() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        assert!(matches!(
+            fold_prefix(vec![0u8; 100], &FoldConfig::default(), &mut rng),
+            Err(FoldedSha256Error::UnalignedSplit { prefix_len: 100 })
+        ));
+
+        let midstate = Midstate {
+            state: H,
+            prefix_len: 100,
+        };
+        assert!(matches!(
+            finalize_from_midstate(&midstate, vec![], &FoldConfig::default(), &mut rng),
+            Err(FoldedSha256Error::UnalignedSplit { prefix_len: 100 })
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+//! File cache of the KZG SRS, expanded on demand.
+//!
+//! `preprocess` generates a fresh SRS from the rng on every run, or rejects a supplied one with
+//! too few powers. `SrsCache` keeps the SRS in a file instead and regenerates it, at the required
+//! size, whenever the cached one is missing, unreadable or too small for the circuit, e.g. after
+//! increasing `blocks_per_step`. A generated SRS is only as trusted as the rng it comes from.
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{preprocess, KZGProverParam, KZGVerifierParam, NovaParamsFor};
+
+use ark_bn254::{constraints::GVar, Bn254, Fr, G1Projective as Projective};
+use ark_grumpkin::{constraints::GVar as GVar2, Projective as Projective2};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::commitment::{kzg::KZG, CommitmentScheme};
+use folding_schemes::folding::nova::get_cs_params_len;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::transcript::poseidon::poseidon_canonical_config;
+use folding_schemes::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Number of KZG powers needed to commit to the witnesses of the Nova augmented circuit of
+/// `F_circuit`.
+pub fn required_srs_degree<FC: FCircuit<Fr>>(F_circuit: FC) -> Result<usize, FoldedSha256Error> {
+    let (required, _) = get_cs_params_len::<Projective, GVar, Projective2, GVar2, FC>(
+        &poseidon_canonical_config::<Fr>(),
+        F_circuit,
+    )
+    .map_err(FoldedSha256Error::Setup)?;
+    Ok(required)
+}
+
+/// A KZG SRS stored in the file at `path`.
+#[derive(Clone, Debug)]
+pub struct SrsCache {
+    path: PathBuf,
+}
+
+impl SrsCache {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns an SRS with at least `min_degree` powers: the cached one if it is large enough,
+    /// otherwise one generated from `rng` with `min_degree` powers, which replaces the cache.
+    ///
+    /// A cache that cannot be read or deserialized is regenerated like an undersized one, so that
+    /// a retry after an interrupted write succeeds. Only failing to write the new SRS is an error.
+    pub fn ensure_srs<R: RngCore + CryptoRng>(
+        &self,
+        min_degree: usize,
+        rng: &mut R,
+    ) -> Result<(KZGProverParam, KZGVerifierParam), FoldedSha256Error> {
+        if let Some(srs) = self.load() {
+            if srs.0.powers_of_g.len() >= min_degree {
+                return Ok(srs);
+            }
+        }
+
+        let srs = KZG::<'static, Bn254>::setup(&mut *rng, min_degree)
+            .map_err(FoldedSha256Error::Setup)?;
+        let mut bytes = Vec::new();
+        srs.serialize_compressed(&mut bytes)?;
+        fs::write(&self.path, bytes).map_err(|e| {
+            FoldedSha256Error::Setup(Error::Other(format!(
+                "cannot write the KZG SRS cache {}: {}",
+                self.path.display(),
+                e
+            )))
+        })?;
+        Ok(srs)
+    }
+
+    fn load(&self) -> Option<(KZGProverParam, KZGVerifierParam)> {
+        let bytes = fs::read(&self.path).ok()?;
+        <(KZGProverParam, KZGVerifierParam)>::deserialize_compressed(&bytes[..]).ok()
+    }
+}
+
+/// Runs `preprocess` for `F_circuit` with the SRS of `cache`, expanded to the size of the circuit
+/// if needed.
+pub fn preprocess_with_srs_cache<FC: FCircuit<Fr>, R: RngCore + CryptoRng>(
+    F_circuit: FC,
+    cache: &SrsCache,
+    rng: &mut R,
+) -> Result<NovaParamsFor<FC>, FoldedSha256Error> {
+    let srs = cache.ensure_srs(required_srs_degree(F_circuit.clone())?, rng)?;
+    preprocess(F_circuit, Some(srs), rng)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn cache(name: &str) -> SrsCache {
+        let cache = SrsCache::new(std::env::temp_dir().join(name));
+        let _ = fs::remove_file(cache.path());
+        cache
+    }
+
+    #[test]
+    fn test_ensure_srs_expands_the_cache() {
+        let cache = cache("folded_sha256-srs-expand-test.bin");
+
+        let (small, _) = cache
+            .ensure_srs(1 << 4, &mut ChaCha20Rng::seed_from_u64(0))
+            .unwrap();
+        assert!(small.powers_of_g.len() >= 1 << 4);
+
+        // a smaller or equal degree is served from the cache, whatever the rng
+        let (cached, _) = cache
+            .ensure_srs(1 << 3, &mut ChaCha20Rng::seed_from_u64(1))
+            .unwrap();
+        assert_eq!(cached.powers_of_g, small.powers_of_g);
+
+        // a larger degree regenerates it, and the cache now holds the larger SRS
+        let (large, _) = cache
+            .ensure_srs(1 << 5, &mut ChaCha20Rng::seed_from_u64(1))
+            .unwrap();
+        assert!(large.powers_of_g.len() >= 1 << 5);
+        assert_ne!(large.powers_of_g[1], small.powers_of_g[1]);
+        let (reloaded, _) = cache
+            .ensure_srs(1 << 5, &mut ChaCha20Rng::seed_from_u64(2))
+            .unwrap();
+        assert_eq!(reloaded.powers_of_g, large.powers_of_g);
+
+        fs::remove_file(cache.path()).unwrap();
+    }
+
+    #[test]
+    fn test_ensure_srs_regenerates_a_corrupted_cache() {
+        let cache = cache("folded_sha256-srs-corrupted-test.bin");
+        fs::write(cache.path(), b"not an SRS").unwrap();
+
+        let (pp, _) = cache
+            .ensure_srs(1 << 4, &mut ChaCha20Rng::seed_from_u64(0))
+            .unwrap();
+        assert!(pp.powers_of_g.len() >= 1 << 4);
+        assert!(SrsCache::new(cache.path()).load().is_some());
+
+        fs::remove_file(cache.path()).unwrap();
+    }
+}
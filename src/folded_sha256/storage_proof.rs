@@ -0,0 +1,216 @@
+//! Storage proofs: one IVC proof that a file of `len` bytes identified by `file_id` hashes to
+//! `digest`.
+//!
+//! The folding carries the SHA-256 state, the length field of the last block
+//! (`message_len::FoldedSha256LengthFCircuit`) and the file ID, so the public inputs of the proof,
+//! `z_0` and `z_i`, bind the three of them. The file ID is copied unchanged from step to step: it
+//! is bound to the proof by `z_0` and only needs to be a field element, e.g. a hash of a path or
+//! a database key reduced modulo `p`.
+#![allow(non_snake_case)]
+use crate::folded_sha256::config::FoldConfig;
+use crate::folded_sha256::digest::{digest_from_state, Digest};
+use crate::folded_sha256::error::FoldedSha256Error;
+use crate::folded_sha256::main::{bigint_to_u32, fold, NovaFor, NovaParamsFor, H, STATE_LEN};
+use crate::folded_sha256::message_len::{message_len_from_state, FoldedSha256LengthFCircuit};
+use crate::folded_sha256::utils::{BlockExternalInputs, BLOCK_LENGTH_BYTES};
+
+use ark_bn254::{Fr, G1Projective as Projective};
+use ark_grumpkin::Projective as Projective2;
+use ark_r1cs_std::fields::fp::FpVar;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::rand::{CryptoRng, RngCore};
+use folding_schemes::folding::nova::IVCProof;
+use folding_schemes::frontend::FCircuit;
+use folding_schemes::{Error, FoldingScheme};
+
+/// Index of the length in the state.
+const LEN_INDEX: usize = STATE_LEN;
+/// Index of the file ID in the state.
+const FILE_ID_INDEX: usize = STATE_LEN + 1;
+
+/// SHA-256 folding circuit with the state `z_i = [H_0, ..., H_7, len_i, file_id]`, `len_i` being
+/// as in `FoldedSha256LengthFCircuit` and `file_id` constant.
+#[derive(Clone, Copy, Debug)]
+pub struct FoldedSha256FileFCircuit {
+    length: FoldedSha256LengthFCircuit<Fr>,
+}
+
+impl FCircuit<Fr> for FoldedSha256FileFCircuit {
+    type Params = ();
+
+    fn new(params: Self::Params) -> Result<Self, Error> {
+        Ok(Self {
+            length: FoldedSha256LengthFCircuit::<Fr>::new(params)?,
+        })
+    }
+
+    fn state_len(&self) -> usize {
+        STATE_LEN + 2
+    }
+    fn external_inputs_len(&self) -> usize {
+        BLOCK_LENGTH_BYTES
+    }
+
+    fn step_native(
+        &self,
+        i: usize,
+        z_i: Vec<Fr>,
+        external_inputs: Vec<Fr>,
+    ) -> Result<Vec<Fr>, Error> {
+        let mut z_i1 =
+            self.length
+                .step_native(i, z_i[..FILE_ID_INDEX].to_vec(), external_inputs)?;
+        z_i1.push(z_i[FILE_ID_INDEX]);
+        Ok(z_i1)
+    }
+
+    fn generate_step_constraints(
+        &self,
+        cs: ConstraintSystemRef<Fr>,
+        i: usize,
+        z_i: Vec<FpVar<Fr>>,
+        external_inputs: Vec<FpVar<Fr>>,
+    ) -> Result<Vec<FpVar<Fr>>, SynthesisError> {
+        let mut z_i1 = self.length.generate_step_constraints(
+            cs,
+            i,
+            z_i[..FILE_ID_INDEX].to_vec(),
+            external_inputs,
+        )?;
+        z_i1.push(z_i[FILE_ID_INDEX].clone());
+        Ok(z_i1)
+    }
+}
+
+/// Public statement of a storage proof.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileStatement {
+    pub file_id: Fr,
+    /// Length of the file in bytes.
+    pub len: u64,
+    pub digest: Digest,
+}
+
+/// The initial state of the folding of the file `file_id`.
+pub fn file_initial_state(file_id: Fr) -> Vec<Fr> {
+    let mut z_0: Vec<Fr> = H.iter().map(|&x| Fr::from(x)).collect();
+    z_0.push(Fr::from(0u64));
+    z_0.push(file_id);
+    z_0
+}
+
+/// Folds the SHA-256 compression of `bytes`, binding its length and `file_id` to the proof. See
+/// `verify_file` for the verification.
+pub fn prove_file<R: RngCore + CryptoRng>(
+    file_id: Fr,
+    bytes: Vec<u8>,
+    config: &FoldConfig,
+    rng: &mut R,
+) -> Result<
+    (
+        NovaFor<FoldedSha256FileFCircuit>,
+        NovaParamsFor<FoldedSha256FileFCircuit>,
+    ),
+    FoldedSha256Error,
+> {
+    config.check_input_len(bytes.len())?;
+
+    fold(
+        FoldedSha256FileFCircuit::new(())?,
+        file_initial_state(file_id),
+        BlockExternalInputs::<Fr, _>::new(bytes),
+        config,
+        rng,
+    )
+}
+
+/// Verifies the IVC proof produced by `prove_file` and returns its statement, to be compared by
+/// the caller to the expected file ID, length and digest.
+///
+/// Returns `FoldedSha256Error::InvalidInitialState` if the proof does not start from
+/// `file_initial_state`, and `FoldedSha256Error::InvalidMessageLength` if the length is not
+/// consistent with the number of folded blocks.
+pub fn verify_file(
+    vp: <NovaFor<FoldedSha256FileFCircuit> as FoldingScheme<
+        Projective,
+        Projective2,
+        FoldedSha256FileFCircuit,
+    >>::VerifierParam,
+    ivc_proof: IVCProof<Projective, Projective2>,
+) -> Result<FileStatement, FoldedSha256Error> {
+    let file_id = ivc_proof.z_0[FILE_ID_INDEX];
+    if ivc_proof.z_0 != file_initial_state(file_id) {
+        return Err(FoldedSha256Error::InvalidInitialState);
+    }
+    let num_steps = bigint_to_u32(ivc_proof.i) as usize;
+    let z_i = ivc_proof.z_i.clone();
+    NovaFor::<FoldedSha256FileFCircuit>::verify(vp, ivc_proof)?;
+
+    Ok(FileStatement {
+        file_id: z_i[FILE_ID_INDEX],
+        len: message_len_from_state(&z_i[..=LEN_INDEX], num_steps)?,
+        digest: digest_from_state(&z_i[..STATE_LEN]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folded_sha256::hasher::sha256;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_file_f_circuit() {
+        let circuit = FoldedSha256FileFCircuit::new(()).unwrap();
+        let file_id = Fr::from(42u64);
+
+        let mut z_i = file_initial_state(file_id);
+        for (i, block) in BlockExternalInputs::<Fr, _>::new(vec![3u8; 70]).enumerate() {
+            let z_i1 = circuit.step_native(i, z_i.clone(), block.clone()).unwrap();
+
+            let cs = ConstraintSystem::<Fr>::new_ref();
+            let z_iVar = Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(z_i)).unwrap();
+            let externalInputsVar =
+                Vec::<FpVar<Fr>>::new_witness(cs.clone(), || Ok(block)).unwrap();
+            let computed_z_i1Var = circuit
+                .generate_step_constraints(cs.clone(), i, z_iVar, externalInputsVar)
+                .unwrap();
+            assert_eq!(computed_z_i1Var.value().unwrap(), z_i1);
+            assert!(cs.is_satisfied().unwrap());
+
+            z_i = z_i1;
+        }
+        assert_eq!(z_i[FILE_ID_INDEX], file_id);
+        assert_eq!(z_i[LEN_INDEX], Fr::from(70u64 * 8));
+        assert_eq!(digest_from_state(&z_i), sha256(&[3u8; 70]));
+    }
+
+    // slow: runs the Nova setup and folding, use `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_prove_file_exposes_its_statement() {
+        let bytes = vec![5u8; 100];
+        let file_id = Fr::from(0xf11eu64);
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let (folding_scheme, (_, vp)) =
+            prove_file(file_id, bytes.clone(), &FoldConfig::default(), &mut rng).unwrap();
+
+        let statement = verify_file(vp.clone(), folding_scheme.ivc_proof()).unwrap();
+        assert_eq!(
+            statement,
+            FileStatement {
+                file_id,
+                len: 100,
+                digest: sha256(&bytes),
+            }
+        );
+
+        // the proof cannot be claimed for another file ID
+        let mut ivc_proof = folding_scheme.ivc_proof();
+        ivc_proof.z_0[FILE_ID_INDEX] = Fr::from(1u64);
+        assert!(verify_file(vp, ivc_proof).is_err());
+    }
+}
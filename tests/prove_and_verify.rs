@@ -0,0 +1,16 @@
+#![cfg(feature = "prover")]
+use folded_sha256::folded_sha256::hasher::sha256;
+use folded_sha256::folded_sha256::main::prove_and_verify;
+
+use ark_std::rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+// slow: runs the full Decider (Groth16) setup, use `cargo test -- --ignored`
+#[test]
+#[ignore]
+fn test_prove_and_verify_abc() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let (digest, verified) = prove_and_verify(b"abc", &mut rng).unwrap();
+    assert!(verified);
+    assert_eq!(digest, sha256(b"abc"));
+}